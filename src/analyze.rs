@@ -1,89 +1,739 @@
-use crate::db::Database;
+use crate::config::FileThresholds;
+use crate::coverage::covered_lines;
+use crate::db::{compute_patch_hash, generate_diff, Database};
 use crate::error::{MutationError, Result};
-use crate::report::generate_report;
+use crate::git_changes::get_commit_hash;
+use crate::manifest::{Manifest, ManifestEntry};
+use crate::mutation::{MUTANT_EXTENSIONS, PATCH_EXTENSION};
+use crate::progress::mutant_bar;
+use crate::report::{
+    annotate_source, changed_line, generate_junit_report, generate_report, get_git_hash,
+    save_reports, ReportData, ReportFormat, ReportMode,
+};
+use log::{debug, error, info, warn};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tempfile::NamedTempFile;
 use tokio::process::Command as TokioCommand;
 use tokio::time::timeout;
 use walkdir::WalkDir;
 
+/// Options controlling how mutants are analyzed.
+#[derive(Debug, Clone)]
+pub struct AnalysisOptions {
+    /// Command to test the mutants (derived from the target file when `None`).
+    pub command: Option<String>,
+    /// Number of jobs used to compile Bitcoin Core (0 = default).
+    pub jobs: u32,
+    /// Timeout per mutant in seconds.
+    pub timeout_secs: u64,
+    /// Time the unmutated test once per folder and use its duration times this
+    /// factor as the per-mutant timeout instead of `timeout_secs`.
+    pub timeout_factor: Option<f64>,
+    /// Analyze the folder even if the tests fail on the unmutated target.
+    pub skip_baseline_check: bool,
+    /// Show a progress bar with the survival rate and an ETA while analyzing.
+    pub progress: bool,
+    /// Command prefix that runs the build and test commands in a sandbox; it is
+    /// followed by `sh -c '<command>'`.
+    pub sandbox: Option<String>,
+    /// Keep analyzing the remaining folders when one fails, and report the
+    /// failures at the end.
+    pub keep_going: bool,
+    /// No mutant is tested after this instant; the remaining ones are skipped.
+    pub deadline: Option<Instant>,
+    /// Covered lines per file; mutants changing an uncovered line are skipped.
+    pub coverage: Option<HashMap<String, Vec<usize>>>,
+    /// Reuse the results of mutants with the same patch from earlier commits.
+    pub incremental: bool,
+    /// Maximum acceptable survival rate before terminating early.
+    pub survival_threshold: f64,
+    /// Survival thresholds of the target files matching globs, overriding
+    /// `survival_threshold`.
+    pub file_survival_thresholds: FileThresholds,
+    /// SQLite database to read mutants from, or to cache results in.
+    pub sqlite_path: Option<PathBuf>,
+    /// Run ID to analyze from the SQLite database.
+    pub run_id: Option<i64>,
+    /// Only analyze mutants for this file path (database mode).
+    pub file_path: Option<String>,
+    /// Only analyze mutants that survived a previous run (database mode).
+    pub survivors_only: bool,
+    /// Randomize the mutant order: `Some(None)` uses `seed`, or picks a random one.
+    pub shuffle: Option<Option<u64>>,
+    /// Seed for all randomness, from `--seed`.
+    pub seed: Option<u64>,
+    /// List what would be analyzed without building or testing anything.
+    pub dry_run: bool,
+    /// Only analyze mutants whose index falls in one of these ranges.
+    pub only: Option<Vec<RangeInclusive<usize>>>,
+    /// Format of the report written after the analysis.
+    pub format: ReportFormat,
+    /// Where the report is written (`-` for stdout); defaults to the format's file.
+    pub report_path: Option<PathBuf>,
+    /// Whether the JSON report replaces or extends an existing one.
+    pub report_mode: ReportMode,
+    /// Write the target file annotated with its surviving mutants to each folder.
+    pub annotate: bool,
+    /// Command that builds a mutant before `command` runs, so build failures
+    /// are told apart from test failures.
+    pub build_command: Option<String>,
+    /// Leave mutants that fail to build out of the score instead of counting
+    /// them as killed.
+    pub exclude_build_failures: bool,
+    /// Stop at the first mutant that fails to build and leave it applied to
+    /// its target, failing with [`MutationError::MutantKept`].
+    pub keep_on_failure: bool,
+    /// Analyze targets with uncommitted changes, which restoring them discards,
+    /// instead of failing with [`MutationError::DirtyTarget`].
+    pub force: bool,
+    /// Also write the per-operator effectiveness as JSON to this path.
+    pub operator_stats_path: Option<PathBuf>,
+    /// Directory searched for `muts*` folders when no folder is given.
+    pub root: PathBuf,
+    /// Also find `muts*` folders nested below `root`'s subdirectories.
+    pub recursive: bool,
+}
+
+impl AnalysisOptions {
+    fn report_destination(&self) -> PathBuf {
+        self.report_path
+            .clone()
+            .unwrap_or_else(|| self.format.default_path().to_path_buf())
+    }
+
+    /// Whether a mutant with `status` counts as killed (`Some(true)`), survived
+    /// (`Some(false)`) or is left out of the score (`None`). Both folder and
+    /// database scores go through it. Mutants that could not be applied
+    /// (`error`), were not tested (`skipped`, `pending`) or are marked
+    /// `equivalent` or `unproductive` are never counted.
+    pub(crate) fn counts_as_killed(&self, status: &str) -> Option<bool> {
+        match status {
+            "killed" | "timeout" => Some(true),
+            "build_failed" => (!self.exclude_build_failures).then_some(true),
+            "survived" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Whether the `deadline` has passed.
+    fn out_of_time(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Whether the coverage data has `target`, but not the `line` a mutant changes.
+    fn uncovered(&self, target: &str, line: Option<usize>) -> bool {
+        let covered = self
+            .coverage
+            .as_ref()
+            .and_then(|coverage| covered_lines(coverage, target));
+        matches!((covered, line), (Some(lines), Some(line)) if !lines.contains(&line))
+    }
+}
+
+/// How each mutant is tested: an optional build step, whose failure means the
+/// mutant does not compile, followed by the test command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCommand {
+    pub build: Option<String>,
+    pub test: String,
+}
+
+impl std::fmt::Display for TestCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.build {
+            Some(ref build) => write!(f, "{} && {}", build, self.test),
+            None => write!(f, "{}", self.test),
+        }
+    }
+}
+
+impl TestCommand {
+    /// Substitute the placeholders of a user-given command for `target`:
+    /// `{target}`, `{test_name}` (its unit test suite or functional test) and
+    /// `{build_jobs}` (`--jobs`, or the number of CPUs when it is 0). `{{` and
+    /// `}}` stand for literal braces.
+    fn for_target(&self, target: &str, jobs: u32) -> Result<TestCommand> {
+        Ok(TestCommand {
+            build: self
+                .build
+                .as_deref()
+                .map(|build| expand_placeholders(build, target, jobs))
+                .transpose()?,
+            test: expand_placeholders(&self.test, target, jobs)?,
+        })
+    }
+
+    /// The same command run through the `sandbox` prefix, if any.
+    fn sandboxed(self, sandbox: Option<&str>) -> TestCommand {
+        TestCommand {
+            build: self.build.map(|build| sandboxed(&build, sandbox)),
+            test: sandboxed(&self.test, sandbox),
+        }
+    }
+}
+
+/// `command` run as `<sandbox> sh -c '<command>'`, e.g. in a container whose
+/// view of the source is the working tree the mutants are applied to.
+fn sandboxed(command: &str, sandbox: Option<&str>) -> String {
+    match sandbox {
+        Some(prefix) => format!("{} sh -c '{}'", prefix, command.replace('\'', r"'\''")),
+        None => command.to_string(),
+    }
+}
+
+/// Replace the `{target}`, `{test_name}` and `{build_jobs}` placeholders of
+/// `template`. Shell variables (`${VAR}`) are left alone, and a `{{` ... `}}`
+/// pair is unescaped to `{` ... `}`: doubled braces without a match stay as
+/// written.
+fn expand_placeholders(template: &str, target: &str, jobs: u32) -> Result<String> {
+    let placeholder = Regex::new(r"\$\{\w+\}|\{\{|\}\}|\{([A-Za-z_]\w*)\}")?;
+    let tokens: Vec<_> = placeholder.captures_iter(template).collect();
+    let mut paired = vec![false; tokens.len()];
+    let mut open = Vec::new();
+    for (i, captures) in tokens.iter().enumerate() {
+        match &captures[0] {
+            "{{" => open.push(i),
+            "}}" => {
+                if let Some(j) = open.pop() {
+                    paired[i] = true;
+                    paired[j] = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut expanded = String::with_capacity(template.len());
+    let mut last = 0;
+    for (captures, paired) in tokens.iter().zip(paired) {
+        let matched = captures.get(0).unwrap();
+        expanded.push_str(&template[last..matched.start()]);
+        last = matched.end();
+        let value = match captures.get(1).map(|name| name.as_str()) {
+            None if paired => matched.as_str()[..1].to_string(),
+            None => matched.as_str().to_string(),
+            Some("target") => target.to_string(),
+            Some("test_name") => test_name(target).ok_or_else(|| {
+                MutationError::InvalidInput(format!(
+                    "{{test_name}} is used in the command but {} is not a unit or functional test",
+                    target
+                ))
+            })?,
+            Some("build_jobs") if jobs > 0 => jobs.to_string(),
+            Some("build_jobs") => std::thread::available_parallelism()
+                .map_or(1, |n| n.get())
+                .to_string(),
+            Some(name) => {
+                return Err(MutationError::InvalidInput(format!(
+                    "Unknown placeholder {{{}}} in command '{}' ({{{{ and }}}} are literal braces)",
+                    name, template
+                )));
+            }
+        };
+        expanded.push_str(&value);
+    }
+    expanded.push_str(&template[last..]);
+    Ok(expanded)
+}
+
+/// Name of the test defined by `target`, as `{test_name}` expands: the boost
+/// suite of a unit test file or the script name of a functional test.
+fn test_name(target: &str) -> Option<String> {
+    if let Some(suite) = unit_test_suite(target) {
+        return Some(suite.to_string());
+    }
+    if target.contains("functional") {
+        return Path::new(target)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(str::to_string);
+    }
+    None
+}
+
+/// Outcome of a single analyzed mutant.
+#[derive(Debug, Clone, Serialize)]
+pub struct MutantResult {
+    /// Mutant file name (folder mode) or `mutant-<id>` (database mode).
+    pub name: String,
+    /// One of `killed`, `survived`, `timeout`, `build_failed`, `error` or `skipped`.
+    pub status: String,
+    /// Diff against the original file, filled in for surviving mutants.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+    /// Operator that produced the mutant, when known from the manifest or database.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+}
+
+/// Results of analyzing the mutants of one target file.
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderResult {
+    pub target_file: String,
+    pub killed: u64,
+    /// Number of mutants found, including those skipped by early termination
+    /// but not build failures left out of the score.
+    pub total: u64,
+    pub mutants: Vec<MutantResult>,
+    /// Duration of the unmutated test run in seconds, when it was timed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline_secs: Option<f64>,
+    /// Surviving-mutant report entry, when the JSON format found survivors.
+    #[serde(skip)]
+    pub report: Option<ReportData>,
+}
+
+/// Summary of an `analyze` run, returned by [`run_analysis`] and printed as
+/// JSON with `--json`.
+#[derive(Debug, Default, Serialize)]
+pub struct AnalysisSummary {
+    pub killed: u64,
+    /// Mutants that survived their tests; mutants skipped by early termination
+    /// count in `total` but not here.
+    pub survived: u64,
+    /// Mutants left untested because the run hit its `deadline` or their line
+    /// is not covered; they are not counted in `total`, so the score is partial.
+    pub skipped: u64,
+    pub total: u64,
+    /// killed / total, or `None` when nothing was analyzed.
+    pub score: Option<f64>,
+    pub files: Vec<FolderResult>,
+    /// Effectiveness of each operator across all files.
+    pub operators: Vec<OperatorStats>,
+    /// Folders that could not be analyzed with `keep_going`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<FailedFolder>,
+}
+
+/// A folder whose analysis failed, and why.
+#[derive(Debug, Serialize)]
+pub struct FailedFolder {
+    pub folder: PathBuf,
+    pub error: String,
+}
+
+/// How one operator's mutants fared in an analysis.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OperatorStats {
+    /// `None` groups the mutants whose operator is unknown.
+    pub operator: Option<String>,
+    /// Mutants counted in the score.
+    pub total: u64,
+    pub killed: u64,
+    pub survived: u64,
+    /// killed / total, or `None` if no mutant was counted.
+    pub kill_rate: Option<f64>,
+    /// Share of all survivors produced by this operator.
+    pub survivor_share: Option<f64>,
+}
+
+impl AnalysisSummary {
+    fn new(files: Vec<FolderResult>, options: &AnalysisOptions) -> Self {
+        let killed = files.iter().map(|r| r.killed).sum();
+        let total = files.iter().map(|r| r.total).sum();
+        let count = |status: &str| {
+            files
+                .iter()
+                .flat_map(|r| &r.mutants)
+                .filter(|m| m.status == status)
+                .count() as u64
+        };
+        let (survived, skipped) = (count("survived"), count("skipped"));
+        if skipped > 0 {
+            warn!(
+                "{} mutants were skipped (--max-duration or --cov) and the score is partial",
+                skipped
+            );
+        }
+        AnalysisSummary {
+            killed,
+            survived,
+            skipped,
+            total,
+            score: (total > 0).then(|| killed as f64 / total as f64),
+            operators: operator_stats(&files, options),
+            files,
+            failed: Vec::new(),
+        }
+    }
+
+    /// Fail if any folder could not be analyzed.
+    pub fn check_failed(&self) -> Result<()> {
+        for failure in &self.failed {
+            error!("{}: {}", failure.folder.display(), failure.error);
+        }
+        match self.failed.len() {
+            0 => Ok(()),
+            count => Err(MutationError::FoldersFailed(count)),
+        }
+    }
+
+    /// The surviving mutants, with the file they mutate.
+    pub fn survivors(&self) -> impl Iterator<Item = (&str, &MutantResult)> {
+        self.files.iter().flat_map(|file| {
+            file.mutants
+                .iter()
+                .filter(|m| m.status == "survived")
+                .map(|m| (file.target_file.as_str(), m))
+        })
+    }
+
+    /// Fail if the score is below `threshold`. Files with their own survival
+    /// threshold in `file_thresholds` are held to it instead, and left out of
+    /// the score. A run with nothing analyzed passes.
+    pub fn check_fail_under(&self, threshold: f64, file_thresholds: &FileThresholds) -> Result<()> {
+        let (mut killed, mut total) = (self.killed, self.total);
+        for file in &self.files {
+            let Some(survival_threshold) = file_thresholds.get(&file.target_file) else {
+                continue;
+            };
+            killed -= file.killed;
+            total -= file.total;
+            if file.total == 0 {
+                continue;
+            }
+            let rate = (file.total - file.killed) as f64 / file.total as f64;
+            if rate > survival_threshold {
+                return Err(MutationError::FileSurvivalAboveThreshold {
+                    file: file.target_file.clone(),
+                    rate,
+                    threshold: survival_threshold,
+                });
+            }
+        }
+        match (total > 0).then(|| killed as f64 / total as f64) {
+            Some(score) if score < threshold => {
+                Err(MutationError::ScoreBelowThreshold { score, threshold })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            command: None,
+            jobs: 0,
+            timeout_secs: 300,
+            timeout_factor: None,
+            skip_baseline_check: false,
+            progress: false,
+            sandbox: None,
+            keep_going: false,
+            deadline: None,
+            coverage: None,
+            incremental: false,
+            survival_threshold: 0.75,
+            file_survival_thresholds: FileThresholds::default(),
+            sqlite_path: None,
+            run_id: None,
+            file_path: None,
+            survivors_only: false,
+            shuffle: None,
+            seed: None,
+            dry_run: false,
+            only: None,
+            format: ReportFormat::default(),
+            report_path: None,
+            report_mode: ReportMode::default(),
+            annotate: false,
+            build_command: None,
+            exclude_build_failures: false,
+            keep_on_failure: false,
+            force: false,
+            operator_stats_path: None,
+            root: PathBuf::from("."),
+            recursive: false,
+        }
+    }
+}
+
 pub async fn run_analysis(
     folder: Option<PathBuf>,
-    command: Option<String>,
-    jobs: u32,
-    timeout_secs: u64,
-    survival_threshold: f64,
-    sqlite_path: Option<PathBuf>,
-    run_id: Option<i64>,
-    file_path: Option<String>,
-    survivors_only: bool,
-) -> Result<()> {
+    options: AnalysisOptions,
+) -> Result<AnalysisSummary> {
+    // A dry run only reads the folders, so it must not create or migrate the
+    // database.
+    if options.dry_run {
+        let options = resolve_shuffle_seed(options);
+        for folder_path in mutation_folders(folder, &options)? {
+            dry_run_folder(&folder_path, &options)?;
+        }
+        return Ok(AnalysisSummary::default());
+    }
+
+    let db = match options.sqlite_path {
+        Some(ref path) => {
+            let db = Database::open(path)?;
+            db.ensure_schema()?;
+            db.seed_projects()?;
+            Some(db)
+        }
+        None => None,
+    };
+
     // DB-based analysis mode: read mutants from DB and test them.
-    if let (Some(ref path), Some(rid)) = (sqlite_path.as_ref(), run_id) {
-        let command = command.ok_or_else(|| {
+    if let (Some(db), Some(rid), None) = (db.as_ref(), options.run_id, folder.as_ref()) {
+        let test = options.command.clone().ok_or_else(|| {
             MutationError::InvalidInput(
                 "--command is required when using --sqlite with --run_id".to_string(),
             )
         })?;
-        let db = Database::open(path)?;
-        db.ensure_schema()?;
-        db.seed_projects()?;
-        return run_db_analysis(
-            &db,
-            rid,
-            &command,
-            timeout_secs,
-            file_path.as_deref(),
-            survivors_only,
-        )
-        .await;
+        let command = TestCommand {
+            build: options.build_command.clone(),
+            test,
+        };
+        let cache = AnalysisCache::new(db, options.incremental).await;
+        let results = run_db_analysis(db, cache.as_ref(), rid, &command, &options).await?;
+        if options.format == ReportFormat::Junit {
+            generate_junit_report(&results, &options.report_destination())?;
+        }
+
+        // A filtered or survivors-only pass only sees part of the run, so the
+        // stored score is recomputed from every analyzed mutant in the run.
+        let (killed, analyzed) = db
+            .get_file_kill_counts(rid, |status| options.counts_as_killed(status))?
+            .iter()
+            .fold((0, 0), |(k, a), (_, killed, analyzed)| {
+                (k + killed, a + analyzed)
+            });
+        if analyzed > 0 {
+            db.update_run_score(rid, killed as f64 / analyzed as f64)?;
+        }
+        let summary = AnalysisSummary::new(results, &options);
+        report_operator_stats(&summary.operators, &options)?;
+        return Ok(summary);
     }
 
     // Folder-based analysis mode (existing behaviour).
-    let folders = if let Some(folder_path) = folder {
-        vec![folder_path]
-    } else {
-        // Find all folders starting with "muts"
-        find_mutation_folders()?
+    let folders = mutation_folders(folder, &options)?;
+
+    // In folder mode the database caches results and, with --run_id, receives
+    // the status of each analyzed mutant.
+    let cache = match db {
+        Some(ref db) => AnalysisCache::new(db, options.incremental).await,
+        None => None,
     };
+    let run = db.as_ref().zip(options.run_id);
+    let options = resolve_shuffle_seed(options);
 
-    for folder_path in folders {
-        analyze_folder(
-            &folder_path,
-            command.clone(),
-            jobs,
-            timeout_secs,
-            survival_threshold,
-        )
-        .await?;
+    // Without a test command, the tree is built from scratch once; each mutant
+    // then only triggers an incremental rebuild.
+    if options.command.is_none() && !folders.is_empty() {
+        run_build_command(options.sandbox.as_deref()).await?;
+    }
+
+    let mut results: Vec<FolderResult> = Vec::new();
+    let mut failed = Vec::new();
+    for folder_path in &folders {
+        // The target as it was before the folder, uncommitted changes included.
+        let before = read_target_file(folder_path)
+            .ok()
+            .and_then(|target| Some((fs::read_to_string(&target).ok()?, target)));
+        let outcome = analyze_folder(folder_path, &options, cache.as_ref(), run).await;
+        match outcome {
+            Err(e @ MutationError::MutantKept { .. }) => return Err(e),
+            // Nothing was applied, and restoring would discard the changes.
+            Err(MutationError::DirtyTarget(_)) => {}
+            // A folder that failed midway can leave its target mutated, which
+            // would end up in the build of the next folders.
+            _ => {
+                if let Some((content, target)) = before {
+                    restore_if_mutated(&target, &content)?;
+                }
+            }
+        }
+        match outcome {
+            Ok(result) => results.push(result),
+            Err(e) if options.keep_going => {
+                error!("Skipping {}: {}", folder_path.display(), e);
+                failed.push(FailedFolder {
+                    folder: folder_path.clone(),
+                    error: e.to_string(),
+                });
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    // Reports from every folder go to a single destination.
+    match options.format {
+        ReportFormat::Json => {
+            let reports = results.iter_mut().filter_map(|r| r.report.take()).collect();
+            save_reports(reports, &options.report_destination(), options.report_mode)?;
+        }
+        ReportFormat::Junit => generate_junit_report(&results, &options.report_destination())?,
+    }
+
+    let mut summary = AnalysisSummary::new(results, &options);
+    summary.failed = failed;
+    if let (Some((db, run_id)), Some(score)) = (run, summary.score) {
+        if folders.len() > 1 {
+            info!("\nAGGREGATE MUTATION SCORE: {:.2}%", score * 100.0);
+        }
+        db.update_run_score(run_id, score)?;
+    }
+    report_operator_stats(&summary.operators, &options)?;
+
+    Ok(summary)
+}
+
+/// Group the analyzed mutants by operator. Mutants left out of the score
+/// (build failures with `exclude_build_failures`) are not counted.
+fn operator_stats(files: &[FolderResult], options: &AnalysisOptions) -> Vec<OperatorStats> {
+    let mut counts: BTreeMap<Option<&str>, (u64, u64)> = BTreeMap::new();
+    for mutant in files.iter().flat_map(|f| &f.mutants) {
+        let Some(killed) = options.counts_as_killed(&mutant.status) else {
+            continue;
+        };
+        let (num_killed, num_survived) = counts.entry(mutant.operator.as_deref()).or_default();
+        if killed {
+            *num_killed += 1;
+        } else {
+            *num_survived += 1;
+        }
+    }
+
+    let total_survived: u64 = counts.values().map(|(_, survived)| survived).sum();
+    counts
+        .into_iter()
+        .map(|(operator, (killed, survived))| {
+            let total = killed + survived;
+            OperatorStats {
+                operator: operator.map(str::to_string),
+                total,
+                killed,
+                survived,
+                kill_rate: (total > 0).then(|| killed as f64 / total as f64),
+                survivor_share: (total_survived > 0)
+                    .then(|| survived as f64 / total_survived as f64),
+            }
+        })
+        .collect()
+}
+
+/// Log the operator effectiveness table and, with `--operator-stats`, write it
+/// as JSON.
+fn report_operator_stats(stats: &[OperatorStats], options: &AnalysisOptions) -> Result<()> {
+    if stats.iter().all(|s| s.operator.is_none()) {
+        // Still write the file, so that a stale one is not mistaken for this run's.
+        if let Some(ref path) = options.operator_stats_path {
+            warn!(
+                "No analyzed mutant has a known operator; writing empty operator stats to {}",
+                path.display()
+            );
+            fs::write(path, "[]")?;
+        }
+        return Ok(());
     }
 
+    let percent = |value: Option<f64>| {
+        value
+            .map(|v| format!("{:.1}%", v * 100.0))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    info!("\nOPERATOR EFFECTIVENESS:");
+    info!(
+        "{:>7}  {:>7}  {:>8}  {:>9}  {:>9}  {}",
+        "TOTAL", "KILLED", "SURVIVED", "KILL RATE", "SURVIVORS", "OPERATOR"
+    );
+    for s in stats {
+        info!(
+            "{:>7}  {:>7}  {:>8}  {:>9}  {:>9}  {}",
+            s.total,
+            s.killed,
+            s.survived,
+            percent(s.kill_rate),
+            percent(s.survivor_share),
+            s.operator.as_deref().unwrap_or("-")
+        );
+    }
+
+    if let Some(ref path) = options.operator_stats_path {
+        fs::write(path, serde_json::to_string_pretty(stats)?)?;
+        info!("Operator effectiveness saved to {}", path.display());
+    }
     Ok(())
 }
 
-/// Test all pending mutants in `run_id` from the database, optionally filtered by `file_path`.
-/// When `survivors_only` is true, only previously survived mutants are analyzed.
+/// Lower bound of the timeout derived with `--timeout-factor`.
+const MIN_AUTO_TIMEOUT_SECS: u64 = 30;
+
+/// Written to each mutation folder with `--annotate`. Its extension keeps it out
+/// of the mutant list.
+const ANNOTATED_SOURCE_FILE: &str = "annotated_source.txt";
+
+/// Analysis results stored in SQLite, keyed by `(commit_hash, patch_hash)`, so a
+/// mutant whose patch was already tested against the current commit is not rebuilt.
+/// When `incremental`, results from earlier commits are reused too, except
+/// survivors: new commits may add the tests that kill them.
+/// Without a commit to key results on, nothing is cached.
+pub struct AnalysisCache<'a> {
+    db: &'a Database,
+    commit_hash: String,
+    incremental: bool,
+}
+
+impl<'a> AnalysisCache<'a> {
+    pub async fn new(db: &'a Database, incremental: bool) -> Option<Self> {
+        match get_commit_hash().await {
+            Ok(commit_hash) => Some(AnalysisCache {
+                db,
+                commit_hash,
+                incremental,
+            }),
+            Err(e) => {
+                warn!("Not caching analysis results: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, patch_hash: &str) -> Result<Option<String>> {
+        match self.db.get_cached_status(&self.commit_hash, patch_hash)? {
+            None if self.incremental => self.db.get_latest_cached_status(patch_hash, &["survived"]),
+            status => Ok(status),
+        }
+    }
+
+    pub fn put(&self, patch_hash: &str, status: &str) -> Result<()> {
+        self.db.cache_status(&self.commit_hash, patch_hash, status)
+    }
+}
+
+/// Test all pending mutants in `run_id` from the database, optionally filtered by
+/// `options.file_path`. With `options.survivors_only`, only previously survived
+/// mutants are analyzed. Results are grouped per file.
 async fn run_db_analysis(
     db: &Database,
+    cache: Option<&AnalysisCache<'_>>,
     run_id: i64,
-    command: &str,
-    timeout_secs: u64,
-    file_path: Option<&str>,
-    survivors_only: bool,
-) -> Result<()> {
+    test_command: &TestCommand,
+    options: &AnalysisOptions,
+) -> Result<Vec<FolderResult>> {
+    let file_path = options.file_path.as_deref();
+    let survivors_only = options.survivors_only;
     let mutants = db.get_mutants_for_run(run_id, file_path, survivors_only)?;
     let total = mutants.len();
 
     match (file_path, survivors_only) {
-        (Some(fp), true) => println!(
+        (Some(fp), true) => info!(
             "* {} SURVIVING MUTANTS in run_id={} (file: {}) *",
             total, run_id, fp
         ),
-        (Some(fp), false) => println!("* {} MUTANTS in run_id={} (file: {}) *", total, run_id, fp),
-        (None, true) => println!("* {} SURVIVING MUTANTS in run_id={} *", total, run_id),
-        (None, false) => println!("* {} MUTANTS in run_id={} *", total, run_id),
+        (Some(fp), false) => info!("* {} MUTANTS in run_id={} (file: {}) *", total, run_id, fp),
+        (None, true) => info!("* {} SURVIVING MUTANTS in run_id={} *", total, run_id),
+        (None, false) => info!("* {} MUTANTS in run_id={} *", total, run_id),
     }
 
     if total == 0 {
@@ -93,20 +743,104 @@ async fn run_db_analysis(
         )));
     }
 
+    let mut targets: Vec<&str> = mutants
+        .iter()
+        .filter_map(|mutant| mutant.file_path.as_deref())
+        .filter(|path| !path.is_empty())
+        .collect();
+    targets.sort_unstable();
+    targets.dedup();
+    for target in targets {
+        check_clean_target(target, options.force).await?;
+    }
+
     let mut num_killed: u64 = 0;
     let mut num_survived: u64 = 0;
+    let mut num_build_failed: u64 = 0;
+    let mut num_errors: u64 = 0;
+    let mut num_skipped: u64 = 0;
+    let mut results: BTreeMap<String, FolderResult> = BTreeMap::new();
 
     for (i, mutant) in mutants.iter().enumerate() {
-        println!("[{}/{}] Analyzing mutant id={}", i + 1, total, mutant.id);
+        info!("[{}/{}] Analyzing mutant id={}", i + 1, total, mutant.id);
 
         // Determine the file path to restore later.
         let file_path = mutant.file_path.as_deref().unwrap_or("");
+        let test_command = test_command
+            .for_target(file_path, options.jobs)?
+            .sandboxed(options.sandbox.as_deref());
+        let command = test_command.to_string();
+        let command = command.as_str();
+        let file_result = results
+            .entry(file_path.to_string())
+            .or_insert_with(|| FolderResult {
+                target_file: file_path.to_string(),
+                killed: 0,
+                total: 0,
+                mutants: Vec::new(),
+                baseline_secs: None,
+                report: None,
+            });
+        file_result.total += 1;
+        let mut record = |status: &str| {
+            let survived = status == "survived";
+            match options.counts_as_killed(status) {
+                Some(true) => file_result.killed += 1,
+                Some(false) => {}
+                None => file_result.total -= 1,
+            }
+            file_result.mutants.push(MutantResult {
+                name: format!("mutant-{}", mutant.id),
+                status: status.to_string(),
+                diff: survived.then(|| mutant.diff.clone()),
+                operator: mutant.operator.clone(),
+            });
+        };
+
+        let cached = match cache {
+            Some(cache) => cache.get(&mutant.patch_hash)?,
+            None => None,
+        };
+        if let Some(status) = cached {
+            match options.counts_as_killed(&status) {
+                Some(false) => {
+                    info!("  NOT KILLED ❌ (cached)");
+                    num_survived += 1;
+                }
+                Some(true) => {
+                    info!("  KILLED ✅ (cached)");
+                    num_killed += 1;
+                }
+                None => {
+                    info!("  BUILD ERROR (not counted, cached)");
+                    num_build_failed += 1;
+                }
+            }
+            db.update_mutant_status(mutant.id, &status, command)?;
+            record(&status);
+            continue;
+        }
+
+        let skip_reason = if options.out_of_time() {
+            Some("out of time")
+        } else if options.uncovered(file_path, changed_line(&mutant.diff)) {
+            Some("not covered")
+        } else {
+            None
+        };
+        if let Some(reason) = skip_reason {
+            info!("  SKIPPED ({})", reason);
+            num_skipped += 1;
+            db.update_mutant_status(mutant.id, "skipped", command)?;
+            record("skipped");
+            continue;
+        }
 
         // Ensure the file is at HEAD before applying the mutant diff.
         // A previous mutant may have been left applied if restore silently failed.
         if !file_path.is_empty() {
             if let Err(e) = restore_file(file_path).await {
-                eprintln!("  Warning: pre-restore failed for {}: {}", file_path, e);
+                warn!("pre-restore failed for {}: {}", file_path, e);
             }
         }
 
@@ -116,25 +850,45 @@ async fn run_db_analysis(
         // Write the patch to a temp file and apply it with `git apply`.
         let apply_result = apply_diff(&mutant.diff).await;
         if let Err(ref e) = apply_result {
-            eprintln!("  Failed to apply diff for mutant {}: {}", mutant.id, e);
+            error!("Failed to apply diff for mutant {}: {}", mutant.id, e);
+            num_errors += 1;
             db.update_mutant_status(mutant.id, "error", command)?;
+            record("error");
             continue;
         }
 
-        // Run the test command.
-        let killed = !run_command(command, timeout_secs).await?;
+        // Build and run the test command.
+        let outcome = run_mutant(&test_command, options.timeout_secs, options.timeout_secs).await?;
+        let new_status = outcome.mutant_status();
 
-        let new_status = if killed {
-            println!("  KILLED ✅");
-            num_killed += 1;
-            "killed"
-        } else {
-            println!("  NOT KILLED ❌");
-            num_survived += 1;
-            "survived"
-        };
+        if outcome == CommandOutcome::BuildFailed && options.keep_on_failure {
+            db.update_mutant_status(mutant.id, new_status, command)?;
+            return Err(MutationError::MutantKept {
+                mutant: format!("Mutant id={}", mutant.id),
+                target: file_path.to_string(),
+            });
+        }
+
+        match options.counts_as_killed(new_status) {
+            Some(false) => {
+                info!("  NOT KILLED ❌");
+                num_survived += 1;
+            }
+            Some(true) => {
+                info!("  KILLED ✅");
+                num_killed += 1;
+            }
+            None => {
+                info!("  BUILD ERROR (not counted)");
+                num_build_failed += 1;
+            }
+        }
 
         db.update_mutant_status(mutant.id, new_status, command)?;
+        if let Some(cache) = cache {
+            cache.put(&mutant.patch_hash, new_status)?;
+        }
+        record(new_status);
 
         // Restore the modified file.
         if !file_path.is_empty() {
@@ -142,24 +896,93 @@ async fn run_db_analysis(
         }
     }
 
-    let score = if total > 0 {
-        num_killed as f64 / total as f64
+    let counted = total as u64 - num_build_failed - num_errors - num_skipped;
+    let score = if counted > 0 {
+        num_killed as f64 / counted as f64
     } else {
         0.0
     };
-    println!(
+    info!(
         "\nMUTATION SCORE: {:.2}% ({} killed / {} total)",
         score * 100.0,
         num_killed,
-        total
+        counted
     );
-    println!("Survived: {}", num_survived);
+    info!("Survived: {}", num_survived);
+    if num_build_failed > 0 {
+        info!("Build errors (not counted): {}", num_build_failed);
+    }
+    if num_errors > 0 {
+        warn!("{} mutants did not apply and were not tested", num_errors);
+    }
+    if num_skipped > 0 {
+        info!("Skipped (not counted): {}", num_skipped);
+    }
 
-    Ok(())
+    Ok(results.into_values().collect())
+}
+
+/// Whether a mutant file is a unified diff written with `mutate --patch`.
+fn is_patch(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .is_some_and(|ext| ext == PATCH_EXTENSION)
+}
+
+/// The diff of a mutant against the clean target: patch mutants already are one.
+async fn mutant_diff(
+    folder_path: &Path,
+    file_name: &str,
+    target_file_path: &str,
+) -> Result<String> {
+    let mutant_content = fs::read_to_string(folder_path.join(file_name))?;
+    if is_patch(file_name) {
+        Ok(mutant_content)
+    } else {
+        generate_diff(target_file_path, &mutant_content).await
+    }
 }
 
 /// Apply a unified diff patch using `git apply`.
 async fn apply_diff(diff: &str) -> Result<()> {
+    git_apply(diff, None).await
+}
+
+/// Undo a previously applied unified diff patch using `git apply -R`.
+async fn revert_diff(diff: &str) -> Result<()> {
+    git_apply(diff, Some("-R")).await
+}
+
+/// Check that a mutant's diff against the current target is a single hunk
+/// that applies cleanly. A stale mutant, generated from a different version
+/// of the target, fails this check instead of being tested.
+async fn check_mutant_diff(diff: &str) -> Result<()> {
+    let hunks = diff.lines().filter(|line| line.starts_with("@@")).count();
+    if hunks != 1 {
+        return Err(MutationError::InvalidInput(format!(
+            "mutant changes {} hunks of the target instead of one",
+            hunks
+        )));
+    }
+    git_apply(diff, Some("--check")).await
+}
+
+/// Number of lines a single-file diff removes and adds. Only hunk lines count,
+/// so a removed `--i;` is not taken for the `---` header.
+fn changed_lines(diff: &str) -> (usize, usize) {
+    let hunk_lines = diff.lines().skip_while(|line| !line.starts_with("@@"));
+    let (mut removed, mut added) = (0, 0);
+    for line in hunk_lines {
+        if line.starts_with('-') {
+            removed += 1;
+        } else if line.starts_with('+') {
+            added += 1;
+        }
+    }
+    (removed, added)
+}
+
+async fn git_apply(diff: &str, flag: Option<&str>) -> Result<()> {
     use std::io::Write;
 
     let mut tmp = NamedTempFile::new()?;
@@ -168,8 +991,11 @@ async fn apply_diff(diff: &str) -> Result<()> {
 
     let tmp_path = tmp.path().to_path_buf();
     // Keep `tmp` alive until after the command runs.
+    let mut args = vec!["apply", "--whitespace=nowarn"];
+    args.extend(flag);
+    args.push(tmp_path.to_str().unwrap());
     let output = TokioCommand::new("git")
-        .args(["apply", "--whitespace=nowarn", tmp_path.to_str().unwrap()])
+        .args(&args)
         .output()
         .await
         .map_err(|e| MutationError::Git(format!("git apply failed: {}", e)))?;
@@ -185,15 +1011,25 @@ async fn apply_diff(diff: &str) -> Result<()> {
     Ok(())
 }
 
-fn find_mutation_folders() -> Result<Vec<PathBuf>> {
+/// Find the `muts*` folders in `root` or, with `recursive`, anywhere below it.
+/// Hidden directories and the mutation folders themselves are not searched.
+fn find_mutation_folders(root: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
     let mut folders = Vec::new();
 
-    for entry in WalkDir::new(".").max_depth(1) {
+    let max_depth = if recursive { usize::MAX } else { 1 };
+    let mut entries = WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(max_depth)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| !entry.file_name().to_string_lossy().starts_with('.'));
+    while let Some(entry) = entries.next() {
         let entry = entry?;
         if entry.file_type().is_dir() {
             if let Some(name) = entry.file_name().to_str() {
                 if name.starts_with("muts") {
                     folders.push(entry.path().to_path_buf());
+                    entries.skip_current_dir();
                 }
             }
         }
@@ -202,105 +1038,1006 @@ fn find_mutation_folders() -> Result<Vec<PathBuf>> {
     Ok(folders)
 }
 
-pub async fn analyze_folder(
-    folder_path: &Path,
-    command: Option<String>,
-    jobs: u32,
-    timeout_secs: u64,
-    survival_threshold: f64,
-) -> Result<()> {
-    let mut num_killed: u64 = 0;
-    let mut not_killed = Vec::new();
-
-    // Read target file path
+/// Read the path of the file targeted by the mutants in `folder_path`.
+fn read_target_file(folder_path: &Path) -> Result<String> {
     let original_file_path = folder_path.join("original_file.txt");
-    let target_file_path = fs::read_to_string(original_file_path)?;
-    let target_file_path = target_file_path.trim();
+    let target_file_path = fs::read_to_string(&original_file_path).map_err(|e| {
+        MutationError::InvalidInput(format!(
+            "Cannot read {}: {}",
+            original_file_path.display(),
+            e
+        ))
+    })?;
+    Ok(target_file_path.trim().to_string())
+}
 
-    // Setup command if not provided
-    let test_command = if let Some(cmd) = command {
-        cmd
-    } else {
-        run_build_command().await?;
-        get_command_to_kill(&target_file_path, jobs)?
+/// Make sure the target still exists and is tracked by git, since it is
+/// overwritten by the mutants and restored with git afterwards.
+async fn check_target_file(folder_path: &Path, target_file_path: &str) -> Result<()> {
+    let missing = |reason| MutationError::MissingTarget {
+        target: target_file_path.to_string(),
+        folder: folder_path.display().to_string(),
+        reason,
     };
+    if !Path::new(target_file_path).is_file() {
+        return Err(missing("does not exist"));
+    }
 
-    // Get list of mutant files
+    let output = TokioCommand::new("git")
+        .args(["ls-files", "--error-unmatch", "--", target_file_path])
+        .output()
+        .await
+        .map_err(|e| MutationError::Git(format!("git ls-files failed: {}", e)))?;
+    if !output.status.success() {
+        return Err(missing("is not tracked by git"));
+    }
+    Ok(())
+}
+
+/// Make sure restoring `target_file_path` with git after the mutants will not
+/// discard uncommitted changes to it; with `force`, only warn.
+async fn check_clean_target(target_file_path: &str, force: bool) -> Result<()> {
+    // Unlike `git diff`, `git status` fails outside a repository instead of
+    // comparing files.
+    let output = TokioCommand::new("git")
+        .args(["status", "--porcelain", "--", target_file_path])
+        .output()
+        .await
+        .map_err(|e| MutationError::Git(format!("git status failed: {}", e)))?;
+    if !output.status.success() {
+        return Err(MutationError::Git(format!(
+            "Cannot tell whether {} has uncommitted changes: {}",
+            target_file_path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    if output.stdout.is_empty() {
+        return Ok(());
+    }
+    if !force {
+        return Err(MutationError::DirtyTarget(target_file_path.to_string()));
+    }
+    warn!(
+        "{} has uncommitted changes; they will be DISCARDED when it is restored (--force)",
+        target_file_path
+    );
+    Ok(())
+}
+
+/// List the mutant files in `folder_path`, in the order they will be analyzed.
+/// Folders with a manifest are analyzed in generation order.
+fn list_mutant_files(folder_path: &Path, options: &AnalysisOptions) -> Result<Vec<String>> {
+    let mut mutant_files = match Manifest::read(folder_path)? {
+        Some(manifest) => manifest
+            .mutants
+            .into_iter()
+            .map(|entry| entry.file)
+            .filter(|file| folder_path.join(file).is_file())
+            .collect(),
+        None => scan_mutant_files(folder_path)?,
+    };
+
+    if let Some(ref only) = options.only {
+        mutant_files = select_mutants_by_index(mutant_files, only)?;
+    }
+
+    if let Some(Some(seed)) = options.shuffle {
+        shuffle_mutants(&mut mutant_files, seed);
+    }
+
+    Ok(mutant_files)
+}
+
+/// Find the mutant files in a folder generated without a manifest, ordered by
+/// mutant index so runs visit them in the same order on every platform.
+fn scan_mutant_files(folder_path: &Path) -> Result<Vec<String>> {
     let mut mutant_files = Vec::new();
     for entry in fs::read_dir(folder_path)? {
         let entry = entry?;
         let path = entry.path();
-        if path.is_file() && !path.extension().map_or(true, |ext| ext == "txt") {
+        let is_mutant = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| MUTANT_EXTENSIONS.contains(&ext) || ext == PATCH_EXTENSION);
+        if path.is_file() && is_mutant {
             if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
                 mutant_files.push(name.to_string());
             }
         }
     }
+    mutant_files.sort_by(|a, b| (mutant_index(a), a).cmp(&(mutant_index(b), b)));
+    Ok(mutant_files)
+}
 
-    let total_mutants = mutant_files.len();
-    println!("* {} MUTANTS *", total_mutants);
+/// Parse a mutant index selection such as `10-20,42` into inclusive ranges.
+pub fn parse_mutant_indices(spec: &str) -> Result<Vec<RangeInclusive<usize>>> {
+    let invalid = |part: &str| {
+        MutationError::InvalidInput(format!(
+            "Invalid mutant index '{}' in '{}' (expected e.g. 10-20,42)",
+            part, spec
+        ))
+    };
 
-    if total_mutants == 0 {
-        return Err(MutationError::InvalidInput(format!(
-            "No mutants in the provided folder path ({})",
-            folder_path.display()
-        )));
+    let mut ranges = Vec::new();
+    for part in spec.split(',').map(str::trim) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (part, part),
+        };
+        let start: usize = start.parse().map_err(|_| invalid(part))?;
+        let end: usize = end.parse().map_err(|_| invalid(part))?;
+        if start > end {
+            return Err(invalid(part));
+        }
+        ranges.push(start..=end);
     }
 
-    for (i, file_name) in mutant_files.iter().enumerate() {
-        let current_survival_rate = not_killed.len() as f64 / total_mutants as f64;
-        if current_survival_rate > survival_threshold {
-            println!(
-                "\nTerminating early: {:.2}% mutants surviving after {} iterations",
-                current_survival_rate * 100.0,
-                i + 1
-            );
-            println!(
-                "Survival rate exceeds threshold of {:.0}%",
-                survival_threshold * 100.0
-            );
-            break;
-        }
+    Ok(ranges)
+}
 
-        println!("[{}/{}] Analyzing {}", i + 1, total_mutants, file_name);
+/// Extract the numeric id embedded in a mutant file name (`name.mutant.N.ext`).
+fn mutant_index(file_name: &str) -> Option<usize> {
+    let (_, rest) = file_name.rsplit_once(".mutant.")?;
+    rest.split('.').next()?.parse().ok()
+}
+
+/// Keep only the mutants whose index is selected by `only`, rejecting indices
+/// that do not exist in the folder.
+fn select_mutants_by_index(
+    mutant_files: Vec<String>,
+    only: &[RangeInclusive<usize>],
+) -> Result<Vec<String>> {
+    let indices: BTreeSet<usize> = mutant_files
+        .iter()
+        .filter_map(|f| mutant_index(f))
+        .collect();
+    if indices.is_empty() {
+        return Err(MutationError::InvalidInput(
+            "--only was given but no indexed mutants were found".to_string(),
+        ));
+    }
+
+    // Walk the known indices of each range, so that large ranges stay cheap.
+    let mut unknown = Vec::new();
+    for range in only {
+        let mut next = *range.start();
+        for &idx in indices.range(range.clone()) {
+            if idx > next {
+                unknown.push(next..=idx - 1);
+            }
+            next = idx + 1;
+        }
+        if next <= *range.end() {
+            unknown.push(next..=*range.end());
+        }
+    }
+    if !unknown.is_empty() {
+        let valid = indices.iter().map(|&idx| idx..=idx).collect();
+        return Err(MutationError::InvalidInput(format!(
+            "No mutants with index {} (valid: {})",
+            format_index_ranges(unknown),
+            format_index_ranges(valid)
+        )));
+    }
+
+    Ok(mutant_files
+        .into_iter()
+        .filter(|f| mutant_index(f).is_some_and(|idx| only.iter().any(|r| r.contains(&idx))))
+        .collect())
+}
+
+/// Format index ranges the way `--only` takes them, e.g. `3,7-9`, merging
+/// the ranges that overlap or touch.
+fn format_index_ranges(mut ranges: Vec<RangeInclusive<usize>>) -> String {
+    ranges.sort_by_key(|r| *r.start());
+    let mut merged: Vec<RangeInclusive<usize>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if *range.start() <= last.end().saturating_add(1) => {
+                *last = *last.start()..=*last.end().max(range.end());
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+        .iter()
+        .map(|r| match r.start() == r.end() {
+            true => r.start().to_string(),
+            false => format!("{}-{}", r.start(), r.end()),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The folder to analyze, or else every mutation folder under the root.
+fn mutation_folders(folder: Option<PathBuf>, options: &AnalysisOptions) -> Result<Vec<PathBuf>> {
+    match folder {
+        Some(folder_path) => Ok(vec![folder_path]),
+        // Find all folders starting with "muts"
+        None => find_mutation_folders(&options.root, options.recursive),
+    }
+}
+
+/// Resolve the shuffle seed once so every folder uses (and reports) the same one.
+fn resolve_shuffle_seed(mut options: AnalysisOptions) -> AnalysisOptions {
+    if let Some(None) = options.shuffle {
+        options.shuffle = Some(Some(options.seed.unwrap_or_else(rand::random)));
+    }
+    if let Some(Some(seed)) = options.shuffle {
+        info!("Shuffling mutants with seed {}", seed);
+    }
+    options
+}
+
+/// Print what `analyze_folder` would run for `folder_path` without building or
+/// testing anything.
+fn dry_run_folder(folder_path: &Path, options: &AnalysisOptions) -> Result<()> {
+    let target_file_path = read_target_file(folder_path)?;
+    let sandbox = options.sandbox.as_deref();
+    let test_command = match options.command {
+        Some(ref cmd) => TestCommand {
+            build: options.build_command.clone(),
+            test: cmd.clone(),
+        }
+        .for_target(&target_file_path, options.jobs)?
+        .sandboxed(sandbox)
+        .to_string(),
+        None => format!(
+            "{} (after one clean build for all folders)",
+            get_command_to_kill(&target_file_path, options.jobs)?.sandboxed(sandbox)
+        ),
+    };
+    let mutant_files = list_mutant_files(folder_path, options)?;
+
+    println!("\nFolder: {}", folder_path.display());
+    println!("Target: {}", target_file_path);
+    println!("Command: {}", test_command);
+    println!("* {} MUTANTS *", mutant_files.len());
+
+    if mutant_files.is_empty() {
+        println!("Warning: no mutants in this folder");
+    }
+
+    for (i, file_name) in mutant_files.iter().enumerate() {
+        println!("[{}/{}] {}", i + 1, mutant_files.len(), file_name);
+    }
+
+    Ok(())
+}
+
+/// What `verify` found in a mutation folder.
+#[derive(Debug, Serialize)]
+pub struct FolderCheck {
+    pub folder: PathBuf,
+    /// Target named by `original_file.txt`, when it could be read.
+    pub target: Option<String>,
+    /// Number of mutant files in the folder.
+    pub mutants: usize,
+    /// Inconsistencies that would make the analysis of the folder wrong or fail.
+    pub problems: Vec<String>,
+}
+
+/// Check that a mutation folder can be analyzed as it is: its target exists
+/// and is tracked, each mutant changes one line of the current target and
+/// applies cleanly, and the mutants match the manifest, if any. Nothing is
+/// built or tested, and the target is left untouched.
+pub async fn verify_folder(folder_path: &Path) -> Result<FolderCheck> {
+    let mut check = FolderCheck {
+        folder: folder_path.to_path_buf(),
+        target: None,
+        mutants: 0,
+        problems: Vec::new(),
+    };
+    let mutant_files = scan_mutant_files(folder_path)?;
+    check.mutants = mutant_files.len();
+
+    let target_file_path = match read_target_file(folder_path) {
+        Ok(target) => target,
+        Err(e) => {
+            check.problems.push(e.to_string());
+            return Ok(check);
+        }
+    };
+    check.target = Some(target_file_path.clone());
+    if let Err(e) = check_target_file(folder_path, &target_file_path).await {
+        check.problems.push(e.to_string());
+        return Ok(check);
+    }
+    let original_content = fs::read_to_string(&target_file_path)?;
+
+    if mutant_files.is_empty() {
+        check.problems.push("no mutants in this folder".to_string());
+    }
+
+    let mut patch_hashes = HashMap::new();
+    match Manifest::read(folder_path) {
+        Ok(Some(manifest)) => {
+            if manifest.target_file != target_file_path {
+                check.problems.push(format!(
+                    "the manifest targets {} but original_file.txt names {}",
+                    manifest.target_file, target_file_path
+                ));
+            }
+            for entry in manifest.mutants {
+                if !mutant_files.contains(&entry.file) {
+                    check
+                        .problems
+                        .push(format!("{} is in the manifest but missing", entry.file));
+                }
+                patch_hashes.insert(entry.file, entry.patch_hash);
+            }
+            for file_name in &mutant_files {
+                if !patch_hashes.contains_key(file_name) {
+                    check
+                        .problems
+                        .push(format!("{} is not in the manifest", file_name));
+                }
+            }
+        }
+        Ok(None) => {}
+        Err(e) => check.problems.push(e.to_string()),
+    }
+
+    for file_name in &mutant_files {
+        let mutant_content = fs::read_to_string(folder_path.join(file_name))?;
+        let identical = if is_patch(file_name) {
+            !mutant_content.lines().any(|line| line.starts_with("@@"))
+        } else {
+            mutant_content == original_content
+        };
+        if identical {
+            check.problems.push(format!(
+                "{} is identical to the target and would always survive",
+                file_name
+            ));
+            continue;
+        }
+
+        let diff = mutant_diff(folder_path, file_name, &target_file_path).await?;
+        let (removed, added) = changed_lines(&diff);
+        if let Err(e) = check_mutant_diff(&diff).await {
+            check.problems.push(format!("{}: {}", file_name, e));
+        } else if (removed, added) != (1, 1) {
+            check.problems.push(format!(
+                "{} removes {} and adds {} lines instead of changing one line",
+                file_name, removed, added
+            ));
+        } else if patch_hashes
+            .get(file_name)
+            .is_some_and(|hash| *hash != compute_patch_hash(&diff))
+        {
+            check.problems.push(format!(
+                "{} differs from the mutant recorded in the manifest",
+                file_name
+            ));
+        }
+    }
+    Ok(check)
+}
+
+/// Verify `folder`, or the `muts*` folders found like `analyze` does, and print
+/// what was found. Fails when any folder is inconsistent.
+pub async fn run_verify(
+    folder: Option<PathBuf>,
+    root: &Path,
+    recursive: bool,
+    json: bool,
+) -> Result<()> {
+    let folders = match folder {
+        Some(folder) => vec![folder],
+        None => find_mutation_folders(root, recursive)?,
+    };
+    if folders.is_empty() {
+        return Err(MutationError::InvalidInput(format!(
+            "No mutation folders found in {}",
+            root.display()
+        )));
+    }
+
+    let mut checks = Vec::new();
+    for folder in &folders {
+        checks.push(verify_folder(folder).await?);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&checks)?);
+    } else {
+        for check in &checks {
+            let status = if check.problems.is_empty() {
+                "OK"
+            } else {
+                "FAIL"
+            };
+            println!(
+                "{:<4}  {}: {} mutants of {}",
+                status,
+                check.folder.display(),
+                check.mutants,
+                check.target.as_deref().unwrap_or("?")
+            );
+            for problem in &check.problems {
+                println!("      - {}", problem);
+            }
+        }
+    }
+
+    let inconsistent = checks.iter().filter(|c| !c.problems.is_empty()).count();
+    if inconsistent > 0 {
+        return Err(MutationError::InvalidInput(format!(
+            "{} of {} mutation folders are inconsistent",
+            inconsistent,
+            checks.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Analyze every mutant in `folder_path`. Without a test command, the tree
+/// must already be built: [`run_analysis`] builds it once for all folders.
+pub async fn analyze_folder(
+    folder_path: &Path,
+    options: &AnalysisOptions,
+    cache: Option<&AnalysisCache<'_>>,
+    run: Option<(&Database, i64)>,
+) -> Result<FolderResult> {
+    let mut timeout_secs = options.timeout_secs;
+    let mut num_killed: u64 = 0;
+    let mut num_build_failed: u64 = 0;
+    let mut num_errors: u64 = 0;
+    let mut num_skipped: u64 = 0;
+    let mut not_killed = Vec::new();
+    let mut results = Vec::new();
+
+    // Read target file path
+    let target_file_path = read_target_file(folder_path)?;
+    let target_file_path = target_file_path.as_str();
+    check_target_file(folder_path, target_file_path).await?;
+    check_clean_target(target_file_path, options.force).await?;
+    let survival_threshold = options
+        .file_survival_thresholds
+        .get(target_file_path)
+        .unwrap_or(options.survival_threshold);
+
+    let test_command = folder_test_command(target_file_path, options)?;
+
+    // Get list of mutant files
+    let mutant_files = list_mutant_files(folder_path, options)?;
+
+    let total_mutants = mutant_files.len();
+    info!("* {} MUTANTS *", total_mutants);
+
+    if total_mutants == 0 {
+        return Err(MutationError::InvalidInput(format!(
+            "No mutants in the provided folder path ({})",
+            folder_path.display()
+        )));
+    }
+
+    let manifest_entries: HashMap<String, ManifestEntry> = Manifest::read(folder_path)?
+        .map(|manifest| {
+            manifest
+                .mutants
+                .into_iter()
+                .map(|entry| (entry.file.clone(), entry))
+                .collect()
+        })
+        .unwrap_or_default();
+    let operator_of = |file_name: &str| {
+        manifest_entries
+            .get(file_name)
+            .map(|entry| entry.operator.clone())
+    };
+
+    // Patch hashes come from the manifest when there is one; otherwise they must be
+    // computed against the clean target, before any mutant is applied.
+    let mut patch_hashes = HashMap::new();
+    if cache.is_some() || run.is_some() {
+        for file_name in &mutant_files {
+            let hash = match manifest_entries.get(file_name) {
+                Some(entry) => entry.patch_hash.clone(),
+                None => {
+                    let diff = mutant_diff(folder_path, file_name, target_file_path).await?;
+                    compute_patch_hash(&diff)
+                }
+            };
+            patch_hashes.insert(file_name.clone(), hash);
+        }
+    }
+    let original_content = fs::read_to_string(target_file_path)?;
+    let checksum = compute_patch_hash(&original_content);
+
+    // If the tests already fail on the unmutated target, every mutant would look
+    // killed. The same run times the tests for `timeout_factor`.
+    let mut baseline = None;
+    if (!options.skip_baseline_check || options.timeout_factor.is_some()) && !options.out_of_time()
+    {
+        info!("Running the tests on the unmutated target");
+        match run_baseline(&test_command, options.timeout_secs, timeout_secs).await? {
+            (CommandOutcome::Success, elapsed) => {
+                info!("Baseline passed in {:.1}s", elapsed.as_secs_f64());
+                if let Some(factor) = options.timeout_factor {
+                    timeout_secs = auto_timeout(elapsed, factor);
+                    info!("Timing out mutants after {}s", timeout_secs);
+                }
+                baseline = Some(elapsed);
+            }
+            (outcome, _) if !options.skip_baseline_check => {
+                return Err(MutationError::BaselineFailed(format!(
+                    "{} ({})",
+                    test_command,
+                    match outcome {
+                        CommandOutcome::Timeout => "timed out",
+                        CommandOutcome::BuildFailed => "build failed",
+                        _ => "tests failed",
+                    }
+                )));
+            }
+            (outcome, _) => warn!(
+                "Baseline run did not pass ({}), timing out mutants after {}s",
+                outcome.mutant_status(),
+                timeout_secs
+            ),
+        }
+    }
+
+    let command = test_command.to_string();
+    let progress = mutant_bar(total_mutants as u64, options.progress);
+    for (i, file_name) in mutant_files.iter().enumerate() {
+        let current_survival_rate = not_killed.len() as f64 / total_mutants as f64;
+        if current_survival_rate > survival_threshold {
+            info!(
+                "\nTerminating early: {:.2}% mutants surviving after {} iterations",
+                current_survival_rate * 100.0,
+                i + 1
+            );
+            info!(
+                "Survival rate exceeds threshold of {:.0}%",
+                survival_threshold * 100.0
+            );
+            break;
+        }
+
+        progress.set_position(i as u64);
+        progress.set_message(format!(
+            "{:.1}% surviving",
+            not_killed.len() as f64 / i.max(1) as f64 * 100.0
+        ));
+        info!("[{}/{}] Analyzing {}", i + 1, total_mutants, file_name);
+
+        let patch_hash = patch_hashes.get(file_name);
+        if let (Some(cache), Some(hash)) = (cache, patch_hash) {
+            if let Some(status) = cache.get(hash)? {
+                match options.counts_as_killed(&status) {
+                    Some(false) => {
+                        info!("NOT KILLED ❌ (cached)");
+                        not_killed.push(file_name.clone());
+                    }
+                    Some(true) => {
+                        info!("KILLED ✅ (cached)");
+                        num_killed += 1;
+                    }
+                    None => {
+                        info!("BUILD ERROR (not counted, cached)");
+                        num_build_failed += 1;
+                    }
+                }
+                record_run_status(run, hash, &status, &command)?;
+                results.push(MutantResult {
+                    name: file_name.clone(),
+                    status,
+                    diff: None,
+                    operator: operator_of(file_name),
+                });
+                continue;
+            }
+        }
+
+        let skip_reason = if options.out_of_time() {
+            Some("out of time")
+        } else if options.coverage.is_some() {
+            let line = match manifest_entries.get(file_name) {
+                Some(entry) => Some(entry.line),
+                None => changed_line(&mutant_diff(folder_path, file_name, target_file_path).await?),
+            };
+            options
+                .uncovered(target_file_path, line)
+                .then_some("not covered")
+        } else {
+            None
+        };
+        if let Some(reason) = skip_reason {
+            info!("SKIPPED ({})", reason);
+            num_skipped += 1;
+            if let Some(hash) = patch_hash {
+                record_run_status(run, hash, "skipped", &command)?;
+            }
+            results.push(MutantResult {
+                name: file_name.clone(),
+                status: "skipped".to_string(),
+                diff: None,
+                operator: operator_of(file_name),
+            });
+            continue;
+        }
 
         let file_path = folder_path.join(file_name);
 
-        // Read and apply mutant
+        // Validate the mutant against the current target, then apply it.
         let mutant_content = fs::read_to_string(&file_path)?;
-        fs::write(&target_file_path, &mutant_content)?;
+        let applied = async {
+            check_mutant_diff(&mutant_diff(folder_path, file_name, target_file_path).await?)
+                .await?;
+            if is_patch(file_name) {
+                apply_diff(&mutant_content).await
+            } else {
+                Ok(fs::write(target_file_path, &mutant_content)?)
+            }
+        };
+        if let Err(e) = applied.await {
+            error!("Skipping {} (not counted): {}", file_name, e);
+            num_errors += 1;
+            if let Some(hash) = patch_hash {
+                record_run_status(run, hash, "error", &command)?;
+            }
+            results.push(MutantResult {
+                name: file_name.clone(),
+                status: "error".to_string(),
+                diff: None,
+                operator: operator_of(file_name),
+            });
+            continue;
+        }
 
         //println!("Running: {}", test_command);
-        let result = run_command(&test_command, timeout_secs).await?;
+        let outcome = run_mutant(&test_command, options.timeout_secs, timeout_secs).await?;
+
+        if outcome == CommandOutcome::BuildFailed && options.keep_on_failure {
+            progress.finish_and_clear();
+            if let Some(hash) = patch_hash {
+                record_run_status(run, hash, outcome.mutant_status(), &command)?;
+            }
+            return Err(MutationError::MutantKept {
+                mutant: file_path.display().to_string(),
+                target: target_file_path.to_string(),
+            });
+        }
 
-        if result {
-            println!("NOT KILLED ❌");
-            not_killed.push(file_name.clone());
+        // The target is reset after each mutant so the next one is checked and
+        // applied against the clean file.
+        if is_patch(file_name) {
+            if let Err(e) = revert_diff(&mutant_content).await {
+                warn!("reverse-apply failed for {}: {}", file_name, e);
+                restore_file(target_file_path).await?;
+            }
         } else {
-            println!("KILLED ✅");
-            num_killed += 1
+            fs::write(target_file_path, &original_content)?;
+        }
+
+        match options.counts_as_killed(outcome.mutant_status()) {
+            Some(false) => {
+                info!("NOT KILLED ❌");
+                not_killed.push(file_name.clone());
+            }
+            Some(true) => {
+                info!("KILLED ✅");
+                num_killed += 1
+            }
+            None => {
+                info!("BUILD ERROR (not counted)");
+                num_build_failed += 1;
+            }
+        }
+
+        if let Some(hash) = patch_hash {
+            if let Some(cache) = cache {
+                cache.put(hash, outcome.mutant_status())?;
+            }
+            record_run_status(run, hash, outcome.mutant_status(), &command)?;
         }
+        results.push(MutantResult {
+            name: file_name.clone(),
+            status: outcome.mutant_status().to_string(),
+            diff: None,
+            operator: operator_of(file_name),
+        });
     }
+    progress.finish_and_clear();
 
     // Generate report
-    let score = num_killed as f64 / total_mutants as f64;
-    println!("\nMUTATION SCORE: {:.2}%", score * 100.0);
-
-    generate_report(
-        &not_killed,
-        folder_path.to_str().unwrap(),
-        &target_file_path,
-        score,
-    )
-    .await?;
+    let counted = total_mutants as u64 - num_build_failed - num_errors - num_skipped;
+    let score = if counted > 0 {
+        num_killed as f64 / counted as f64
+    } else {
+        0.0
+    };
+    info!("\nMUTATION SCORE: {:.2}%", score * 100.0);
+    if num_build_failed > 0 {
+        info!("Build errors (not counted): {}", num_build_failed);
+    }
+    if num_errors > 0 {
+        warn!(
+            "{} mutants did not apply to {} and were not tested",
+            num_errors, target_file_path
+        );
+    }
+    if num_skipped > 0 {
+        info!("Skipped (not counted): {}", num_skipped);
+    }
+
+    let report = if options.format == ReportFormat::Json || options.annotate {
+        generate_report(
+            &not_killed,
+            folder_path.to_str().unwrap(),
+            target_file_path,
+            score,
+        )
+        .await?
+    } else {
+        None
+    };
 
     // Restore the original file
-    restore_file(&target_file_path).await?;
+    restore_file(target_file_path).await?;
+    if compute_patch_hash(&fs::read_to_string(target_file_path)?) != checksum {
+        warn!(
+            "{} no longer matches its content before the analysis",
+            target_file_path
+        );
+    }
+
+    if let (true, Some(report)) = (options.annotate, report.as_ref()) {
+        let annotated = annotate_source(&fs::read_to_string(target_file_path)?, report);
+        let annotated_path = folder_path.join(ANNOTATED_SOURCE_FILE);
+        fs::write(&annotated_path, annotated)?;
+        info!("Annotated source saved to {}", annotated_path.display());
+    }
+    // The JSON report lists every file with its counts, survivors or not.
+    let report = match (options.format, report) {
+        (ReportFormat::Json, Some(report)) => Some(report),
+        (ReportFormat::Json, None) => {
+            let commit = get_git_hash().await?;
+            Some(ReportData::new(
+                target_file_path,
+                &commit,
+                score,
+                HashMap::new(),
+            ))
+        }
+        _ => None,
+    }
+    .map(|report| ReportData {
+        total_mutants: counted,
+        killed_mutants: num_killed,
+        ..report
+    });
+
+    for result in results.iter_mut().filter(|r| r.status == "survived") {
+        result.diff = Some(mutant_diff(folder_path, &result.name, target_file_path).await?);
+    }
+
+    Ok(FolderResult {
+        target_file: target_file_path.to_string(),
+        killed: num_killed,
+        total: counted,
+        mutants: results,
+        baseline_secs: baseline.map(|duration| duration.as_secs_f64()),
+        report,
+    })
+}
+
+/// The command testing the mutants of `target_file_path`: `--command` (and
+/// `--build-command`) if given, otherwise the one derived from the target.
+fn folder_test_command(target_file_path: &str, options: &AnalysisOptions) -> Result<TestCommand> {
+    let test_command = if let Some(ref cmd) = options.command {
+        TestCommand {
+            build: options.build_command.clone(),
+            test: cmd.clone(),
+        }
+        .for_target(target_file_path, options.jobs)?
+    } else {
+        get_command_to_kill(target_file_path, options.jobs)?
+    };
+    Ok(test_command.sandboxed(options.sandbox.as_deref()))
+}
+
+/// Result of testing a single mutant with `analyze --mutant`.
+#[derive(Debug, Serialize)]
+pub struct MutantRun {
+    pub mutant: PathBuf,
+    pub target: String,
+    pub command: String,
+    /// One of `killed`, `survived`, `timeout` or `build_failed`.
+    pub status: String,
+    /// Output of the test command, or of the build command if the mutant failed to build.
+    pub output: String,
+}
+
+impl MutantRun {
+    /// How the analysis of a folder logs this status.
+    pub fn verdict(&self) -> &'static str {
+        match self.status.as_str() {
+            "survived" => "NOT KILLED ❌",
+            "build_failed" => "BUILD ERROR",
+            "timeout" => "KILLED ✅ (timeout)",
+            _ => "KILLED ✅",
+        }
+    }
+}
+
+/// Apply the mutant file `mutant`, whose target is named by the
+/// `original_file.txt` next to it, and test it once, without a baseline run.
+/// The target is restored afterwards.
+pub async fn analyze_mutant(mutant: &Path, options: &AnalysisOptions) -> Result<MutantRun> {
+    let folder_path = match mutant.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = mutant
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|_| mutant.is_file())
+        .ok_or_else(|| {
+            MutationError::InvalidInput(format!("{} is not a mutant file", mutant.display()))
+        })?;
+
+    let target_file_path = read_target_file(folder_path)?;
+    let target_file_path = target_file_path.as_str();
+    check_target_file(folder_path, target_file_path).await?;
+    check_clean_target(target_file_path, options.force).await?;
+    let test_command = folder_test_command(target_file_path, options)?;
+
+    let mutant_content = fs::read_to_string(mutant)?;
+    let original_content = fs::read_to_string(target_file_path)?;
+    check_mutant_diff(&mutant_diff(folder_path, file_name, target_file_path).await?).await?;
+    if is_patch(file_name) {
+        apply_diff(&mutant_content).await?;
+    } else {
+        fs::write(target_file_path, &mutant_content)?;
+    }
 
+    info!("Running: {}", test_command);
+    let outcome =
+        run_mutant_output(&test_command, options.timeout_secs, options.timeout_secs).await;
+
+    if is_patch(file_name) {
+        if let Err(e) = revert_diff(&mutant_content).await {
+            warn!("reverse-apply failed for {}: {}", file_name, e);
+            restore_file(target_file_path).await?;
+        }
+    } else {
+        fs::write(target_file_path, &original_content)?;
+    }
+
+    let (outcome, output) = outcome?;
+    Ok(MutantRun {
+        mutant: mutant.to_path_buf(),
+        target: target_file_path.to_string(),
+        command: test_command.to_string(),
+        status: outcome.mutant_status().to_string(),
+        output,
+    })
+}
+
+/// Per-mutant timeout derived from the unmutated test's duration. It never goes
+/// below [`MIN_AUTO_TIMEOUT_SECS`], so short test runs leave room for noise.
+/// It bounds only the test step: the build step keeps `--timeout`.
+fn auto_timeout(baseline: Duration, factor: f64) -> u64 {
+    ((baseline.as_secs_f64() * factor).ceil() as u64).max(MIN_AUTO_TIMEOUT_SECS)
+}
+
+/// Write a folder mutant's status, and the command it was tested with, back to
+/// its row in the SQLite run, if any.
+fn record_run_status(
+    run: Option<(&Database, i64)>,
+    patch_hash: &str,
+    status: &str,
+    command: &str,
+) -> Result<()> {
+    if let Some((db, run_id)) = run {
+        if !db.update_status_mutant(run_id, patch_hash, status, command)? {
+            warn!(
+                "mutant {} not found in run_id={}",
+                &patch_hash[..12.min(patch_hash.len())],
+                run_id
+            );
+        }
+    }
     Ok(())
 }
 
+/// Sort `mutant_files` and shuffle them deterministically from `seed`, so the
+/// early-exit survival rate is estimated on a representative sample.
+fn shuffle_mutants(mutant_files: &mut [String], seed: u64) {
+    mutant_files.sort();
+    let mut rng = StdRng::seed_from_u64(seed);
+    mutant_files.shuffle(&mut rng);
+}
+
+/// How a test command run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandOutcome {
+    Success,
+    Failure,
+    Timeout,
+    /// The mutant's build step failed, so its tests never ran.
+    BuildFailed,
+}
+
+impl CommandOutcome {
+    /// Status recorded for a mutant whose test command ended this way.
+    pub fn mutant_status(self) -> &'static str {
+        match self {
+            CommandOutcome::Success => "survived",
+            CommandOutcome::Failure => "killed",
+            CommandOutcome::Timeout => "timeout",
+            CommandOutcome::BuildFailed => "build_failed",
+        }
+    }
+}
+
+/// Build and test the currently applied mutant, giving each step its own timeout.
+async fn run_mutant(
+    command: &TestCommand,
+    build_timeout_secs: u64,
+    test_timeout_secs: u64,
+) -> Result<CommandOutcome> {
+    Ok(
+        run_mutant_output(command, build_timeout_secs, test_timeout_secs)
+            .await?
+            .0,
+    )
+}
+
+/// [`run_mutant`] on the unmutated target, also returning how long the test
+/// step alone took, so the build does not inflate the derived timeout.
+async fn run_baseline(
+    command: &TestCommand,
+    build_timeout_secs: u64,
+    test_timeout_secs: u64,
+) -> Result<(CommandOutcome, Duration)> {
+    let build_only = TestCommand {
+        build: command.build.clone(),
+        test: "true".to_string(),
+    };
+    if command.build.is_some() {
+        let outcome = run_mutant(&build_only, build_timeout_secs, test_timeout_secs).await?;
+        if outcome != CommandOutcome::Success {
+            return Ok((outcome, Duration::ZERO));
+        }
+    }
+    let start = Instant::now();
+    let outcome = run_command_outcome(&command.test, test_timeout_secs).await?;
+    Ok((outcome, start.elapsed()))
+}
+
+/// [`run_mutant`], also returning the output of the test command, or of the
+/// build step if the mutant does not compile in time.
+async fn run_mutant_output(
+    command: &TestCommand,
+    build_timeout_secs: u64,
+    test_timeout_secs: u64,
+) -> Result<(CommandOutcome, String)> {
+    if let Some(ref build) = command.build {
+        let (outcome, output) = run_command_output(build, build_timeout_secs).await?;
+        if outcome != CommandOutcome::Success {
+            info!("{}", MutationError::MutantBuildFailed(build.to_string()));
+            return Ok((CommandOutcome::BuildFailed, output));
+        }
+    }
+    run_command_output(&command.test, test_timeout_secs).await
+}
+
 async fn run_command(command: &str, timeout_secs: u64) -> Result<bool> {
+    Ok(run_command_outcome(command, timeout_secs).await? == CommandOutcome::Success)
+}
+
+async fn run_command_outcome(command: &str, timeout_secs: u64) -> Result<CommandOutcome> {
+    Ok(run_command_output(command, timeout_secs).await?.0)
+}
+
+/// Run `command` in a shell, returning how it ended and its stdout followed by
+/// its stderr (empty if it timed out).
+async fn run_command_output(command: &str, timeout_secs: u64) -> Result<(CommandOutcome, String)> {
     use std::process::Stdio;
 
     // Split command into shell and arguments for better cross-platform support
@@ -310,7 +2047,7 @@ async fn run_command(command: &str, timeout_secs: u64) -> Result<bool> {
         ("sh", "-c")
     };
 
-    println!("Executing command: {}", command);
+    debug!("Executing command: {}", command);
 
     let mut cmd = TokioCommand::new(shell);
     cmd.arg(shell_arg)
@@ -326,34 +2063,41 @@ async fn run_command(command: &str, timeout_secs: u64) -> Result<bool> {
             let stdout = String::from_utf8_lossy(&output.stdout);
             let stderr = String::from_utf8_lossy(&output.stderr);
 
-            println!("Command exit code: {}", output.status.code().unwrap_or(-1));
+            debug!("Command exit code: {}", output.status.code().unwrap_or(-1));
 
             if !stdout.is_empty() {
-                println!("STDOUT:\n{}", stdout);
+                debug!("STDOUT:\n{}", stdout);
             }
 
             if !stderr.is_empty() {
-                println!("STDERR:\n{}", stderr);
+                debug!("STDERR:\n{}", stderr);
             }
 
-            Ok(output.status.success())
+            let outcome = if output.status.success() {
+                CommandOutcome::Success
+            } else {
+                CommandOutcome::Failure
+            };
+            Ok((outcome, format!("{}{}", stdout, stderr)))
         }
         Ok(Err(e)) => {
-            println!("Command execution failed: {}", e);
-            Ok(false)
+            warn!("Command execution failed: {}", e);
+            Ok((CommandOutcome::Failure, e.to_string()))
         }
         Err(_) => {
-            println!("Command timed out after {} seconds", timeout_secs);
-            Ok(false)
+            info!("Command timed out after {} seconds", timeout_secs);
+            Ok((CommandOutcome::Timeout, String::new()))
         }
     }
 }
 
-async fn run_build_command() -> Result<()> {
-    let build_command =
-        "rm -rf build && cmake -B build -DENABLE_IPC=OFF && cmake --build build -j $(nproc)";
+async fn run_build_command(sandbox: Option<&str>) -> Result<()> {
+    let build_command = sandboxed(
+        "rm -rf build && cmake -B build -DENABLE_IPC=OFF && cmake --build build -j $(nproc)",
+        sandbox,
+    );
 
-    let success = run_command(build_command, 3600).await?; // 1 hour timeout for build
+    let success = run_command(&build_command, 3600).await?; // 1 hour timeout for build
     if !success {
         return Err(MutationError::Command("Build command failed".to_string()));
     }
@@ -361,39 +2105,101 @@ async fn run_build_command() -> Result<()> {
     Ok(())
 }
 
-fn get_command_to_kill(target_file_path: &str, jobs: u32) -> Result<String> {
+fn get_command_to_kill(target_file_path: &str, jobs: u32) -> Result<TestCommand> {
+    command_to_kill(target_file_path, jobs, cfg!(target_os = "windows"))
+}
+
+/// [`get_command_to_kill`] for Windows or Unix. On Windows, commands run in
+/// `cmd`: paths use `\`, Python scripts are run through `python` and the
+/// multi-config build puts `test_bitcoin.exe` in `bin\Release`.
+fn command_to_kill(target_file_path: &str, jobs: u32, windows: bool) -> Result<TestCommand> {
     let mut build_command = "cmake --build build".to_string();
+    if windows {
+        build_command.push_str(" --config Release");
+    }
     if jobs > 0 {
         build_command.push_str(&format!(" -j{}", jobs));
     }
 
-    let command = if target_file_path.contains("functional") {
-        format!("./build/{}", target_file_path)
-    } else if target_file_path.contains("test") {
-        let filename_with_extension = Path::new(target_file_path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .ok_or_else(|| MutationError::InvalidInput("Invalid file path".to_string()))?;
-
-        let test_to_run = filename_with_extension
-            .rsplit('.')
-            .nth(1)
-            .ok_or_else(|| MutationError::InvalidInput("Cannot extract test name".to_string()))?;
+    let build_path = |path: &str| {
+        if windows {
+            format!("build\\{}", path.replace('/', "\\"))
+        } else {
+            format!("./build/{}", path)
+        }
+    };
+    let python_script = |path: &str| {
+        if windows {
+            format!("python {}", build_path(path))
+        } else {
+            build_path(path)
+        }
+    };
 
-        format!(
-            "{} && ./build/bin/test_bitcoin --run_test={}",
-            build_command, test_to_run
-        )
+    let command = if target_file_path.contains("functional") {
+        TestCommand {
+            build: None,
+            test: python_script(target_file_path),
+        }
+    } else if let Some(test_to_run) = unit_test_suite(target_file_path) {
+        let test_bitcoin = if windows {
+            build_path("bin/Release/test_bitcoin.exe")
+        } else {
+            build_path("bin/test_bitcoin")
+        };
+        TestCommand {
+            build: Some(build_command),
+            test: format!("{} --run_test={}", test_bitcoin, test_to_run),
+        }
     } else {
-        format!(
-            "{} && ctest --output-on-failure --stop-on-failure -C Release && CI_FAILFAST_TEST_LEAVE_DANGLING=1 ./build/test/functional/test_runner.py -F",
-            build_command
-        )
+        let leave_dangling = if windows {
+            "set \"CI_FAILFAST_TEST_LEAVE_DANGLING=1\" && "
+        } else {
+            "CI_FAILFAST_TEST_LEAVE_DANGLING=1 "
+        };
+        TestCommand {
+            build: Some(build_command),
+            test: format!(
+                "ctest --output-on-failure --stop-on-failure -C Release && {}{} -F",
+                leave_dangling,
+                python_script("test/functional/test_runner.py")
+            ),
+        }
     };
 
     Ok(command)
 }
 
+/// Return the boost test suite defined by a unit test file: a `*_tests.cpp`
+/// file directly inside a `test` directory (e.g. `src/test/util_tests.cpp`,
+/// `src/wallet/test/wallet_tests.cpp`). Test utilities such as
+/// `src/test/util/setup_common.cpp` define no suite.
+fn unit_test_suite(target_file_path: &str) -> Option<&str> {
+    let path = Path::new(target_file_path);
+    let in_test_dir = path
+        .parent()
+        .and_then(|dir| dir.file_name())
+        .is_some_and(|dir| dir == "test");
+    if !in_test_dir {
+        return None;
+    }
+    path.file_name()?
+        .to_str()?
+        .strip_suffix(".cpp")
+        .filter(|stem| stem.ends_with("_tests"))
+}
+
+/// Write `content`, read before the analysis, back to `target_file_path` if
+/// its checksum no longer matches.
+fn restore_if_mutated(target_file_path: &str, content: &str) -> Result<()> {
+    let current = fs::read_to_string(target_file_path)?;
+    if compute_patch_hash(&current) != compute_patch_hash(content) {
+        warn!("{} was left mutated, restoring it", target_file_path);
+        fs::write(target_file_path, content)?;
+    }
+    Ok(())
+}
+
 async fn restore_file(target_file_path: &str) -> Result<()> {
     let restore_command = format!("git restore {}", target_file_path);
     let success = run_command(&restore_command, 30).await?;
@@ -409,29 +2215,794 @@ async fn restore_file(target_file_path: &str) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{enter_dir, init_git_repo};
     use std::fs;
     use tempfile::tempdir;
 
+    /// The clean target of [`patch_mutant_folder`].
+    const TEST_CPP: &str = "int f(int a, int b) {\n    return a + b;\n}\n";
+
+    /// Commit `test.cpp` and write its mutation folder: patches 0 (`a - b`),
+    /// 1 (`int c)`) and 2 (generated from another version of the target), then
+    /// full copies 3 (`a - b`) and 4 (`int c)`). Returns the folder and the
+    /// diff of mutant 1.
+    async fn patch_mutant_folder() -> (&'static Path, String) {
+        init_git_repo(&[("test.cpp", TEST_CPP)]);
+
+        let folder = Path::new("muts-test-cpp");
+        fs::create_dir(folder).unwrap();
+        fs::write(folder.join("original_file.txt"), "test.cpp").unwrap();
+        let killed = generate_diff("test.cpp", &TEST_CPP.replace("a + b", "a - b"))
+            .await
+            .unwrap();
+        let survived = generate_diff("test.cpp", &TEST_CPP.replace("int b)", "int c)"))
+            .await
+            .unwrap();
+        let stale = killed.replace("-    return a + b;", "-    return a * b;");
+        fs::write(folder.join("test.mutant.0.patch"), killed).unwrap();
+        fs::write(folder.join("test.mutant.1.patch"), &survived).unwrap();
+        fs::write(folder.join("test.mutant.2.patch"), stale).unwrap();
+        fs::write(
+            folder.join("test.mutant.3.cpp"),
+            TEST_CPP.replace("a + b", "a - b"),
+        )
+        .unwrap();
+        fs::write(
+            folder.join("test.mutant.4.cpp"),
+            TEST_CPP.replace("int b)", "int c)"),
+        )
+        .unwrap();
+        (folder, survived)
+    }
+
+    /// Options testing the mutants of [`patch_mutant_folder`] with `command`,
+    /// without early termination.
+    fn folder_options(command: &str) -> AnalysisOptions {
+        AnalysisOptions {
+            command: Some(command.to_string()),
+            timeout_secs: 30,
+            survival_threshold: 1.0,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_analyze_folder_applies_patch_mutants() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let (folder, survived) = patch_mutant_folder().await;
+
+        let options = AnalysisOptions {
+            timeout_factor: Some(3.0),
+            ..folder_options("grep -q 'a + b' test.cpp")
+        };
+        let result = analyze_folder(folder, &options, None, None).await.unwrap();
+
+        assert!(result.baseline_secs.is_some());
+        assert_eq!((result.killed, result.total), (2, 4));
+        // A mutant generated from another version of the target is not tested.
+        assert_eq!(result.mutants[2].status, "error");
+        // Full copies are checked against the clean target, not the previous mutant.
+        assert_eq!(result.mutants[4].status, "survived");
+        let survivor = result
+            .mutants
+            .iter()
+            .find(|m| m.status == "survived")
+            .unwrap();
+        assert_eq!(survivor.diff.as_deref(), Some(survived.as_str()));
+        assert_eq!(fs::read_to_string("test.cpp").unwrap(), TEST_CPP);
+    }
+
+    #[tokio::test]
+    async fn test_file_survival_threshold_stops_early() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let (folder, _) = patch_mutant_folder().await;
+
+        // A per-file threshold overrides the global one: the first survivor
+        // stops the analysis.
+        let options = AnalysisOptions {
+            file_survival_thresholds: FileThresholds::new(&BTreeMap::from([(
+                "*.cpp".to_string(),
+                0.0,
+            )]))
+            .unwrap(),
+            ..folder_options("grep -q 'a + b' test.cpp")
+        };
+        let result = analyze_folder(folder, &options, None, None).await.unwrap();
+        assert_eq!(result.mutants.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failing_baseline_aborts_analysis() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let (folder, _) = patch_mutant_folder().await;
+
+        // A command that already fails on the clean target aborts the analysis.
+        let mut options = folder_options("grep -q 'a - b' test.cpp");
+        let err = analyze_folder(folder, &options, None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MutationError::BaselineFailed(_)));
+
+        options.skip_baseline_check = true;
+        let result = analyze_folder(folder, &options, None, None).await.unwrap();
+        assert_eq!(result.baseline_secs, None);
+    }
+
+    #[tokio::test]
+    async fn test_keep_on_failure() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let (folder, _) = patch_mutant_folder().await;
+
+        // The first mutant that fails to build is left applied for debugging.
+        let options = AnalysisOptions {
+            build_command: Some("! grep -q 'int c)' test.cpp".to_string()),
+            keep_on_failure: true,
+            skip_baseline_check: true,
+            ..folder_options("grep -q 'a - b' test.cpp")
+        };
+        let err = analyze_folder(folder, &options, None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MutationError::MutantKept { ref mutant, .. } if mutant.ends_with("test.mutant.1.patch")
+        ));
+        assert!(fs::read_to_string("test.cpp").unwrap().contains("int c)"));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_mutant() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let (folder, _) = patch_mutant_folder().await;
+
+        // A single mutant is tested once, and its output returned.
+        let options = folder_options("grep 'a - b' test.cpp");
+        let run = analyze_mutant(&folder.join("test.mutant.0.patch"), &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            (run.target.as_str(), run.status.as_str()),
+            ("test.cpp", "survived")
+        );
+        assert!(run.output.contains("return a - b;"));
+        assert_eq!(fs::read_to_string("test.cpp").unwrap(), TEST_CPP);
+
+        let run = analyze_mutant(&folder.join("test.mutant.4.cpp"), &options)
+            .await
+            .unwrap();
+        assert_eq!(run.verdict(), "KILLED ✅");
+        assert_eq!(fs::read_to_string("test.cpp").unwrap(), TEST_CPP);
+        assert!(analyze_mutant(&folder.join("test.mutant.9.cpp"), &options)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deadline_skips_mutants() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let (folder, _) = patch_mutant_folder().await;
+
+        // Once the deadline has passed, no mutant is tested.
+        let options = AnalysisOptions {
+            deadline: Some(Instant::now()),
+            ..folder_options("grep -q 'a + b' test.cpp")
+        };
+        let result = analyze_folder(folder, &options, None, None).await.unwrap();
+        assert!(result.mutants.iter().all(|m| m.status == "skipped"));
+        assert_eq!(result.total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_coverage_skips_uncovered_mutants() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let (folder, _) = patch_mutant_folder().await;
+
+        // With coverage, only the mutants of covered lines are tested.
+        let options = AnalysisOptions {
+            coverage: Some(HashMap::from([("test.cpp".to_string(), vec![2])])),
+            ..folder_options("grep -q 'a + b' test.cpp")
+        };
+        let result = analyze_folder(folder, &options, None, None).await.unwrap();
+        let skipped: Vec<_> = result
+            .mutants
+            .iter()
+            .filter(|m| m.status == "skipped")
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(skipped, ["test.mutant.1.patch", "test.mutant.4.cpp"]);
+    }
+
+    #[tokio::test]
+    async fn test_dirty_target_needs_force() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let (folder, _) = patch_mutant_folder().await;
+
+        // Uncommitted changes to the target are not discarded without --force.
+        let mut options = folder_options("grep -q 'a + b' test.cpp");
+        let edited = TEST_CPP.replace("int f", "long f");
+        fs::write("test.cpp", &edited).unwrap();
+        let err = analyze_folder(folder, &options, None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MutationError::DirtyTarget(_)));
+        assert_eq!(fs::read_to_string("test.cpp").unwrap(), edited);
+
+        options.force = true;
+        analyze_folder(folder, &options, None, None).await.unwrap();
+        assert_eq!(fs::read_to_string("test.cpp").unwrap(), TEST_CPP);
+    }
+
+    #[tokio::test]
+    async fn test_missing_target() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let (folder, _) = patch_mutant_folder().await;
+
+        // The target must still exist and be tracked.
+        let options = folder_options("grep -q 'a + b' test.cpp");
+        fs::rename("test.cpp", "moved.cpp").unwrap();
+        let err = analyze_folder(folder, &options, None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MutationError::MissingTarget {
+                reason: "does not exist",
+                ..
+            }
+        ));
+        fs::write(folder.join("original_file.txt"), "moved.cpp").unwrap();
+        let err = analyze_folder(folder, &options, None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MutationError::MissingTarget {
+                reason: "is not tracked by git",
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_analysis_cache_needs_a_commit() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let db = Database::open(Path::new("mutation.db")).unwrap();
+        db.ensure_schema().unwrap();
+
+        // Outside a repository, results are not cached under a made-up commit.
+        assert!(AnalysisCache::new(&db, false).await.is_none());
+
+        init_git_repo(&[]);
+        let cache = AnalysisCache::new(&db, false).await.unwrap();
+        cache.put("hash", "killed").unwrap();
+        assert_eq!(cache.get("hash").unwrap().as_deref(), Some("killed"));
+    }
+
+    #[test]
+    fn test_auto_timeout() {
+        assert_eq!(auto_timeout(Duration::from_secs(100), 3.0), 300);
+        assert_eq!(auto_timeout(Duration::from_millis(100_100), 2.0), 201);
+        assert_eq!(
+            auto_timeout(Duration::from_secs(1), 3.0),
+            MIN_AUTO_TIMEOUT_SECS
+        );
+    }
+
+    #[test]
+    fn test_command_placeholders() {
+        let command = TestCommand {
+            build: Some("cmake --build build -j{build_jobs}".to_string()),
+            test: "./build/bin/test_bitcoin --run_test={test_name} # {target}".to_string(),
+        };
+        let expanded = command.for_target("src/test/util_tests.cpp", 8).unwrap();
+        assert_eq!(
+            expanded.to_string(),
+            "cmake --build build -j8 && ./build/bin/test_bitcoin --run_test=util_tests # src/test/util_tests.cpp"
+        );
+        assert!(command.for_target("src/wallet/wallet.cpp", 8).is_err());
+
+        let functional = "build/test/functional/test_runner.py {test_name}";
+        assert_eq!(
+            expand_placeholders(functional, "test/functional/wallet_basic.py", 0).unwrap(),
+            "build/test/functional/test_runner.py wallet_basic.py"
+        );
+        // Braces that are not placeholders are kept; `{{`/`}}` escape them.
+        assert_eq!(
+            expand_placeholders("find . -exec true {} \\; && echo {{target}}", "a.cpp", 0).unwrap(),
+            "find . -exec true {} \\; && echo {target}"
+        );
+        assert!(expand_placeholders("make -j{jobs}", "a.cpp", 0).is_err());
+        // Shell variables and unpaired doubled braces are not touched.
+        assert_eq!(
+            expand_placeholders("make -j${JOBS} && echo }} {{", "a.cpp", 0).unwrap(),
+            "make -j${JOBS} && echo }} {{"
+        );
+        assert_eq!(
+            expand_placeholders("awk '{{ if ($1) {{ print }} }}' {target}", "a.cpp", 0).unwrap(),
+            "awk '{ if ($1) { print } }' a.cpp"
+        );
+    }
+
+    #[test]
+    fn test_sandboxed_command() {
+        assert_eq!(sandboxed("make check", None), "make check");
+        assert_eq!(
+            sandboxed("echo 'a b' && true", Some("docker run --rm img")),
+            r"docker run --rm img sh -c 'echo '\''a b'\'' && true'"
+        );
+        let cmd = TestCommand {
+            build: Some("make".to_string()),
+            test: "make check".to_string(),
+        }
+        .sandboxed(Some("systemd-run --scope -p MemoryMax=4G"));
+        assert_eq!(
+            cmd.to_string(),
+            "systemd-run --scope -p MemoryMax=4G sh -c 'make' && \
+             systemd-run --scope -p MemoryMax=4G sh -c 'make check'"
+        );
+    }
+
     #[test]
+    #[cfg(not(windows))]
     fn test_get_command_to_kill() {
         // Test functional test
         let cmd = get_command_to_kill("test/functional/test_example.py", 4).unwrap();
-        assert_eq!(cmd, "./build/test/functional/test_example.py");
+        assert_eq!(cmd.build, None);
+        assert_eq!(cmd.to_string(), "./build/test/functional/test_example.py");
 
         // Test unit test
-        let cmd = get_command_to_kill("src/test/test_example.cpp", 0).unwrap();
+        let cmd = get_command_to_kill("src/test/example_tests.cpp", 0).unwrap();
+        assert_eq!(cmd.build.as_deref(), Some("cmake --build build"));
         assert_eq!(
-            cmd,
-            "cmake --build build && ./build/bin/test_bitcoin --run_test=test_example"
+            cmd.to_string(),
+            "cmake --build build && ./build/bin/test_bitcoin --run_test=example_tests"
         );
 
+        // Test utilities are not boost suites and fall back to the general case
+        let cmd = get_command_to_kill("src/test/util/setup_common.cpp", 0)
+            .unwrap()
+            .to_string();
+        assert!(!cmd.contains("--run_test"));
+        assert!(cmd.contains("ctest"));
+
         // Test general case
-        let cmd = get_command_to_kill("src/wallet/wallet.cpp", 2).unwrap();
+        let cmd = get_command_to_kill("src/wallet/wallet.cpp", 2)
+            .unwrap()
+            .to_string();
         assert!(cmd.contains("cmake --build build -j2"));
         assert!(cmd.contains("ctest"));
         assert!(cmd.contains("test_runner.py"));
     }
 
+    #[test]
+    fn test_command_to_kill_on_windows() {
+        let cmd = command_to_kill("test/functional/test_example.py", 4, true).unwrap();
+        assert_eq!(cmd.build, None);
+        assert_eq!(
+            cmd.to_string(),
+            "python build\\test\\functional\\test_example.py"
+        );
+
+        let cmd = command_to_kill("src/test/example_tests.cpp", 0, true).unwrap();
+        assert_eq!(
+            cmd.to_string(),
+            "cmake --build build --config Release && build\\bin\\Release\\test_bitcoin.exe --run_test=example_tests"
+        );
+
+        let cmd = command_to_kill("src/wallet/wallet.cpp", 2, true)
+            .unwrap()
+            .to_string();
+        assert!(cmd.starts_with("cmake --build build --config Release -j2 && ctest"));
+        assert!(cmd.ends_with(
+            "&& set \"CI_FAILFAST_TEST_LEAVE_DANGLING=1\" && python build\\test\\functional\\test_runner.py -F"
+        ));
+        assert!(!cmd.contains('/'));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_get_command_to_kill() {
+        let cmd = get_command_to_kill("src/test/example_tests.cpp", 0).unwrap();
+        assert_eq!(
+            cmd.test,
+            "build\\bin\\Release\\test_bitcoin.exe --run_test=example_tests"
+        );
+        let cmd = get_command_to_kill("test/functional/test_example.py", 0).unwrap();
+        assert!(cmd.test.starts_with("python "));
+    }
+
+    #[test]
+    fn test_analysis_summary_json() {
+        let result = |killed, total, mutants| FolderResult {
+            target_file: "src/a.cpp".to_string(),
+            killed,
+            total,
+            mutants,
+            baseline_secs: None,
+            report: None,
+        };
+        let survivor = MutantResult {
+            name: "a.mutant.1.cpp".to_string(),
+            status: "survived".to_string(),
+            diff: Some("-x\n+y".to_string()),
+            operator: None,
+        };
+        let options = AnalysisOptions::default();
+        let summary = AnalysisSummary::new(
+            vec![result(3, 4, vec![survivor]), result(1, 1, vec![])],
+            &options,
+        );
+        assert_eq!((summary.killed, summary.survived, summary.total), (4, 1, 5));
+        assert_eq!(summary.score, Some(0.8));
+        let survivors: Vec<_> = summary.survivors().collect();
+        assert_eq!(survivors.len(), 1);
+        assert_eq!(survivors[0].0, "src/a.cpp");
+        assert_eq!(survivors[0].1.name, "a.mutant.1.cpp");
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["files"][0]["mutants"][0]["diff"], "-x\n+y");
+        assert!(json["files"][0].get("report").is_none());
+        assert_eq!(AnalysisSummary::new(Vec::new(), &options).score, None);
+    }
+
+    #[test]
+    fn test_operator_stats() {
+        let mutant = |operator: &str, status: &str| MutantResult {
+            name: "a.mutant.0.cpp".to_string(),
+            status: status.to_string(),
+            diff: None,
+            operator: Some(operator.to_string()),
+        };
+        let files = vec![FolderResult {
+            target_file: "src/a.cpp".to_string(),
+            killed: 3,
+            total: 5,
+            mutants: vec![
+                mutant("==", "killed"),
+                mutant("==", "survived"),
+                mutant("+", "timeout"),
+                mutant("+", "build_failed"),
+                mutant("<", "survived"),
+            ],
+            baseline_secs: None,
+            report: None,
+        }];
+
+        let mut options = AnalysisOptions::default();
+        let stats = operator_stats(&files, &options);
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[0].operator.as_deref(), Some("+"));
+        assert_eq!((stats[0].total, stats[0].killed), (2, 2));
+        assert_eq!(stats[0].kill_rate, Some(1.0));
+        assert_eq!(stats[0].survivor_share, Some(0.0));
+        assert_eq!(stats[2].operator.as_deref(), Some("=="));
+        assert_eq!(stats[2].kill_rate, Some(0.5));
+        assert_eq!(stats[2].survivor_share, Some(0.5));
+
+        options.exclude_build_failures = true;
+        let stats = operator_stats(&files, &options);
+        assert_eq!((stats[0].total, stats[0].killed), (1, 1));
+
+        // Without a known operator, the file is still written.
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("operators.json");
+        fs::write(&path, "stale").unwrap();
+        let options = AnalysisOptions {
+            operator_stats_path: Some(path.clone()),
+            ..Default::default()
+        };
+        let unknown = OperatorStats {
+            operator: None,
+            total: 2,
+            killed: 1,
+            survived: 1,
+            kill_rate: Some(0.5),
+            survivor_share: Some(1.0),
+        };
+        report_operator_stats(&[unknown], &options).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_check_fail_under() {
+        let summary = AnalysisSummary {
+            killed: 3,
+            survived: 1,
+            skipped: 0,
+            total: 4,
+            score: Some(0.75),
+            files: Vec::new(),
+            operators: Vec::new(),
+            failed: Vec::new(),
+        };
+        let no_thresholds = FileThresholds::default();
+        assert!(summary.check_fail_under(0.75, &no_thresholds).is_ok());
+        let err = summary.check_fail_under(0.8, &no_thresholds).unwrap_err();
+        assert!(matches!(err, MutationError::ScoreBelowThreshold { .. }));
+        assert_eq!(err.exit_code(), 3);
+        assert!(AnalysisSummary::default()
+            .check_fail_under(1.0, &no_thresholds)
+            .is_ok());
+
+        // A file with its own threshold is held to it instead of --fail-under.
+        let file = |target_file: &str, killed, total| FolderResult {
+            target_file: target_file.to_string(),
+            killed,
+            total,
+            mutants: Vec::new(),
+            baseline_secs: None,
+            report: None,
+        };
+        let summary = AnalysisSummary::new(
+            vec![
+                file("src/util/strencodings.cpp", 6, 10),
+                file("src/validation.cpp", 19, 20),
+            ],
+            &AnalysisOptions::default(),
+        );
+        let thresholds = |validation: f64| {
+            FileThresholds::new(&BTreeMap::from([
+                ("src/util/**".to_string(), 0.4),
+                ("src/validation.cpp".to_string(), validation),
+            ]))
+            .unwrap()
+        };
+        assert!(summary.check_fail_under(0.9, &no_thresholds).is_err());
+        assert!(summary.check_fail_under(0.9, &thresholds(0.05)).is_ok());
+        let err = summary
+            .check_fail_under(0.9, &thresholds(0.04))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MutationError::FileSurvivalAboveThreshold { ref file, .. } if file == "src/validation.cpp"
+        ));
+        assert_eq!(err.exit_code(), 3);
+    }
+
+    #[test]
+    fn test_counts_as_killed() {
+        let mut options = AnalysisOptions::default();
+        assert_eq!(options.counts_as_killed("survived"), Some(false));
+        assert_eq!(options.counts_as_killed("timeout"), Some(true));
+        assert_eq!(options.counts_as_killed("error"), None);
+        assert_eq!(options.counts_as_killed("build_failed"), Some(true));
+
+        options.exclude_build_failures = true;
+        assert_eq!(options.counts_as_killed("build_failed"), None);
+        assert_eq!(options.counts_as_killed("killed"), Some(true));
+        assert_eq!(options.counts_as_killed("equivalent"), None);
+        assert_eq!(options.counts_as_killed("pending"), None);
+    }
+
+    #[test]
+    fn test_unit_test_suite() {
+        assert_eq!(
+            unit_test_suite("src/test/util_tests.cpp"),
+            Some("util_tests")
+        );
+        assert_eq!(
+            unit_test_suite("src/wallet/test/wallet_tests.cpp"),
+            Some("wallet_tests")
+        );
+        assert_eq!(unit_test_suite("src/test/util/setup_common.cpp"), None);
+        assert_eq!(unit_test_suite("src/test/util/random.h"), None);
+        assert_eq!(unit_test_suite("src/wallet/test/util.cpp"), None);
+        assert_eq!(unit_test_suite("src/test/fuzz/tx_pool.cpp"), None);
+        assert_eq!(unit_test_suite("src/txmempool.cpp"), None);
+    }
+
+    #[test]
+    fn test_dry_run_folder() {
+        let temp_dir = tempdir().unwrap();
+        let folder = temp_dir.path().join("muts-test-cpp");
+        fs::create_dir(&folder).unwrap();
+
+        // A folder without original_file.txt is rejected.
+        assert!(dry_run_folder(&folder, &AnalysisOptions::default()).is_err());
+
+        fs::write(folder.join("original_file.txt"), "src/wallet/wallet.cpp").unwrap();
+        fs::write(folder.join("wallet.mutant.0.cpp"), "mutated").unwrap();
+        fs::write(folder.join("wallet.mutant.1.cpp"), "mutated").unwrap();
+
+        let mutants = list_mutant_files(&folder, &AnalysisOptions::default()).unwrap();
+        assert_eq!(mutants.len(), 2);
+        assert!(dry_run_folder(&folder, &AnalysisOptions::default()).is_ok());
+    }
+
+    /// Store a run with two mutants of a committed `a.cpp` in `mutation.db`:
+    /// one that applies and one whose diff does not. Returns the options to
+    /// analyze the run with a test command that kills every mutant.
+    fn run_with_stale_mutant() -> AnalysisOptions {
+        use crate::db::MutantData;
+
+        init_git_repo(&[("a.cpp", "x = 1;\ny = 2;\n")]);
+        let header = "diff --git a/a.cpp b/a.cpp\n--- a/a.cpp\n+++ b/a.cpp\n";
+        let mutant = |hunk: &str, patch_hash: &str| MutantData {
+            diff: format!("{}{}", header, hunk),
+            patch_hash: patch_hash.to_string(),
+            file_path: "a.cpp".to_string(),
+            operator: "op".to_string(),
+        };
+
+        let mut db = Database::open(Path::new("mutation.db")).unwrap();
+        db.ensure_schema().unwrap();
+        db.seed_projects().unwrap();
+        let project_id = db.get_bitcoin_core_project_id().unwrap();
+        let run_id = db
+            .create_run(project_id, "abc", "0.0.0", None, None, None)
+            .unwrap();
+        db.insert_mutant_batch(
+            run_id,
+            &[
+                mutant("@@ -1,2 +1,2 @@\n-x = 1;\n+x = 0;\n y = 2;\n", "h1"),
+                mutant("@@ -1,2 +1,2 @@\n-z = 1;\n+z = 0;\n y = 2;\n", "h2"),
+            ],
+        )
+        .unwrap();
+
+        AnalysisOptions {
+            command: Some("false".to_string()),
+            sqlite_path: Some(PathBuf::from("mutation.db")),
+            run_id: Some(run_id),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_db_analysis_leaves_errors_out_of_the_score() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let options = run_with_stale_mutant();
+
+        let summary = run_analysis(None, options).await.unwrap();
+        assert_eq!((summary.killed, summary.total), (1, 1));
+        assert_eq!(summary.score, Some(1.0));
+        let statuses: Vec<&str> = summary.files[0]
+            .mutants
+            .iter()
+            .map(|m| m.status.as_str())
+            .collect();
+        assert_eq!(statuses, ["killed", "error"]);
+        assert_eq!(fs::read_to_string("a.cpp").unwrap(), "x = 1;\ny = 2;\n");
+    }
+
+    #[tokio::test]
+    async fn test_db_analysis_fail_under_ignores_errors() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let options = run_with_stale_mutant();
+
+        // The mutant that did not apply is not a survivor.
+        let summary = run_analysis(None, options).await.unwrap();
+        assert!(summary
+            .check_fail_under(1.0, &FileThresholds::default())
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_leaves_database_alone() {
+        let temp_dir = tempdir().unwrap();
+        let folder = temp_dir.path().join("muts-test-cpp");
+        fs::create_dir(&folder).unwrap();
+        fs::write(folder.join("original_file.txt"), "src/wallet/wallet.cpp").unwrap();
+        fs::write(folder.join("wallet.mutant.0.cpp"), "mutated").unwrap();
+
+        let db_path = temp_dir.path().join("mutation.db");
+        let options = AnalysisOptions {
+            dry_run: true,
+            sqlite_path: Some(db_path.clone()),
+            ..Default::default()
+        };
+        run_analysis(Some(folder), options).await.unwrap();
+        assert!(!db_path.exists());
+    }
+
+    #[test]
+    fn test_list_mutant_files_follows_manifest() {
+        let temp_dir = tempdir().unwrap();
+        let folder = temp_dir.path();
+        for i in [10, 2, 0, 1] {
+            fs::write(folder.join(format!("wallet.mutant.{}.cpp", i)), "mutated").unwrap();
+        }
+        // Without a manifest the folder is scanned in mutant index order.
+        let mutants = list_mutant_files(folder, &AnalysisOptions::default()).unwrap();
+        assert_eq!(
+            mutants,
+            vec![
+                "wallet.mutant.0.cpp",
+                "wallet.mutant.1.cpp",
+                "wallet.mutant.2.cpp",
+                "wallet.mutant.10.cpp"
+            ]
+        );
+
+        let entry = |i: usize| crate::manifest::ManifestEntry {
+            file: format!("wallet.mutant.{}.cpp", i),
+            line: i + 1,
+            operator: "op".to_string(),
+            description: String::new(),
+            patch_hash: format!("h{}", i),
+        };
+        // Entries are kept in manifest order; missing files are dropped.
+        Manifest {
+            target_file: "src/wallet/wallet.cpp".to_string(),
+            mutants: vec![entry(2), entry(0), entry(7)],
+            ast_stats: None,
+            equivalent: 0,
+        }
+        .write(folder)
+        .unwrap();
+
+        let mutants = list_mutant_files(folder, &AnalysisOptions::default()).unwrap();
+        assert_eq!(mutants, vec!["wallet.mutant.2.cpp", "wallet.mutant.0.cpp"]);
+    }
+
+    #[test]
+    fn test_parse_mutant_indices() {
+        let ranges = parse_mutant_indices("10-20,42").unwrap();
+        assert_eq!(ranges, vec![10..=20, 42..=42]);
+
+        assert!(parse_mutant_indices("20-10").is_err());
+        assert!(parse_mutant_indices("abc").is_err());
+        assert!(parse_mutant_indices("1,,2").is_err());
+    }
+
+    #[test]
+    fn test_select_mutants_by_index() {
+        let files: Vec<String> = (0..50)
+            .map(|i| format!("wallet.mutant.{}.cpp", i))
+            .collect();
+
+        let selected = select_mutants_by_index(files.clone(), &[10..=12, 42..=42]).unwrap();
+        assert_eq!(
+            selected,
+            vec![
+                "wallet.mutant.10.cpp",
+                "wallet.mutant.11.cpp",
+                "wallet.mutant.12.cpp",
+                "wallet.mutant.42.cpp"
+            ]
+        );
+
+        let err = select_mutants_by_index(files.clone(), &[45..=60]).unwrap_err();
+        assert!(err.to_string().contains("index 50-60 (valid: 0-49)"));
+
+        // Every missing index inside the valid span is reported too.
+        let files: Vec<String> = files.into_iter().filter(|f| !f.contains(".2")).collect();
+        let err = select_mutants_by_index(files, &[0..=3, 20..=30, 29..=29]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("index 2,20-29 (valid: 0-1,3-19,30-49)"));
+    }
+
+    #[test]
+    fn test_shuffle_mutants_is_deterministic() {
+        let files: Vec<String> = (0..20).map(|i| format!("a.mutant.{}.cpp", i)).collect();
+
+        let mut first = files.clone();
+        shuffle_mutants(&mut first, 42);
+        let mut second: Vec<String> = files.iter().rev().cloned().collect();
+        shuffle_mutants(&mut second, 42);
+        assert_eq!(first, second);
+        assert_ne!(first, files);
+
+        let mut sorted = first.clone();
+        sorted.sort();
+        let mut expected = files.clone();
+        expected.sort();
+        assert_eq!(sorted, expected);
+    }
+
     #[tokio::test]
     async fn test_run_command() {
         // Test successful command
@@ -445,12 +3016,41 @@ mod tests {
         // Test command that should timeout (note: this might be flaky in CI)
         let result = run_command("sleep 10", 1).await.unwrap();
         assert!(!result);
+
+        let outcome = run_command_outcome("sleep 10", 1).await.unwrap();
+        assert_eq!(outcome, CommandOutcome::Timeout);
+        assert_eq!(outcome.mutant_status(), "timeout");
+
+        // A failing build step is a build error, not a kill.
+        let command = |build: &str, test: &str| TestCommand {
+            build: Some(build.to_string()),
+            test: test.to_string(),
+        };
+        let outcome = run_mutant(&command("false", "true"), 5, 5).await.unwrap();
+        assert_eq!(outcome, CommandOutcome::BuildFailed);
+        assert_eq!(outcome.mutant_status(), "build_failed");
+        let outcome = run_mutant(&command("true", "false"), 5, 5).await.unwrap();
+        assert_eq!(outcome, CommandOutcome::Failure);
+
+        // The test timeout does not bound the build, and the baseline times
+        // the test step alone.
+        let outcome = run_mutant(&command("sleep 2", "true"), 5, 1).await.unwrap();
+        assert_eq!(outcome, CommandOutcome::Success);
+        let (outcome, elapsed) = run_baseline(&command("sleep 2", "true"), 5, 1)
+            .await
+            .unwrap();
+        assert_eq!(outcome, CommandOutcome::Success);
+        assert!(elapsed < Duration::from_secs(1));
+        let (outcome, _) = run_baseline(&command("sleep 2", "true"), 1, 5)
+            .await
+            .unwrap();
+        assert_eq!(outcome, CommandOutcome::BuildFailed);
     }
 
     #[test]
     fn test_find_mutation_folders() {
         let temp_dir = tempdir().unwrap();
-        std::env::set_current_dir(&temp_dir).unwrap();
+        let _cwd = enter_dir(temp_dir.path());
 
         // Create some test directories
         fs::create_dir("muts-test-1").unwrap();
@@ -458,7 +3058,7 @@ mod tests {
         fs::create_dir("not-muts").unwrap();
         fs::create_dir("another-dir").unwrap();
 
-        let folders = find_mutation_folders().unwrap();
+        let folders = find_mutation_folders(Path::new("."), false).unwrap();
         assert_eq!(folders.len(), 2);
 
         let folder_names: Vec<String> = folders
@@ -470,5 +3070,182 @@ mod tests {
         assert!(folder_names.contains(&"muts-test-1".to_string()));
         assert!(folder_names.contains(&"muts-test-2".to_string()));
         assert!(!folder_names.contains(&"not-muts".to_string()));
+
+        // Nested runs are only found recursively, or from their own root.
+        fs::create_dir_all("runs/2024-05-01/muts-old").unwrap();
+        fs::create_dir_all(".hidden/muts-ignored").unwrap();
+        assert_eq!(
+            find_mutation_folders(Path::new("."), false).unwrap().len(),
+            2
+        );
+        let folders = find_mutation_folders(Path::new("."), true).unwrap();
+        assert_eq!(
+            folders,
+            vec![
+                PathBuf::from("./muts-test-1"),
+                PathBuf::from("./muts-test-2"),
+                PathBuf::from("./runs/2024-05-01/muts-old"),
+            ]
+        );
+        let folders = find_mutation_folders(Path::new("runs/2024-05-01"), false).unwrap();
+        assert_eq!(folders, vec![PathBuf::from("runs/2024-05-01/muts-old")]);
+    }
+
+    #[tokio::test]
+    async fn test_keep_going() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        // Neither folder has an original_file.txt.
+        fs::create_dir("muts-a").unwrap();
+        fs::create_dir("muts-b").unwrap();
+
+        let options = AnalysisOptions {
+            command: Some("true".to_string()),
+            ..Default::default()
+        };
+        assert!(run_analysis(None, options.clone()).await.is_err());
+
+        let options = AnalysisOptions {
+            keep_going: true,
+            ..options
+        };
+        let summary = run_analysis(None, options).await.unwrap();
+        let failed: Vec<_> = summary.failed.iter().map(|f| &f.folder).collect();
+        assert_eq!(failed, [Path::new("./muts-a"), Path::new("./muts-b")]);
+        let err = summary.check_failed().unwrap_err();
+        assert!(matches!(err, MutationError::FoldersFailed(2)));
+    }
+
+    #[tokio::test]
+    async fn test_restore_if_mutated() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        init_git_repo(&[("a.cpp", "int a;\n")]);
+        let wip = "int a; // wip\n";
+
+        restore_if_mutated("a.cpp", "int a;\n").unwrap();
+        assert_eq!(fs::read_to_string("a.cpp").unwrap(), "int a;\n");
+
+        // A mutant left behind by a failed folder is reverted to the content
+        // from before the analysis, not to HEAD.
+        fs::write("a.cpp", "int b;\n").unwrap();
+        restore_if_mutated("a.cpp", wip).unwrap();
+        assert_eq!(fs::read_to_string("a.cpp").unwrap(), wip);
+
+        // Uncommitted changes to a target stop the analysis and are kept.
+        let folder = Path::new("muts-a-cpp");
+        fs::create_dir(folder).unwrap();
+        fs::write(folder.join("original_file.txt"), "a.cpp").unwrap();
+        fs::write(folder.join("a.mutant.0.cpp"), "int c;\n").unwrap();
+        let options = AnalysisOptions {
+            command: Some("true".to_string()),
+            timeout_secs: 30,
+            ..Default::default()
+        };
+        let err = run_analysis(Some(folder.to_path_buf()), options)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MutationError::DirtyTarget(_)));
+        assert_eq!(fs::read_to_string("a.cpp").unwrap(), wip);
+
+        // A target git cannot check is an error, not a clean target.
+        fs::remove_dir_all(".git").unwrap();
+        let err = check_clean_target("a.cpp", true).await.unwrap_err();
+        assert!(matches!(err, MutationError::Git(_)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_folder() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let original = "int f(int a, int b) {\n    return a + b;\n}\n";
+        init_git_repo(&[("test.cpp", original)]);
+
+        let folder = Path::new("muts-test-cpp");
+        fs::create_dir(folder).unwrap();
+        let check = verify_folder(folder).await.unwrap();
+        assert!(check.target.is_none());
+        assert!(check.problems[0].contains("original_file.txt"));
+
+        fs::write(folder.join("original_file.txt"), "test.cpp").unwrap();
+        let mutated = original.replace("a + b", "a - b");
+        fs::write(folder.join("test.mutant.0.cpp"), &mutated).unwrap();
+        let check = verify_folder(folder).await.unwrap();
+        assert_eq!(check.target.as_deref(), Some("test.cpp"));
+        assert_eq!(check.mutants, 1);
+        assert!(check.problems.is_empty(), "{:?}", check.problems);
+
+        let diff = generate_diff("test.cpp", &mutated).await.unwrap();
+        fs::write(folder.join("test.mutant.1.cpp"), original).unwrap();
+        fs::write(
+            folder.join("test.mutant.2.patch"),
+            diff.replace("-    return a + b;", "-    return a * b;"),
+        )
+        .unwrap();
+        let entry = |file: &str, patch_hash: String| ManifestEntry {
+            file: file.to_string(),
+            line: 2,
+            operator: "op".to_string(),
+            description: String::new(),
+            patch_hash,
+        };
+        Manifest {
+            target_file: "test.cpp".to_string(),
+            mutants: vec![
+                entry("test.mutant.0.cpp", "edited".to_string()),
+                entry("test.mutant.1.cpp", String::new()),
+                entry("test.mutant.2.patch", String::new()),
+                entry("test.mutant.3.cpp", compute_patch_hash(&diff)),
+            ],
+            ast_stats: None,
+            equivalent: 0,
+        }
+        .write(folder)
+        .unwrap();
+
+        let check = verify_folder(folder).await.unwrap();
+        assert_eq!(check.mutants, 3);
+        assert_eq!(check.problems.len(), 4, "{:?}", check.problems);
+        assert_eq!(
+            check.problems[0],
+            "test.mutant.3.cpp is in the manifest but missing"
+        );
+        assert!(check.problems[1].starts_with("test.mutant.0.cpp differs from the mutant"));
+        assert!(check.problems[2].contains("identical to the target"));
+        assert!(check.problems[3].starts_with("test.mutant.2.patch: "));
+        // Verifying leaves the target untouched.
+        assert_eq!(fs::read_to_string("test.cpp").unwrap(), original);
+    }
+
+    #[tokio::test]
+    async fn test_verify_folder_rejects_multi_line_mutants() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let original = "int f(int i) {\n--i;\n    return i;\n}\n";
+        init_git_repo(&[("test.cpp", original)]);
+
+        let folder = Path::new("muts-test-cpp");
+        fs::create_dir(folder).unwrap();
+        fs::write(folder.join("original_file.txt"), "test.cpp").unwrap();
+        // The removed `---i;` line is not a header.
+        fs::write(
+            folder.join("test.mutant.0.cpp"),
+            original.replace("--i", "++i"),
+        )
+        .unwrap();
+        // A single hunk that rewrites two lines.
+        let mutated = original.replace("--i;\n    return i;", "++i;\n    return -i;");
+        fs::write(folder.join("test.mutant.1.cpp"), &mutated).unwrap();
+        let diff = generate_diff("test.cpp", &mutated).await.unwrap();
+        assert_eq!(
+            diff.lines().filter(|line| line.starts_with("@@")).count(),
+            1
+        );
+
+        let check = verify_folder(folder).await.unwrap();
+        assert_eq!(
+            check.problems,
+            ["test.mutant.1.cpp removes 2 and adds 2 lines instead of changing one line"]
+        );
     }
 }
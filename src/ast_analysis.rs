@@ -1,9 +1,19 @@
 use crate::error::Result;
+use log::debug;
 use regex::Regex;
+use serde::Serialize;
 use std::collections::HashMap;
 
+/// Whether `line` has the shape of a variable declaration statement: no call
+/// or parameter list, and an initializer or a final `;`. The `const` qualifier
+/// operator only mutates such lines.
+pub fn has_declaration_shape(line: &str) -> bool {
+    let line = line.trim_end();
+    !line.contains('(') && (line.contains('=') || line.ends_with(';'))
+}
+
 /// Represents different types of AST nodes
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum AstNodeType {
     // Simple nodes (no body)
     FunctionCall,
@@ -18,6 +28,14 @@ pub enum AstNodeType {
     IfStatement,
     ForLoop,
     WhileLoop,
+    /// `switch`, whose children are its case labels.
+    SwitchStatement,
+    /// `case X:` or `default:`, whose children are the statements up to the
+    /// next label.
+    CaseLabel,
+    /// `try` or one of its `catch` handlers, whose children are the statements
+    /// of its block.
+    TryCatch,
     Block,
     Function,
     Class,
@@ -295,10 +313,22 @@ impl AridNodeDetector {
         let line_number = line_index + 1;
         let node_type = self.classify_line(trimmed);
 
+        // A switch is arid when all of its cases are; a case when its statements are.
+        if node_type == AstNodeType::SwitchStatement {
+            return !self.is_switch_arid(lines, line_index);
+        }
+        if node_type == AstNodeType::CaseLabel {
+            let case = self.parse_case(lines, line_index, lines.len());
+            return !self.is_arid(&case.0);
+        }
+
         // For control structures, check if their body is all arid
         if matches!(
             node_type,
-            AstNodeType::IfStatement | AstNodeType::ForLoop | AstNodeType::WhileLoop
+            AstNodeType::IfStatement
+                | AstNodeType::ForLoop
+                | AstNodeType::WhileLoop
+                | AstNodeType::TryCatch
         ) {
             // If the control structure body is all arid, don't mutate the control structure
             return !self.is_control_structure_body_arid(lines, line_index);
@@ -316,9 +346,20 @@ impl AridNodeDetector {
 
         // Check if this is a single-line control structure (no braces)
         // e.g., "if (condition) single_statement;"
-        if !start_line.contains('{') {
+        let brace_on_next_line = lines
+            .get(start_index + 1)
+            .is_some_and(|line| line.trim() == "{");
+        if !start_line.contains('{') && !brace_on_next_line {
             // Look for the statement on the same line or next line
-            let statement = if start_line.contains(')') && start_line.ends_with(';') {
+            let else_statement = else_branch(start_line)
+                .filter(|rest| !rest.starts_with("if ") && !rest.starts_with("if("));
+            let statement = if let Some(rest) = else_statement.filter(|rest| !rest.is_empty()) {
+                // e.g., "else LogPrintf(...);"
+                rest
+            } else if else_statement.is_none()
+                && start_line.contains(')')
+                && start_line.ends_with(';')
+            {
                 // Extract everything after the closing paren
                 if let Some(pos) = start_line.rfind(')') {
                     start_line[pos + 1..].trim()
@@ -355,8 +396,13 @@ impl AridNodeDetector {
 
         // Check if all non-empty lines in the body are arid
         let mut has_non_empty_line = false;
-        for i in body_range.0..body_range.1 {
-            let line = lines[i].trim();
+        for (i, line) in lines
+            .iter()
+            .enumerate()
+            .take(body_range.1)
+            .skip(body_range.0)
+        {
+            let line = line.trim();
 
             // Skip empty lines and braces
             if line.is_empty() || line == "{" || line == "}" {
@@ -378,6 +424,78 @@ impl AridNodeDetector {
         has_non_empty_line
     }
 
+    /// Build the switch starting at `start_index` as a compound node whose
+    /// children are its cases, and check whether it is arid. Cases that only
+    /// `break` are left out.
+    fn is_switch_arid(&mut self, lines: &[String], start_index: usize) -> bool {
+        let brace_line_index = if lines[start_index].contains('{') {
+            start_index
+        } else if start_index + 1 < lines.len() && lines[start_index + 1].trim() == "{" {
+            start_index + 1
+        } else {
+            return false;
+        };
+        let Some(end_index) = self.find_matching_brace(lines, brace_line_index) else {
+            return false;
+        };
+
+        let mut switch = self.parse_line_to_simple_ast(&lines[start_index], start_index + 1);
+        let mut i = brace_line_index + 1;
+        while i < end_index {
+            if self.classify_line(lines[i].trim()) == AstNodeType::CaseLabel {
+                let (case, next) = self.parse_case(lines, i, end_index);
+                if !case.children.is_empty() {
+                    switch.add_child(case);
+                }
+                i = next;
+            } else {
+                i += 1;
+            }
+        }
+        self.is_arid(&switch)
+    }
+
+    /// Parse the case label at `start_index` with its statements, up to the next
+    /// label at the same depth or `end_index`. Labels that fall through are
+    /// merged with the case they fall into. Returns the case node and the
+    /// index of the line after it.
+    fn parse_case(
+        &self,
+        lines: &[String],
+        start_index: usize,
+        end_index: usize,
+    ) -> (AstNode, usize) {
+        let label = lines[start_index].trim();
+        let mut case = self.parse_line_to_simple_ast(label, start_index + 1);
+        let mut depth = 0;
+        let mut i = start_index;
+        while i < end_index {
+            let line = lines[i].trim();
+            let statement = if depth <= 0 && self.classify_line(line) == AstNodeType::CaseLabel {
+                if !case.children.is_empty() {
+                    break;
+                }
+                case_label_body(line)
+            } else {
+                line
+            };
+            depth += brace_balance(line);
+            if depth < 0 {
+                // The closing brace of the switch.
+                break;
+            }
+            let statement = statement
+                .trim()
+                .trim_matches(|c| c == '{' || c == '}')
+                .trim();
+            if !statement.is_empty() && statement != "break;" {
+                case.add_child(self.parse_line_to_simple_ast(statement, i + 1));
+            }
+            i += 1;
+        }
+        (case, i)
+    }
+
     /// Find the index of the closing brace that matches the opening brace at start_index
     fn find_matching_brace(&self, lines: &[String], start_index: usize) -> Option<usize> {
         let mut brace_count = 0;
@@ -390,6 +508,8 @@ impl AridNodeDetector {
                         brace_count += 1;
                         found_opening = true;
                     }
+                    // The brace closing the previous branch of `} else {`.
+                    '}' if !found_opening => {}
                     '}' => {
                         brace_count -= 1;
                         if found_opening && brace_count == 0 {
@@ -443,8 +563,20 @@ impl AridNodeDetector {
             return AstNodeType::Class;
         }
 
+        // Switches and their labels; `Foo::Bar` alone is not a label.
+        if line.starts_with("switch ") || line.starts_with("switch(") {
+            return AstNodeType::SwitchStatement;
+        }
+        if (line.starts_with("case ")
+            || line.starts_with("default:")
+            || line.starts_with("default :"))
+            && line.len() != case_label_body(line).len()
+        {
+            return AstNodeType::CaseLabel;
+        }
+
         // Control flow statements (compound nodes) - check these before function declarations
-        if line.starts_with("if ") || line.starts_with("if(") || line.contains("} else ") {
+        if line.starts_with("if ") || line.starts_with("if(") || else_branch(line).is_some() {
             return AstNodeType::IfStatement;
         }
         if line.starts_with("for ") || line.starts_with("for(") {
@@ -453,6 +585,9 @@ impl AridNodeDetector {
         if line.starts_with("while ") || line.starts_with("while(") {
             return AstNodeType::WhileLoop;
         }
+        if is_try_catch(line) {
+            return AstNodeType::TryCatch;
+        }
 
         // Block statements
         if line == "{" || line == "}" || line.ends_with(" {") {
@@ -542,9 +677,7 @@ impl AridNodeDetector {
             Regex::new(r"^\s*auto\s+\w+").unwrap(),
         ];
 
-        var_patterns.iter().any(|pattern| pattern.is_match(line))
-            && !line.contains('(')
-            && (line.contains('=') || line.ends_with(';'))
+        var_patterns.iter().any(|pattern| pattern.is_match(line)) && has_declaration_shape(line)
     }
 
     /// Check if line is an assignment
@@ -613,7 +746,7 @@ impl AridNodeDetector {
     pub fn add_expert_rule(&mut self, pattern: &str, description: &str) -> Result<()> {
         let regex = Regex::new(pattern)?;
         self.expert.arid_function_patterns.push(regex);
-        println!("Added expert rule: {} ({})", pattern, description);
+        debug!("Added expert rule: {} ({})", pattern, description);
         Ok(())
     }
 
@@ -643,7 +776,6 @@ impl AridNodeDetector {
     }
 
     /// Export detailed analysis of which lines were filtered and why
-    #[allow(dead_code)]
     pub fn analyze_file_detailed(&mut self, file_content: &str) -> DetailedAnalysis {
         let lines: Vec<String> = file_content.lines().map(|s| s.to_string()).collect();
         let mut analysis = DetailedAnalysis::new();
@@ -672,12 +804,16 @@ impl AridNodeDetector {
     }
 
     /// Get the reason why a node is considered arid
-    #[allow(dead_code)]
     fn get_arid_reason(&self, node: &AstNode, _lines: &[String], _line_index: usize) -> String {
         // Check if this is a control structure with arid body
         if matches!(
             node.node_type,
-            AstNodeType::IfStatement | AstNodeType::ForLoop | AstNodeType::WhileLoop
+            AstNodeType::IfStatement
+                | AstNodeType::ForLoop
+                | AstNodeType::WhileLoop
+                | AstNodeType::SwitchStatement
+                | AstNodeType::CaseLabel
+                | AstNodeType::TryCatch
         ) {
             return "Control structure with arid body (logging/debugging only)".to_string();
         }
@@ -739,14 +875,18 @@ impl AridNodeDetector {
 }
 
 /// Detailed analysis results for a file
-#[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct DetailedAnalysis {
     pub lines: Vec<LineAnalysis>,
     pub summary: AnalysisSummary,
 }
 
-#[allow(dead_code)]
+impl Default for DetailedAnalysis {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DetailedAnalysis {
     pub fn new() -> Self {
         Self {
@@ -779,7 +919,7 @@ impl DetailedAnalysis {
     pub fn print_arid_lines(&self) {
         println!("\n=== Filtered Out (Arid) Lines ===");
         for line in &self.lines {
-            if line.is_arid {
+            if line.is_arid && !matches!(line.content.trim(), "" | "{" | "}") {
                 println!(
                     "Line {}: {} | Reason: {}",
                     line.line_number,
@@ -789,11 +929,27 @@ impl DetailedAnalysis {
             }
         }
     }
+
+    /// Print the arid lines of `file` among `line_numbers`, skipping blank
+    /// lines and lone braces, which have nothing to mutate anyway.
+    pub fn print_arid_lines_in(&self, file: &str, line_numbers: &[usize]) {
+        for line in &self.lines {
+            let content = line.content.trim();
+            if line.is_arid
+                && !matches!(content, "" | "{" | "}")
+                && line_numbers.contains(&line.line_number)
+            {
+                println!(
+                    "{}:{}: {} | Reason: {}",
+                    file, line.line_number, content, line.reason
+                );
+            }
+        }
+    }
 }
 
 /// Analysis of a single line
-#[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct LineAnalysis {
     pub line_number: usize,
     pub content: String,
@@ -803,14 +959,63 @@ pub struct LineAnalysis {
 }
 
 /// Summary statistics for analysis
-#[allow(dead_code)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AnalysisSummary {
     pub total_lines: usize,
     pub mutatable_lines: usize,
     pub arid_lines: usize,
 }
 
+/// What follows `else` in `else`, `else {`, `} else {` or `else if (...)`, or
+/// `None` if `line` does not start an else branch.
+fn else_branch(line: &str) -> Option<&str> {
+    let rest = line
+        .trim_start_matches('}')
+        .trim_start()
+        .strip_prefix("else")?;
+    if rest.is_empty() || rest.starts_with([' ', '{']) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+/// Whether `line` starts a `try` block or a `catch` handler, e.g. `try {` or
+/// `} catch (const std::exception& e) {`.
+fn is_try_catch(line: &str) -> bool {
+    let line = line.trim_start_matches('}').trim_start();
+    ["try", "catch"].iter().any(|keyword| {
+        line.strip_prefix(keyword)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '{', '(']))
+    })
+}
+
+/// What follows the label of a `case X:`/`default:` line, skipping the `::` of
+/// qualified names. The whole line if there is no label.
+fn case_label_body(line: &str) -> &str {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b':' if bytes.get(i + 1) == Some(&b':') => i += 2,
+            b':' => return &line[i + 1..],
+            _ => i += 1,
+        }
+    }
+    line
+}
+
+/// Opening minus closing braces in `line`.
+fn brace_balance(line: &str) -> i32 {
+    line.chars()
+        .map(|c| match c {
+            '{' => 1,
+            '}' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
 /// Integration with existing mutation system - context-aware version
 pub fn filter_mutatable_lines(lines: &[String], detector: &mut AridNodeDetector) -> Vec<usize> {
     lines
@@ -968,6 +1173,220 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_analyze_file_detailed_matches_filter() {
+        let mut detector = AridNodeDetector::new().unwrap();
+        let source = "int x = 0;\nLogPrintf(\"x\\n\");\nvec.reserve(10);\nx = x + 1;\n";
+        let lines: Vec<String> = source.lines().map(|s| s.to_string()).collect();
+
+        let analysis = detector.analyze_file_detailed(source);
+        let mutatable: Vec<usize> = analysis
+            .lines
+            .iter()
+            .filter(|line| !line.is_arid)
+            .map(|line| line.line_number)
+            .collect();
+        assert_eq!(mutatable, filter_mutatable_lines(&lines, &mut detector));
+        assert_eq!(analysis.summary.arid_lines, 2);
+        assert!(analysis.lines[1].reason.contains("arid function pattern"));
+        assert_eq!(analysis.lines[3].reason, "Not arid - will be mutated");
+
+        let json = serde_json::to_value(&analysis).unwrap();
+        assert_eq!(json["summary"]["arid_lines"], 2);
+        assert_eq!(json["lines"][1]["node_type"], "FunctionCall");
+        assert_eq!(json["lines"][1]["is_arid"], true);
+    }
+
+    #[test]
+    fn test_else_branches() {
+        let mut detector = AridNodeDetector::new().unwrap();
+
+        for line in [
+            "else",
+            "else {",
+            "} else {",
+            "}else{",
+            "else if (x) {",
+            "} else if (x) {",
+        ] {
+            assert_eq!(
+                detector.classify_line(line),
+                AstNodeType::IfStatement,
+                "{:?} should start a branch",
+                line
+            );
+        }
+        assert_ne!(
+            detector.classify_line("elsewhere();"),
+            AstNodeType::IfStatement
+        );
+
+        let log = "    LogPrintf(\"fallback\\n\");";
+        let live = "    x = x + 1;";
+        let cases: Vec<(Vec<&str>, bool)> = vec![
+            (vec!["if (a) {", live, "} else {", log, "}"], false),
+            (vec!["if (a) {", live, "}", "else {", log, "}"], false),
+            (vec!["if (a) {", live, "}", "else", "{", log, "}"], false),
+            (vec!["if (a)", live, "else", log], false),
+            (
+                vec!["if (a)", live, "else LogPrintf(\"fallback\\n\");"],
+                false,
+            ),
+            (vec!["if (a) {", live, "} else if (b) {", log, "}"], false),
+            (vec!["if (a) {", log, "} else {", live, "}"], true),
+            (vec!["if (a) {", log, "} else if (b) {", live, "}"], true),
+        ];
+        for (lines, mutated) in cases {
+            let lines: Vec<String> = lines.into_iter().map(String::from).collect();
+            let else_index = lines
+                .iter()
+                .rposition(|line| else_branch(line.trim()).is_some())
+                .unwrap();
+            assert_eq!(
+                detector.should_mutate_line_with_context(&lines, else_index),
+                mutated,
+                "{:?}",
+                lines
+            );
+        }
+    }
+
+    #[test]
+    fn test_switch_with_arid_cases() {
+        let mut detector = AridNodeDetector::new().unwrap();
+
+        let lines = vec![
+            "switch (state) {".to_string(),
+            "case State::IDLE:".to_string(),
+            "case State::WAITING:".to_string(),
+            "    LogDebug(BCLog::NET, \"waiting\\n\");".to_string(),
+            "    break;".to_string(),
+            "default: LogPrintf(\"unknown state\\n\");".to_string(),
+            "}".to_string(),
+        ];
+
+        assert_eq!(
+            detector.classify_line(lines[0].trim()),
+            AstNodeType::SwitchStatement
+        );
+        assert_eq!(
+            detector.classify_line(lines[1].trim()),
+            AstNodeType::CaseLabel
+        );
+        assert_eq!(
+            detector.classify_line(lines[5].trim()),
+            AstNodeType::CaseLabel
+        );
+        assert_ne!(
+            detector.classify_line("State::IDLE;"),
+            AstNodeType::CaseLabel
+        );
+
+        // `break;` is left to the operators; everything else only logs.
+        let mutatable_lines = filter_mutatable_lines(&lines, &mut detector);
+        for line in [1, 2, 3, 4, 6] {
+            assert!(
+                !mutatable_lines.contains(&line),
+                "Line {} should not be mutatable, got: {:?}",
+                line,
+                mutatable_lines
+            );
+        }
+    }
+
+    #[test]
+    fn test_switch_with_live_case() {
+        let mut detector = AridNodeDetector::new().unwrap();
+
+        let lines = vec![
+            "switch (state) {".to_string(),
+            "case State::IDLE:".to_string(),
+            "    LogDebug(BCLog::NET, \"idle\\n\");".to_string(),
+            "    break;".to_string(),
+            "case State::ACTIVE: {".to_string(),
+            "    x = x + 1;".to_string(),
+            "    break;".to_string(),
+            "}".to_string(),
+            "}".to_string(),
+        ];
+
+        let mutatable_lines = filter_mutatable_lines(&lines, &mut detector);
+        assert!(mutatable_lines.contains(&1), "Switch should be mutatable");
+        assert!(
+            !mutatable_lines.contains(&2),
+            "Logging-only case should not be mutatable"
+        );
+        assert!(
+            mutatable_lines.contains(&5),
+            "Live case should be mutatable"
+        );
+        assert!(
+            mutatable_lines.contains(&6),
+            "Assignment should be mutatable"
+        );
+    }
+
+    #[test]
+    fn test_try_catch_with_arid_bodies() {
+        let mut detector = AridNodeDetector::new().unwrap();
+
+        for line in [
+            "try {",
+            "try",
+            "} catch (const std::exception& e) {",
+            "}catch(...){",
+        ] {
+            assert_eq!(
+                detector.classify_line(line),
+                AstNodeType::TryCatch,
+                "{:?}",
+                line
+            );
+        }
+        assert_ne!(detector.classify_line("try_lock();"), AstNodeType::TryCatch);
+        assert_ne!(
+            detector.classify_line("catcher.Run();"),
+            AstNodeType::TryCatch
+        );
+
+        let lines = vec![
+            "try {".to_string(),
+            "    LogDebug(BCLog::NET, \"connecting\\n\");".to_string(),
+            "} catch (const std::exception& e) {".to_string(),
+            "    LogPrintf(\"failed: %s\\n\", e.what());".to_string(),
+            "}".to_string(),
+        ];
+        let mutatable_lines = filter_mutatable_lines(&lines, &mut detector);
+        assert!(mutatable_lines.is_empty(), "{:?}", mutatable_lines);
+    }
+
+    #[test]
+    fn test_try_catch_with_live_statement() {
+        let mut detector = AridNodeDetector::new().unwrap();
+
+        let lines = vec![
+            "try".to_string(),
+            "{".to_string(),
+            "    value = ParseValue(str);".to_string(),
+            "} catch (const std::runtime_error&) {".to_string(),
+            "    LogPrintf(\"invalid value\\n\");".to_string(),
+            "}".to_string(),
+        ];
+        let mutatable_lines = filter_mutatable_lines(&lines, &mut detector);
+        assert!(
+            mutatable_lines.contains(&1),
+            "Try with a live body should be mutatable"
+        );
+        assert!(
+            mutatable_lines.contains(&3),
+            "Assignment should be mutatable"
+        );
+        assert!(
+            !mutatable_lines.contains(&4),
+            "Logging-only catch should not be mutatable"
+        );
+    }
+
     #[test]
     fn test_if_statement_mixed_body() {
         let mut detector = AridNodeDetector::new().unwrap();
@@ -0,0 +1,188 @@
+//! `bcore-mutation.toml`: per-project defaults for the `mutate` and `analyze`
+//! flags.
+//!
+//! Each table maps flag names to values, e.g. `survival_threshold = 0.3` under
+//! `[analyze]` stands for `--survival-threshold=0.3`. The flags are placed
+//! before the ones given on the command line, which therefore take precedence.
+//!
+//! `[survival_thresholds]` maps globs to the `--survival-threshold` of the
+//! files they match, e.g. `"src/validation.cpp" = 0.05`.
+
+use crate::error::{MutationError, Result};
+use globset::{Glob, GlobMatcher};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// Config file read from the working directory when `--config` is not given.
+pub const CONFIG_FILE: &str = "bcore-mutation.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub mutate: Table,
+    #[serde(default)]
+    pub analyze: Table,
+    #[serde(default)]
+    pub survival_thresholds: BTreeMap<String, f64>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            MutationError::InvalidInput(format!("Cannot read {}: {}", path.display(), e))
+        })?;
+        toml::from_str(&contents)
+            .map_err(|e| MutationError::InvalidInput(format!("Invalid {}: {}", path.display(), e)))
+    }
+
+    /// The command-line flags equivalent to the defaults for `subcommand`.
+    pub fn args_for(&self, subcommand: &str) -> Result<Vec<String>> {
+        let table = match subcommand {
+            "mutate" => &self.mutate,
+            "analyze" => &self.analyze,
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut args = Vec::new();
+        for (key, value) in table {
+            let flag = format!("--{}", key.replace('_', "-"));
+            match value {
+                // `true` passes a switch, `false` leaves it off.
+                Value::Boolean(true) => args.push(flag),
+                Value::Boolean(false) => {}
+                // Arrays repeat the flag, e.g. `exclude = ["src/qt/**"]`.
+                Value::Array(values) => {
+                    for value in values {
+                        args.push(format!("{}={}", flag, scalar(key, value)?));
+                    }
+                }
+                value => args.push(format!("{}={}", flag, scalar(key, value)?)),
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Survival thresholds of the files matching globs, overriding
+/// `--survival-threshold` for them.
+#[derive(Debug, Clone, Default)]
+pub struct FileThresholds(Vec<(GlobMatcher, f64)>);
+
+impl FileThresholds {
+    pub fn new(thresholds: &BTreeMap<String, f64>) -> Result<Self> {
+        let mut matchers = Vec::new();
+        for (pattern, &threshold) in thresholds {
+            if !(0.0..=1.0).contains(&threshold) {
+                return Err(MutationError::InvalidInput(format!(
+                    "Survival threshold of '{}' must be between 0 and 1, got {}",
+                    pattern, threshold
+                )));
+            }
+            let glob = Glob::new(pattern).map_err(|e| {
+                MutationError::InvalidInput(format!("Invalid glob '{}': {}", pattern, e))
+            })?;
+            matchers.push((glob.compile_matcher(), threshold));
+        }
+        Ok(FileThresholds(matchers))
+    }
+
+    /// The threshold of `file`: that of the longest, so most specific, glob
+    /// matching it.
+    pub fn get(&self, file: &str) -> Option<f64> {
+        self.0
+            .iter()
+            .filter(|(matcher, _)| matcher.is_match(file))
+            .max_by_key(|(matcher, _)| matcher.glob().glob().len())
+            .map(|&(_, threshold)| threshold)
+    }
+}
+
+fn scalar(key: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        _ => Err(MutationError::InvalidInput(format!(
+            "Unsupported value for '{}' in {}: {}",
+            key, CONFIG_FILE, value
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_config_args() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(CONFIG_FILE);
+        fs::write(
+            &path,
+            r#"
+[mutate]
+one_mutant = true
+patch = false
+exclude = ["src/qt/**", "src/wallet/**"]
+
+[analyze]
+jobs = 8
+survival_threshold = 0.3
+command = "ctest --test-dir build"
+"#,
+        )
+        .unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(
+            config.args_for("mutate").unwrap(),
+            vec![
+                "--exclude=src/qt/**",
+                "--exclude=src/wallet/**",
+                "--one-mutant"
+            ]
+        );
+        assert_eq!(
+            config.args_for("analyze").unwrap(),
+            vec![
+                "--command=ctest --test-dir build",
+                "--jobs=8",
+                "--survival-threshold=0.3"
+            ]
+        );
+        assert!(config.args_for("runs").unwrap().is_empty());
+
+        assert!(config.survival_thresholds.is_empty());
+
+        fs::write(&path, "[analyse]\njobs = 8\n").unwrap();
+        assert!(Config::load(&path).is_err());
+        fs::write(&path, "[analyze]\njobs = { n = 8 }\n").unwrap();
+        assert!(Config::load(&path).unwrap().args_for("analyze").is_err());
+    }
+
+    #[test]
+    fn test_file_thresholds() {
+        let config: Config = toml::from_str(
+            r#"
+[survival_thresholds]
+"src/**" = 0.4
+"src/validation.cpp" = 0.05
+"src/util/*.cpp" = 0.6
+"#,
+        )
+        .unwrap();
+        let thresholds = FileThresholds::new(&config.survival_thresholds).unwrap();
+        assert_eq!(thresholds.get("src/validation.cpp"), Some(0.05));
+        assert_eq!(thresholds.get("src/util/strencodings.cpp"), Some(0.6));
+        assert_eq!(thresholds.get("src/net.cpp"), Some(0.4));
+        assert_eq!(thresholds.get("test/functional/feature_x.py"), None);
+
+        let invalid = BTreeMap::from([("src/**".to_string(), 1.5)]);
+        assert!(FileThresholds::new(&invalid).is_err());
+    }
+}
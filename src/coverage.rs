@@ -4,7 +4,23 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
+/// Covered lines of each source file, keyed by the file's normalized path. The
+/// coverage is read from a directory of gcov `*.gcov` files, a single `*.gcov`
+/// file or an lcov `*.info` file. Look files up with [`covered_lines`].
 pub fn parse_coverage_file(coverage_file_path: &Path) -> Result<HashMap<String, Vec<usize>>> {
+    let is_gcov = coverage_file_path.is_dir()
+        || coverage_file_path
+            .extension()
+            .is_some_and(|ext| ext == "gcov");
+    if is_gcov {
+        parse_gcov_file(coverage_file_path)
+    } else {
+        parse_lcov_file(coverage_file_path)
+    }
+}
+
+/// Covered lines from an lcov `*.info` file.
+fn parse_lcov_file(coverage_file_path: &Path) -> Result<HashMap<String, Vec<usize>>> {
     let content = fs::read_to_string(coverage_file_path)?;
     let mut coverage_data: HashMap<String, Vec<usize>> = HashMap::new();
     let mut current_file: Option<String> = None;
@@ -18,17 +34,9 @@ pub fn parse_coverage_file(coverage_file_path: &Path) -> Result<HashMap<String,
 
         // Check for source file
         if let Some(captures) = file_pattern.captures(line) {
-            let full_path = &captures[1];
-
-            // Extract from "src/" onwards
-            let relative_path = if let Some(pos) = full_path.find("src/") {
-                &full_path[pos..]
-            } else {
-                full_path // fallback to full path if "src/" not found
-            };
-
-            current_file = Some(relative_path.to_string());
-            coverage_data.insert(relative_path.to_string(), Vec::new());
+            let path = normalize_path(&captures[1]);
+            coverage_data.insert(path.clone(), Vec::new());
+            current_file = Some(path);
             continue;
         }
 
@@ -56,6 +64,103 @@ pub fn parse_coverage_file(coverage_file_path: &Path) -> Result<HashMap<String,
     Ok(coverage_data)
 }
 
+/// Covered lines from a gcov text file, or from every `*.gcov` file in a
+/// directory. Each file starts with a `-:    0:Source:<path>` header, followed
+/// by `<count>:<line>:<source>` lines whose count is `-` for lines without
+/// code and `#####` (or `=====`) for lines that never ran.
+pub fn parse_gcov_file(path: &Path) -> Result<HashMap<String, Vec<usize>>> {
+    let files = if path.is_dir() {
+        let mut files: Vec<_> = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<_>>()?;
+        files.retain(|file| file.extension().is_some_and(|ext| ext == "gcov"));
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut coverage_data: HashMap<String, Vec<usize>> = HashMap::new();
+    for file in files {
+        let content = fs::read_to_string(&file)?;
+        let mut lines = content.lines().map(|line| {
+            let mut fields = line.splitn(3, ':').map(str::trim);
+            (
+                fields.next().unwrap_or_default(),
+                fields.next().unwrap_or_default(),
+                fields.next().unwrap_or_default(),
+            )
+        });
+        let source = lines
+            .by_ref()
+            .find_map(|(_, line_number, text)| match line_number {
+                "0" => text.strip_prefix("Source:"),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                MutationError::Coverage(format!("No Source: header in {}", file.display()))
+            })?;
+        let covered = coverage_data.entry(normalize_path(source)).or_default();
+        for (count, line_number, _) in lines {
+            // Counts may be suffixed with `*` (unexecuted blocks) or be
+            // abbreviated like `1.2k` with `gcov -H`.
+            let count = count.trim_end_matches('*');
+            let executed = count.starts_with(|c: char| c.is_ascii_digit()) && count != "0";
+            let Ok(line_number) = line_number.parse::<usize>() else {
+                continue;
+            };
+            if executed && line_number > 0 && !covered.contains(&line_number) {
+                covered.push(line_number);
+            }
+        }
+    }
+    for lines in coverage_data.values_mut() {
+        lines.sort_unstable();
+    }
+
+    Ok(coverage_data)
+}
+
+/// `path` with forward slashes, without `.` components or repeated separators,
+/// so that paths from lcov and from git compare equal.
+pub fn normalize_path(path: &str) -> String {
+    let path = path.trim().replace('\\', "/");
+    let absolute = path.starts_with('/');
+    let components: Vec<&str> = path
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect();
+    format!(
+        "{}{}",
+        if absolute { "/" } else { "" },
+        components.join("/")
+    )
+}
+
+/// The covered lines of `file_path`, if the coverage data has an entry for it.
+/// An entry matches when one path ends with the other on a component boundary
+/// (`/repo/src/net.cpp` matches `src/net.cpp`, not `src/net_processing.cpp`);
+/// the longest matching entry wins.
+pub fn covered_lines<'a>(
+    coverage: &'a HashMap<String, Vec<usize>>,
+    file_path: &str,
+) -> Option<&'a Vec<usize>> {
+    let file_path = normalize_path(file_path);
+    coverage
+        .iter()
+        .filter(|(path, _)| is_path_suffix(path, &file_path) || is_path_suffix(&file_path, path))
+        .max_by_key(|(path, _)| path.len())
+        .map(|(_, lines)| lines)
+}
+
+/// Whether `suffix` is `path` or its trailing components.
+fn is_path_suffix(path: &str, suffix: &str) -> bool {
+    path == suffix
+        || path
+            .strip_suffix(suffix)
+            .is_some_and(|rest| rest.ends_with('/'))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,4 +184,73 @@ mod tests {
         assert_eq!(result["/path/to/file1.cpp"], vec![1, 3]);
         assert_eq!(result["/path/to/file2.cpp"], vec![10]);
     }
+
+    #[test]
+    fn test_parse_gcov_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let gcov = "        -:    0:Source:../src/net.cpp
+        -:    0:Graph:net.gcno
+        -:    1:#include <net.h>
+        5:    2:int f() {
+    #####:    3:    return 0;
+       1*:    4:    if (x) g();
+     1.2k:    5:    h();
+    =====:    6:    throw;
+------------------
+_Z1fv:
+        7:    2:int f() {
+------------------
+";
+        fs::write(dir.path().join("net.cpp.gcov"), gcov).unwrap();
+        // Another object file compiled from the same source.
+        fs::write(
+            dir.path().join("net.cpp.other.gcov"),
+            "        -:    0:Source:../src/net.cpp\n        2:    3:    return 0;\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let from_file = parse_coverage_file(&dir.path().join("net.cpp.gcov")).unwrap();
+        assert_eq!(from_file["../src/net.cpp"], vec![2, 4, 5]);
+        let from_dir = parse_coverage_file(dir.path()).unwrap();
+        assert_eq!(from_dir.len(), 1);
+        assert_eq!(
+            covered_lines(&from_dir, "src/net.cpp"),
+            Some(&vec![2, 3, 4, 5])
+        );
+
+        fs::write(dir.path().join("bad.gcov"), "        5:    2:int f() {\n").unwrap();
+        assert!(parse_coverage_file(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_covered_lines() {
+        assert_eq!(normalize_path("./src//net.cpp"), "src/net.cpp");
+        assert_eq!(
+            normalize_path("/home/u/bitcoin/./src/net.cpp"),
+            "/home/u/bitcoin/src/net.cpp"
+        );
+        assert_eq!(normalize_path("src\\wallet\\db.cpp"), "src/wallet/db.cpp");
+
+        let coverage = HashMap::from([
+            ("/home/u/bitcoin/src/net.cpp".to_string(), vec![1]),
+            (
+                "/home/u/bitcoin/src/net_processing.cpp".to_string(),
+                vec![2],
+            ),
+            ("src/wallet/db.cpp".to_string(), vec![3]),
+        ]);
+        // net.cpp is a suffix of net_processing.cpp's name, not of its path.
+        assert_eq!(covered_lines(&coverage, "src/net.cpp"), Some(&vec![1]));
+        assert_eq!(
+            covered_lines(&coverage, "./src/net_processing.cpp"),
+            Some(&vec![2])
+        );
+        assert_eq!(
+            covered_lines(&coverage, "/tmp/bitcoin/src/wallet/db.cpp"),
+            Some(&vec![3])
+        );
+        assert_eq!(covered_lines(&coverage, "src/wallet/walletdb.cpp"), None);
+        assert_eq!(covered_lines(&coverage, "rc/net.cpp"), None);
+    }
 }
@@ -1,11 +1,19 @@
 use crate::error::{MutationError, Result};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::path::Path;
+use std::str::FromStr;
 
 const SCHEMA: &str = "
 PRAGMA foreign_keys = ON;
 
+CREATE TABLE IF NOT EXISTS meta (
+  key             TEXT PRIMARY KEY,
+  value           TEXT NOT NULL
+);
+
 CREATE TABLE IF NOT EXISTS projects (
   id              INTEGER PRIMARY KEY,
   name            TEXT NOT NULL,
@@ -21,7 +29,9 @@ CREATE TABLE IF NOT EXISTS runs (
   pr_number       INTEGER,
   created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
   tool_version    TEXT,
-  config_json     TEXT
+  config_json     TEXT,
+  score           REAL,
+  seed            INTEGER
 );
 
 CREATE INDEX IF NOT EXISTS idx_runs_project_created ON runs(project_id, created_at DESC);
@@ -34,7 +44,8 @@ CREATE TABLE IF NOT EXISTS mutants (
   patch_hash      TEXT NOT NULL,
   status          TEXT NOT NULL DEFAULT 'pending'
                     CHECK (status IN ('pending','running','killed','survived',
-                                      'timeout','error','skipped','equivalent','unproductive')),
+                                      'timeout','error','skipped','equivalent','unproductive',
+                                      'build_failed')),
   killed          INTEGER GENERATED ALWAYS AS (CASE WHEN status='killed' THEN 1 ELSE 0 END) VIRTUAL,
   command_to_test TEXT,
   file_path       TEXT,
@@ -46,8 +57,212 @@ CREATE INDEX IF NOT EXISTS idx_mutants_run_status ON mutants(run_id, status);
 CREATE INDEX IF NOT EXISTS idx_mutants_file ON mutants(file_path);
 CREATE INDEX IF NOT EXISTS idx_mutants_operator ON mutants(operator);
 CREATE INDEX IF NOT EXISTS idx_mutants_killed ON mutants(killed);
+
+CREATE TABLE IF NOT EXISTS analysis_cache (
+  commit_hash     TEXT NOT NULL,
+  patch_hash      TEXT NOT NULL,
+  status          TEXT NOT NULL,
+  PRIMARY KEY (commit_hash, patch_hash)
+);
+";
+
+/// Current schema version, stored under `schema_version` in the `meta` table.
+pub const SCHEMA_VERSION: i64 = 6;
+
+/// Ordered migration steps; `MIGRATIONS[i]` upgrades a database from version
+/// `i + 1` to `i + 2`. Steps run after `SCHEMA`, so a new column must be added
+/// here (and indexed here, not in `SCHEMA`) for older databases to pick it up.
+const MIGRATIONS: &[fn(&Connection) -> Result<()>] = &[
+    add_runs_config_json,
+    unique_patch_per_run,
+    add_runs_score,
+    allow_build_failed_status,
+    add_runs_seed,
+];
+
+/// Apply the migration steps that take a database from version `from` to `to`.
+/// Each step runs in its own transaction together with the version bump.
+pub fn migrate(conn: &Connection, from: i64, to: i64) -> Result<()> {
+    if from < 1 || to > SCHEMA_VERSION || from > to {
+        return Err(MutationError::InvalidInput(format!(
+            "Cannot migrate database schema from version {} to {}",
+            from, to
+        )));
+    }
+    for version in from..to {
+        let tx = conn.unchecked_transaction()?;
+        MIGRATIONS[(version - 1) as usize](&tx)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', ?1)",
+            params![(version + 1).to_string()],
+        )?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Add a column, tolerating "duplicate column name" since the table may have
+/// just been created from `SCHEMA` with the column already present.
+fn add_column(conn: &Connection, table: &str, column: &str) -> Result<()> {
+    let sql = format!("ALTER TABLE {} ADD COLUMN {};", table, column);
+    if let Err(e) = conn.execute_batch(&sql) {
+        if !e.to_string().contains("duplicate column name") {
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+/// v1 -> v2: add `config_json` to `runs`.
+fn add_runs_config_json(conn: &Connection) -> Result<()> {
+    add_column(conn, "runs", "config_json TEXT")
+}
+
+/// v2 -> v3: databases created before `UNIQUE(run_id, patch_hash)` was part of
+/// the `mutants` table may hold duplicate rows. Keep the oldest row of each
+/// duplicate set and add a unique index so later inserts are ignored.
+fn unique_patch_per_run(conn: &Connection) -> Result<()> {
+    if has_unique_index(conn, "mutants", &["run_id", "patch_hash"])? {
+        return Ok(());
+    }
+    conn.execute_batch(
+        "DELETE FROM mutants WHERE id NOT IN
+           (SELECT MIN(id) FROM mutants GROUP BY run_id, patch_hash);
+         CREATE UNIQUE INDEX IF NOT EXISTS idx_mutants_run_patch
+           ON mutants(run_id, patch_hash);",
+    )?;
+    Ok(())
+}
+
+/// v3 -> v4: add the aggregate mutation `score` to `runs`.
+fn add_runs_score(conn: &Connection) -> Result<()> {
+    add_column(conn, "runs", "score REAL")
+}
+
+/// The `mutants` table as of version 5, created under a temporary name.
+const MUTANTS_V5_TABLE: &str = "
+CREATE TABLE mutants_new (
+  id              INTEGER PRIMARY KEY,
+  run_id          INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+  diff            TEXT NOT NULL,
+  patch_hash      TEXT NOT NULL,
+  status          TEXT NOT NULL DEFAULT 'pending'
+                    CHECK (status IN ('pending','running','killed','survived',
+                                      'timeout','error','skipped','equivalent','unproductive',
+                                      'build_failed')),
+  killed          INTEGER GENERATED ALWAYS AS (CASE WHEN status='killed' THEN 1 ELSE 0 END) VIRTUAL,
+  command_to_test TEXT,
+  file_path       TEXT,
+  operator        TEXT,
+  UNIQUE(run_id, patch_hash)
+);
 ";
 
+/// v4 -> v5: allow the `build_failed` status. SQLite cannot alter a CHECK
+/// constraint, so a `mutants` table that has one is rebuilt as of version 5,
+/// keeping the columns it shares with the old one. Version 3 already removed
+/// the rows that would break its `UNIQUE(run_id, patch_hash)`.
+fn allow_build_failed_status(conn: &Connection) -> Result<()> {
+    let table_sql: String = conn.query_row(
+        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'mutants'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !table_sql.contains("CHECK") || table_sql.contains("'build_failed'") {
+        return Ok(());
+    }
+    conn.execute_batch(MUTANTS_V5_TABLE)?;
+    let old_columns = table_columns(conn, "mutants")?;
+    let columns: Vec<String> = table_columns(conn, "mutants_new")?
+        .into_iter()
+        .filter(|column| old_columns.contains(column))
+        .collect();
+    let columns = columns.join(", ");
+    conn.execute_batch(&format!(
+        "INSERT INTO mutants_new ({columns}) SELECT {columns} FROM mutants;"
+    ))?;
+    conn.execute_batch(
+        "DROP TABLE mutants;
+         ALTER TABLE mutants_new RENAME TO mutants;
+         CREATE INDEX IF NOT EXISTS idx_mutants_run_status ON mutants(run_id, status);
+         CREATE INDEX IF NOT EXISTS idx_mutants_file ON mutants(file_path);
+         CREATE INDEX IF NOT EXISTS idx_mutants_operator ON mutants(operator);
+         CREATE INDEX IF NOT EXISTS idx_mutants_killed ON mutants(killed);",
+    )?;
+    Ok(())
+}
+
+/// The stored columns of `table`, in order. Generated columns are not listed.
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(columns)
+}
+
+/// Whether `table` has a unique index, or UNIQUE constraint, on exactly `columns`.
+fn has_unique_index(conn: &Connection, table: &str, columns: &[&str]) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA index_list({})", table))?;
+    let indexes: Vec<(String, bool)> = stmt
+        .query_map([], |row| Ok((row.get(1)?, row.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (index, unique) in indexes {
+        if !unique {
+            continue;
+        }
+        let mut stmt = conn.prepare(&format!("PRAGMA index_info({})", index))?;
+        let indexed: Vec<String> = stmt
+            .query_map([], |row| row.get(2))?
+            .collect::<rusqlite::Result<_>>()?;
+        if indexed == columns {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// v5 -> v6: add the `seed` a run's randomness was derived from to `runs`.
+fn add_runs_seed(conn: &Connection) -> Result<()> {
+    add_column(conn, "runs", "seed INTEGER")
+}
+
+/// Values accepted by the `status` column's CHECK constraint.
+pub const MUTANT_STATUSES: &[&str] = &[
+    "pending",
+    "running",
+    "killed",
+    "survived",
+    "timeout",
+    "error",
+    "skipped",
+    "equivalent",
+    "unproductive",
+    "build_failed",
+];
+
+/// Seeds are full `u64`s but SQLite integers are signed, so a seed is stored
+/// as the `i64` with the same bits.
+fn seed_to_sql(seed: u64) -> i64 {
+    seed as i64
+}
+
+fn seed_from_sql(value: i64) -> u64 {
+    value as u64
+}
+
+fn validate_status(status: &str) -> Result<()> {
+    if MUTANT_STATUSES.contains(&status) {
+        Ok(())
+    } else {
+        Err(MutationError::InvalidInput(format!(
+            "Invalid mutant status '{}' (expected one of: {})",
+            status,
+            MUTANT_STATUSES.join(", ")
+        )))
+    }
+}
+
 /// Data collected during mutation for a single generated mutant.
 pub struct MutantData {
     pub diff: String,
@@ -56,11 +271,75 @@ pub struct MutantData {
     pub operator: String,
 }
 
+/// A run with aggregate mutant counts, as listed by the `runs` command.
+/// Timeouts and build failures count as killed.
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub id: i64,
+    pub commit_hash: String,
+    pub pr_number: Option<i64>,
+    pub created_at: Option<String>,
+    pub total: u64,
+    pub killed: u64,
+    pub survived: u64,
+    /// Score stored by `analyze`, or killed / (killed + survived) when the run
+    /// predates stored scores. `None` if nothing was analyzed.
+    pub score: Option<f64>,
+    /// Seed passed with `--seed` or chosen for `--shuffle`, if any.
+    pub seed: Option<u64>,
+}
+
 /// A mutant row read back from the database.
+#[derive(Debug, Serialize)]
 pub struct MutantRow {
     pub id: i64,
     pub diff: String,
+    pub patch_hash: String,
     pub file_path: Option<String>,
+    pub operator: Option<String>,
+    pub status: String,
+    /// Command the mutant was tested with, once analyzed.
+    pub command_to_test: Option<String>,
+}
+
+/// Column the `mutants` command groups by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupColumn {
+    Operator,
+    File,
+}
+
+impl FromStr for GroupColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "operator" => Ok(GroupColumn::Operator),
+            "file" => Ok(GroupColumn::File),
+            other => Err(format!(
+                "unknown grouping '{}' (expected operator or file)",
+                other
+            )),
+        }
+    }
+}
+
+/// Mutant counts for one operator or file of a run.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MutantGroup {
+    /// Operator or file path; `None` for mutants stored without one.
+    pub key: Option<String>,
+    pub total: u64,
+    pub killed: u64,
+    pub survived: u64,
+}
+
+impl MutantGroup {
+    /// killed / (killed + survived), or `None` if none was analyzed.
+    pub fn kill_rate(&self) -> Option<f64> {
+        let analyzed = self.killed + self.survived;
+        (analyzed > 0).then(|| self.killed as f64 / analyzed as f64)
+    }
 }
 
 pub struct Database {
@@ -75,25 +354,67 @@ impl Database {
         Ok(Database { conn })
     }
 
-    /// Create tables and indexes if they do not yet exist, and apply any
-    /// additive migrations needed for older databases.
+    /// Create tables and indexes if they do not yet exist, and bring older
+    /// databases up to [`SCHEMA_VERSION`] by running pending migrations.
     pub fn ensure_schema(&self) -> Result<()> {
+        // Databases created before the `meta` table existed are treated as
+        // version 1; a database without any tables is created at the latest version.
+        let version = match self.schema_version()? {
+            Some(version) => version,
+            None if self.table_exists("mutants")? => 1,
+            None => SCHEMA_VERSION,
+        };
+        if version > SCHEMA_VERSION {
+            return Err(MutationError::InvalidInput(format!(
+                "Database schema version {} is newer than the supported version {}",
+                version, SCHEMA_VERSION
+            )));
+        }
+
         self.conn.execute_batch(SCHEMA)?;
-        // Migration: add config_json to runs if the column is missing.
-        // ALTER TABLE ADD COLUMN fails with "duplicate column name" when the
-        // column already exists; silence that specific error so the function
-        // is idempotent on databases created before this column was added.
-        if let Err(e) = self
-            .conn
-            .execute_batch("ALTER TABLE runs ADD COLUMN config_json TEXT;")
-        {
-            if !e.to_string().contains("duplicate column name") {
-                return Err(e.into());
-            }
+        migrate(&self.conn, version, SCHEMA_VERSION)?;
+        self.set_schema_version(SCHEMA_VERSION)
+    }
+
+    /// Return the version recorded in the `meta` table, if any.
+    pub fn schema_version(&self) -> Result<Option<i64>> {
+        if !self.table_exists("meta")? {
+            return Ok(None);
         }
+        let version: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'schema_version'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        version
+            .map(|v| {
+                v.parse().map_err(|_| {
+                    MutationError::InvalidInput(format!("Invalid schema_version '{}'", v))
+                })
+            })
+            .transpose()
+    }
+
+    fn set_schema_version(&self, version: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', ?1)",
+            params![version.to_string()],
+        )?;
         Ok(())
     }
 
+    fn table_exists(&self, name: &str) -> Result<bool> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![name],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
     /// Insert the Bitcoin Core project row if not already present.
     pub fn seed_projects(&self) -> Result<()> {
         self.conn.execute(
@@ -103,6 +424,48 @@ impl Database {
         Ok(())
     }
 
+    /// Return the id of the project with `repository_url` (or, without one, of
+    /// the URL-less project called `name`), inserting it if it is unseen. A new
+    /// project whose name is taken is named after its URL instead.
+    pub fn get_or_create_project(&self, name: &str, repository_url: Option<&str>) -> Result<i64> {
+        let existing = match repository_url {
+            Some(url) => self
+                .conn
+                .query_row(
+                    "SELECT id FROM projects WHERE repository_url = ?1",
+                    params![url],
+                    |row| row.get(0),
+                )
+                .optional()?,
+            None => self
+                .conn
+                .query_row(
+                    "SELECT id FROM projects WHERE name = ?1 AND repository_url IS NULL",
+                    params![name],
+                    |row| row.get(0),
+                )
+                .optional()?,
+        };
+        if let Some(id) = existing {
+            return Ok(id);
+        }
+
+        let name_taken: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM projects WHERE name = ?1)",
+            params![name],
+            |row| row.get(0),
+        )?;
+        let name = match repository_url {
+            Some(url) if name_taken => url,
+            _ => name,
+        };
+        self.conn.execute(
+            "INSERT INTO projects (name, repository_url) VALUES (?1, ?2)",
+            params![name, repository_url],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
     /// Return the id of the Bitcoin Core project row.
     pub fn get_bitcoin_core_project_id(&self) -> Result<i64> {
         let id = self.conn.query_row(
@@ -121,19 +484,137 @@ impl Database {
         tool_version: &str,
         pr_number: Option<u32>,
         config_json: Option<&str>,
+        seed: Option<u64>,
     ) -> Result<i64> {
         self.conn.execute(
-            "INSERT INTO runs (project_id, commit_hash, tool_version, pr_number, config_json)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![project_id, commit_hash, tool_version, pr_number, config_json],
+            "INSERT INTO runs (project_id, commit_hash, tool_version, pr_number, config_json, seed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                project_id,
+                commit_hash,
+                tool_version,
+                pr_number,
+                config_json,
+                seed.map(seed_to_sql)
+            ],
         )?;
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// Return the commit hash a run was generated against, or `None` if the run does not exist.
+    pub fn get_run_commit(&self, run_id: i64) -> Result<Option<String>> {
+        let commit = self
+            .conn
+            .query_row(
+                "SELECT commit_hash FROM runs WHERE id = ?1",
+                params![run_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(commit)
+    }
+
+    /// Delete a run and, through `ON DELETE CASCADE`, its mutants. Returns the
+    /// number of mutants deleted, or `None` if the run does not exist.
+    pub fn delete_run(&self, run_id: i64) -> Result<Option<u64>> {
+        let mutants: u64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM mutants WHERE run_id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )?;
+        let deleted = self
+            .conn
+            .execute("DELETE FROM runs WHERE id = ?1", params![run_id])?;
+        Ok((deleted > 0).then_some(mutants))
+    }
+
+    /// Return every run, newest first, with aggregate mutant counts.
+    pub fn list_runs(&self) -> Result<Vec<RunSummary>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.id, r.commit_hash, r.pr_number, r.created_at, r.score, r.seed,
+                    COUNT(m.id),
+                    COALESCE(SUM(CASE WHEN m.status IN ('killed','timeout','build_failed')
+                                      THEN 1 ELSE 0 END), 0),
+                    COALESCE(SUM(CASE WHEN m.status = 'survived' THEN 1 ELSE 0 END), 0)
+             FROM runs r LEFT JOIN mutants m ON m.run_id = r.id
+             GROUP BY r.id
+             ORDER BY r.created_at DESC, r.id DESC",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                let stored_score: Option<f64> = row.get(4)?;
+                let killed: u64 = row.get(7)?;
+                let survived: u64 = row.get(8)?;
+                let analyzed = killed + survived;
+                Ok(RunSummary {
+                    id: row.get(0)?,
+                    commit_hash: row.get(1)?,
+                    pr_number: row.get(2)?,
+                    created_at: row.get(3)?,
+                    total: row.get(6)?,
+                    killed,
+                    survived,
+                    score: stored_score
+                        .or_else(|| (analyzed > 0).then(|| killed as f64 / analyzed as f64)),
+                    seed: row.get::<_, Option<i64>>(5)?.map(seed_from_sql),
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
+
+    /// Store the aggregate mutation score of a run.
+    pub fn update_run_score(&self, run_id: i64, score: f64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE runs SET score = ?1 WHERE id = ?2",
+            params![score, run_id],
+        )?;
+        Ok(())
+    }
+
+    /// Return `(file_path, killed, analyzed)` per file in `run_id`, sorted by file.
+    /// `counts_as_killed` classifies each status as killed, survived or not
+    /// counted (`None`), as for a folder analysis.
+    pub fn get_file_kill_counts(
+        &self,
+        run_id: i64,
+        counts_as_killed: impl Fn(&str) -> Option<bool>,
+    ) -> Result<Vec<(String, u64, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT COALESCE(file_path, ''), status, COUNT(*)
+             FROM mutants WHERE run_id = ?1 GROUP BY file_path, status",
+        )?;
+        let rows = stmt
+            .query_map(params![run_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<rusqlite::Result<Vec<(String, String, u64)>>>()?;
+
+        let mut counts: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+        for (file, status, count) in rows {
+            let (killed, analyzed) = counts.entry(file).or_default();
+            if let Some(is_killed) = counts_as_killed(&status) {
+                *analyzed += count;
+                if is_killed {
+                    *killed += count;
+                }
+            }
+        }
+        Ok(counts
+            .into_iter()
+            .map(|(file, (killed, analyzed))| (file, killed, analyzed))
+            .collect())
+    }
+
     /// Batch-insert mutants under `run_id` using a single transaction.
     /// Duplicates (same run_id + patch_hash) are silently ignored.
-    pub fn insert_mutant_batch(&mut self, run_id: i64, mutants: &[MutantData]) -> Result<()> {
+    pub fn insert_mutant_batch(
+        &mut self,
+        run_id: i64,
+        mutants: &[MutantData],
+    ) -> Result<(usize, usize)> {
         let tx = self.conn.transaction()?;
+        let mut inserted = 0;
         {
             let mut stmt = tx.prepare(
                 "INSERT OR IGNORE INTO mutants
@@ -141,7 +622,7 @@ impl Database {
                  VALUES (?1, ?2, ?3, 'pending', ?4, ?5)",
             )?;
             for m in mutants {
-                stmt.execute(params![
+                inserted += stmt.execute(params![
                     run_id,
                     m.diff,
                     m.patch_hash,
@@ -151,7 +632,7 @@ impl Database {
             }
         }
         tx.commit()?;
-        Ok(())
+        Ok((inserted, mutants.len() - inserted))
     }
 
     /// Return mutants belonging to `run_id`, optionally filtered by `file_path`.
@@ -162,62 +643,150 @@ impl Database {
         file_path: Option<&str>,
         survivors_only: bool,
     ) -> Result<Vec<MutantRow>> {
-        let map_row = |row: &rusqlite::Row<'_>| {
-            Ok(MutantRow {
-                id: row.get(0)?,
-                diff: row.get(1)?,
-                file_path: row.get(2)?,
-            })
-        };
+        self.query_mutants(run_id, survivors_only.then_some("survived"), file_path)
+    }
 
-        let rows: Vec<MutantRow> = match (file_path, survivors_only) {
-            (Some(fp), false) => {
-                let mut stmt = self.conn.prepare(
-                    "SELECT id, diff, file_path FROM mutants WHERE run_id = ?1 AND file_path = ?2",
-                )?;
-                let rows = stmt.query_map(params![run_id, fp], map_row)?
-                    .collect::<rusqlite::Result<_>>()?;
-                rows
-            }
-            (Some(fp), true) => {
-                let mut stmt = self.conn.prepare(
-                    "SELECT id, diff, file_path FROM mutants \
-                     WHERE run_id = ?1 AND file_path = ?2 AND status = 'survived'",
-                )?;
-                let rows = stmt.query_map(params![run_id, fp], map_row)?
-                    .collect::<rusqlite::Result<_>>()?;
-                rows
-            }
-            (None, false) => {
-                let mut stmt = self.conn.prepare(
-                    "SELECT id, diff, file_path FROM mutants WHERE run_id = ?1",
-                )?;
-                let rows = stmt.query_map(params![run_id], map_row)?
-                    .collect::<rusqlite::Result<_>>()?;
-                rows
-            }
-            (None, true) => {
-                let mut stmt = self.conn.prepare(
-                    "SELECT id, diff, file_path FROM mutants \
-                     WHERE run_id = ?1 AND status = 'survived'",
-                )?;
-                let rows = stmt.query_map(params![run_id], map_row)?
-                    .collect::<rusqlite::Result<_>>()?;
-                rows
-            }
-        };
+    /// Return the mutants of `run_id` with the given status and file, if any,
+    /// in insertion order.
+    pub fn query_mutants(
+        &self,
+        run_id: i64,
+        status: Option<&str>,
+        file_path: Option<&str>,
+    ) -> Result<Vec<MutantRow>> {
+        if let Some(status) = status {
+            validate_status(status)?;
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT id, diff, file_path, patch_hash, operator, status, command_to_test FROM mutants
+             WHERE run_id = ?1
+               AND (?2 IS NULL OR status = ?2)
+               AND (?3 IS NULL OR file_path = ?3)
+             ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(params![run_id, status, file_path], |row| {
+                Ok(MutantRow {
+                    id: row.get(0)?,
+                    diff: row.get(1)?,
+                    file_path: row.get(2)?,
+                    patch_hash: row.get(3)?,
+                    operator: row.get(4)?,
+                    status: row.get(5)?,
+                    command_to_test: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
 
+    /// Count the mutants of `run_id` per operator or per file (`column`), with
+    /// timeouts and build failures counted as killed like in [`list_runs`].
+    ///
+    /// [`list_runs`]: Database::list_runs
+    pub fn count_mutants_by(
+        &self,
+        run_id: i64,
+        column: GroupColumn,
+        file_path: Option<&str>,
+    ) -> Result<Vec<MutantGroup>> {
+        let column = match column {
+            GroupColumn::Operator => "operator",
+            GroupColumn::File => "file_path",
+        };
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {column}, COUNT(*),
+                    SUM(CASE WHEN status IN ('killed','timeout','build_failed') THEN 1 ELSE 0 END),
+                    SUM(CASE WHEN status = 'survived' THEN 1 ELSE 0 END)
+             FROM mutants
+             WHERE run_id = ?1 AND (?2 IS NULL OR file_path = ?2)
+             GROUP BY {column}
+             ORDER BY {column}"
+        ))?;
+        let rows = stmt
+            .query_map(params![run_id, file_path], |row| {
+                Ok(MutantGroup {
+                    key: row.get(0)?,
+                    total: row.get(1)?,
+                    killed: row.get(2)?,
+                    survived: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
         Ok(rows)
     }
 
     /// Update the status and command_to_test for a single mutant.
     pub fn update_mutant_status(&self, id: i64, status: &str, command: &str) -> Result<()> {
+        validate_status(status)?;
         self.conn.execute(
             "UPDATE mutants SET status = ?1, command_to_test = ?2 WHERE id = ?3",
             params![status, command, id],
         )?;
         Ok(())
     }
+
+    /// Update the status and command_to_test of the mutant in `run_id`
+    /// identified by `patch_hash`. Returns `false` when no such mutant exists in
+    /// the run.
+    pub fn update_status_mutant(
+        &self,
+        run_id: i64,
+        patch_hash: &str,
+        status: &str,
+        command: &str,
+    ) -> Result<bool> {
+        validate_status(status)?;
+        let updated = self.conn.execute(
+            "UPDATE mutants SET status = ?1, command_to_test = ?2
+             WHERE run_id = ?3 AND patch_hash = ?4",
+            params![status, command, run_id, patch_hash],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Look up a previously analyzed status for the same patch against the same commit.
+    pub fn get_cached_status(&self, commit_hash: &str, patch_hash: &str) -> Result<Option<String>> {
+        let status = self
+            .conn
+            .query_row(
+                "SELECT status FROM analysis_cache WHERE commit_hash = ?1 AND patch_hash = ?2",
+                params![commit_hash, patch_hash],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(status)
+    }
+
+    /// Look up the most recently cached status for the same patch, against any
+    /// commit, skipping the statuses in `except`.
+    pub fn get_latest_cached_status(
+        &self,
+        patch_hash: &str,
+        except: &[&str],
+    ) -> Result<Option<String>> {
+        // `INSERT OR REPLACE` gives a re-analyzed patch a new rowid, so the
+        // highest rowid is the latest analysis.
+        let mut stmt = self.conn.prepare(
+            "SELECT status FROM analysis_cache WHERE patch_hash = ?1 ORDER BY rowid DESC",
+        )?;
+        let statuses = stmt
+            .query_map(params![patch_hash], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(statuses
+            .into_iter()
+            .find(|status| !except.contains(&status.as_str())))
+    }
+
+    /// Record the analysis status of a patch against a commit, replacing any previous entry.
+    pub fn cache_status(&self, commit_hash: &str, patch_hash: &str, status: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO analysis_cache (commit_hash, patch_hash, status)
+             VALUES (?1, ?2, ?3)",
+            params![commit_hash, patch_hash, status],
+        )?;
+        Ok(())
+    }
 }
 
 /// Compute the SHA-256 hex digest of `diff`.
@@ -233,17 +802,23 @@ pub fn compute_patch_hash(diff: &str) -> String {
 pub async fn generate_diff(file_path: &str, mutated_content: &str) -> Result<String> {
     use std::io::Write;
     use tempfile::NamedTempFile;
-    use tokio::process::Command;
 
     let mut tmp = NamedTempFile::new()?;
     tmp.write_all(mutated_content.as_bytes())?;
     tmp.flush()?;
 
-    let tmp_path = tmp.path().to_string_lossy().to_string();
+    generate_diff_from_file(file_path, tmp.path()).await
+}
+
+/// Like [`generate_diff`], for mutated contents already written to `mutated_path`.
+pub async fn generate_diff_from_file(file_path: &str, mutated_path: &Path) -> Result<String> {
+    use tokio::process::Command;
+
+    let mutated_path = mutated_path.to_string_lossy().to_string();
 
     // `git diff --no-index` exits with 1 when differences exist — that is expected.
     let output = Command::new("git")
-        .args(["diff", "--no-index", "--", file_path, &tmp_path])
+        .args(["diff", "--no-index", "--", file_path, &mutated_path])
         .output()
         .await
         .map_err(|e| MutationError::Git(format!("git diff failed to spawn: {}", e)))?;
@@ -283,3 +858,512 @@ pub async fn generate_diff(file_path: &str, mutated_content: &str) -> Result<Str
 
     Ok(fixed)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyze::AnalysisOptions;
+    use crate::test_util::enter_dir;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn open_test_db(dir: &Path) -> Database {
+        let db = Database::open(&dir.join("mutation.db")).unwrap();
+        db.ensure_schema().unwrap();
+        db.seed_projects().unwrap();
+        db
+    }
+
+    #[test]
+    fn test_get_or_create_project() {
+        let temp_dir = tempdir().unwrap();
+        let db = open_test_db(temp_dir.path());
+        let bitcoin_core = db.get_bitcoin_core_project_id().unwrap();
+
+        let upstream = Some("https://github.com/bitcoin/bitcoin");
+        assert_eq!(
+            db.get_or_create_project("bitcoin/bitcoin", upstream)
+                .unwrap(),
+            bitcoin_core
+        );
+        let fork = Some("https://github.com/alice/bitcoin");
+        let fork_id = db.get_or_create_project("alice/bitcoin", fork).unwrap();
+        assert_ne!(fork_id, bitcoin_core);
+        assert_eq!(
+            db.get_or_create_project("alice/bitcoin", fork).unwrap(),
+            fork_id
+        );
+        // Another repository with the same name is told apart by its URL.
+        let mirror = Some("https://gitlab.com/alice/bitcoin");
+        let mirror_id = db.get_or_create_project("alice/bitcoin", mirror).unwrap();
+        assert_ne!(mirror_id, fork_id);
+        let name: String = db
+            .conn
+            .query_row(
+                "SELECT name FROM projects WHERE id = ?1",
+                params![mirror_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(name, "https://gitlab.com/alice/bitcoin");
+        // Without a remote, projects are found by name.
+        let local = db.get_or_create_project("scratch", None).unwrap();
+        assert_eq!(db.get_or_create_project("scratch", None).unwrap(), local);
+    }
+
+    #[test]
+    fn test_update_status_mutant() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = open_test_db(temp_dir.path());
+        let project_id = db.get_bitcoin_core_project_id().unwrap();
+        let run_id = db
+            .create_run(project_id, "abc", "0.0.0", None, None, None)
+            .unwrap();
+        db.insert_mutant_batch(
+            run_id,
+            &[MutantData {
+                diff: "diff".to_string(),
+                patch_hash: "hash1".to_string(),
+                file_path: "src/test.cpp".to_string(),
+                operator: "op".to_string(),
+            }],
+        )
+        .unwrap();
+
+        assert!(db
+            .update_status_mutant(run_id, "hash1", "timeout", "make check")
+            .unwrap());
+        assert!(!db
+            .update_status_mutant(run_id, "missing", "killed", "make check")
+            .unwrap());
+        assert!(db
+            .update_status_mutant(run_id, "hash1", "bogus", "make check")
+            .is_err());
+
+        let mutants = db.query_mutants(run_id, None, None).unwrap();
+        assert_eq!(mutants[0].status, "timeout");
+        assert_eq!(mutants[0].command_to_test.as_deref(), Some("make check"));
+    }
+
+    #[tokio::test]
+    async fn test_stored_mutant_keeps_real_diff() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let mut db = open_test_db(temp_dir.path());
+        let project_id = db.get_bitcoin_core_project_id().unwrap();
+        let run_id = db
+            .create_run(project_id, "abc", "0.0.0", None, None, None)
+            .unwrap();
+
+        let source = temp_dir.path().join("test.cpp");
+        fs::write(&source, "int x = a + b;\n").unwrap();
+        let source = source.to_str().unwrap();
+
+        // Identical content has no diff and is never stored.
+        assert!(generate_diff(source, "int x = a + b;\n").await.is_err());
+
+        let diff = generate_diff(source, "int x = a - b;\n").await.unwrap();
+        db.insert_mutant_batch(
+            run_id,
+            &[MutantData {
+                patch_hash: compute_patch_hash(&diff),
+                diff,
+                file_path: source.to_string(),
+                operator: "op".to_string(),
+            }],
+        )
+        .unwrap();
+
+        let rows = db.get_mutants_for_run(run_id, None, false).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert!(!rows[0].diff.is_empty());
+        assert!(rows[0].diff.contains("+int x = a - b;"));
+    }
+
+    #[test]
+    fn test_insert_mutant_batch_skips_duplicate_patches() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = open_test_db(temp_dir.path());
+        let project_id = db.get_bitcoin_core_project_id().unwrap();
+        let run_id = db
+            .create_run(project_id, "abc", "0.0.0", None, None, None)
+            .unwrap();
+        let mutant = |hash: &str| MutantData {
+            diff: "diff".to_string(),
+            patch_hash: hash.to_string(),
+            file_path: "src/test.cpp".to_string(),
+            operator: "op".to_string(),
+        };
+
+        let counts = db
+            .insert_mutant_batch(run_id, &[mutant("h1"), mutant("h1"), mutant("h2")])
+            .unwrap();
+        assert_eq!(counts, (2, 1));
+        let counts = db.insert_mutant_batch(run_id, &[mutant("h2")]).unwrap();
+        assert_eq!(counts, (0, 1));
+        assert_eq!(
+            db.get_mutants_for_run(run_id, None, false).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_ensure_schema_dedupes_legacy_mutants() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("mutation.db");
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE mutants (
+                   id INTEGER PRIMARY KEY, run_id INTEGER NOT NULL, diff TEXT NOT NULL,
+                   patch_hash TEXT NOT NULL, status TEXT NOT NULL DEFAULT 'pending',
+                   killed INTEGER GENERATED ALWAYS AS (status='killed') VIRTUAL,
+                   command_to_test TEXT, file_path TEXT, operator TEXT);
+                 INSERT INTO mutants (run_id, diff, patch_hash) VALUES
+                   (1, 'a', 'h1'), (1, 'a', 'h1'), (1, 'b', 'h2'), (2, 'a', 'h1');",
+            )
+            .unwrap();
+        }
+
+        let db = Database::open(&db_path).unwrap();
+        db.ensure_schema().unwrap();
+        let count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM mutants", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+        assert!(db
+            .conn
+            .execute(
+                "INSERT INTO mutants (run_id, diff, patch_hash) VALUES (1, 'a', 'h1')",
+                [],
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_has_unique_index() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE a (x INTEGER, y INTEGER, UNIQUE (x,y));
+             CREATE TABLE b (x INTEGER, y INTEGER);
+             CREATE INDEX idx_b ON b(x, y);
+             CREATE TABLE c (x INTEGER, y INTEGER);
+             CREATE UNIQUE INDEX idx_c ON c(y, x);",
+        )
+        .unwrap();
+        assert!(has_unique_index(&conn, "a", &["x", "y"]).unwrap());
+        assert!(!has_unique_index(&conn, "b", &["x", "y"]).unwrap());
+        assert!(!has_unique_index(&conn, "c", &["x", "y"]).unwrap());
+        assert!(has_unique_index(&conn, "c", &["y", "x"]).unwrap());
+        assert_eq!(table_columns(&conn, "a").unwrap(), ["x", "y"]);
+    }
+
+    #[test]
+    fn test_fresh_database_is_at_latest_version() {
+        let temp_dir = tempdir().unwrap();
+        let db = open_test_db(temp_dir.path());
+        assert_eq!(db.schema_version().unwrap(), Some(SCHEMA_VERSION));
+        // Re-running is a no-op.
+        db.ensure_schema().unwrap();
+        assert_eq!(db.schema_version().unwrap(), Some(SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn test_ensure_schema_migrates_unversioned_database() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("mutation.db");
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE runs (
+                   id INTEGER PRIMARY KEY, project_id INTEGER NOT NULL, commit_hash TEXT NOT NULL,
+                   pr_number INTEGER, created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                   tool_version TEXT);
+                 CREATE TABLE mutants (
+                   id INTEGER PRIMARY KEY, run_id INTEGER NOT NULL, diff TEXT NOT NULL,
+                   patch_hash TEXT NOT NULL, status TEXT NOT NULL DEFAULT 'pending',
+                   killed INTEGER GENERATED ALWAYS AS (status='killed') VIRTUAL,
+                   command_to_test TEXT, file_path TEXT, operator TEXT,
+                   UNIQUE(run_id, patch_hash));",
+            )
+            .unwrap();
+        }
+
+        let db = Database::open(&db_path).unwrap();
+        db.ensure_schema().unwrap();
+        db.seed_projects().unwrap();
+        assert_eq!(db.schema_version().unwrap(), Some(SCHEMA_VERSION));
+        let project_id = db.get_bitcoin_core_project_id().unwrap();
+        db.create_run(project_id, "abc", "0.0.0", None, Some("{}"), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_migration_allows_build_failed_status() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("mutation.db");
+        {
+            // A version 4 database, whose CHECK constraint predates `build_failed`.
+            let conn = Connection::open(&db_path).unwrap();
+            let v4_schema = SCHEMA.replace(
+                "'unproductive',\n                                      'build_failed')",
+                "'unproductive')",
+            );
+            assert_ne!(v4_schema, SCHEMA);
+            conn.execute_batch(&v4_schema).unwrap();
+            conn.execute_batch(
+                "INSERT INTO meta (key, value) VALUES ('schema_version', '4');
+                 INSERT INTO projects (id, name) VALUES (1, 'p');
+                 INSERT INTO runs (id, project_id, commit_hash) VALUES (1, 1, 'abc');
+                 INSERT INTO mutants (run_id, diff, patch_hash, status, operator)
+                   VALUES (1, 'd', 'h1', 'killed', 'op');",
+            )
+            .unwrap();
+        }
+
+        let db = Database::open(&db_path).unwrap();
+        db.ensure_schema().unwrap();
+        assert_eq!(db.schema_version().unwrap(), Some(SCHEMA_VERSION));
+        assert!(db
+            .update_status_mutant(1, "h1", "build_failed", "make check")
+            .unwrap());
+        let (status, killed): (String, i64) = db
+            .conn
+            .query_row("SELECT status, killed FROM mutants", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((status.as_str(), killed), ("build_failed", 0));
+        assert!(db
+            .conn
+            .execute(
+                "INSERT INTO mutants (run_id, diff, patch_hash) VALUES (1, 'd', 'h1')",
+                [],
+            )
+            .is_err());
+        let mut options = AnalysisOptions::default();
+        assert_eq!(
+            db.get_file_kill_counts(1, |status| options.counts_as_killed(status))
+                .unwrap(),
+            vec![(String::new(), 1, 1)]
+        );
+        options.exclude_build_failures = true;
+        assert_eq!(
+            db.get_file_kill_counts(1, |status| options.counts_as_killed(status))
+                .unwrap(),
+            vec![(String::new(), 0, 0)]
+        );
+    }
+
+    #[test]
+    fn test_ensure_schema_rejects_newer_database() {
+        let temp_dir = tempdir().unwrap();
+        let db = open_test_db(temp_dir.path());
+        db.set_schema_version(SCHEMA_VERSION + 1).unwrap();
+        assert!(db.ensure_schema().is_err());
+        assert!(migrate(&db.conn, 2, 1).is_err());
+    }
+
+    #[test]
+    fn test_list_runs_counts_mutants() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = open_test_db(temp_dir.path());
+        let project_id = db.get_bitcoin_core_project_id().unwrap();
+        let empty_run = db
+            .create_run(project_id, "aaa", "0.0.0", None, None, None)
+            .unwrap();
+        let run_id = db
+            .create_run(project_id, "bbb", "0.0.0", Some(123), None, Some(u64::MAX))
+            .unwrap();
+        let mutants: Vec<MutantData> = ["h1", "h2", "h3", "h4"]
+            .iter()
+            .map(|hash| MutantData {
+                diff: "diff".to_string(),
+                patch_hash: hash.to_string(),
+                file_path: "src/test.cpp".to_string(),
+                operator: "op".to_string(),
+            })
+            .collect();
+        db.insert_mutant_batch(run_id, &mutants).unwrap();
+        db.update_status_mutant(run_id, "h1", "killed", "make check")
+            .unwrap();
+        db.update_status_mutant(run_id, "h2", "timeout", "make check")
+            .unwrap();
+        db.update_status_mutant(run_id, "h3", "survived", "make check")
+            .unwrap();
+
+        let runs = db.list_runs().unwrap();
+        assert_eq!(runs.len(), 2);
+        let run = runs.iter().find(|r| r.id == run_id).unwrap();
+        assert_eq!(run.pr_number, Some(123));
+        assert_eq!(run.seed, Some(u64::MAX));
+        assert_eq!((run.total, run.killed, run.survived), (4, 2, 1));
+        assert!((run.score.unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        let empty = runs.iter().find(|r| r.id == empty_run).unwrap();
+        assert_eq!(empty.total, 0);
+        assert_eq!(empty.seed, None);
+        assert_eq!(empty.score, None);
+
+        // A stored score takes precedence over the one derived from counts.
+        db.update_run_score(run_id, 0.25).unwrap();
+        let runs = db.list_runs().unwrap();
+        let run = runs.iter().find(|r| r.id == run_id).unwrap();
+        assert_eq!(run.score, Some(0.25));
+    }
+
+    #[test]
+    fn test_delete_run_cascades_to_mutants() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = open_test_db(temp_dir.path());
+        let project_id = db.get_bitcoin_core_project_id().unwrap();
+        let kept = db
+            .create_run(project_id, "aaa", "0.0.0", None, None, None)
+            .unwrap();
+        let deleted = db
+            .create_run(project_id, "bbb", "0.0.0", None, None, None)
+            .unwrap();
+        for run_id in [kept, deleted] {
+            let mutants: Vec<MutantData> = ["h1", "h2"]
+                .iter()
+                .map(|hash| MutantData {
+                    diff: "diff".to_string(),
+                    patch_hash: hash.to_string(),
+                    file_path: "src/test.cpp".to_string(),
+                    operator: "op".to_string(),
+                })
+                .collect();
+            db.insert_mutant_batch(run_id, &mutants).unwrap();
+        }
+
+        // Foreign keys are enabled on every connection, so the cascade applies
+        // to a database opened again.
+        drop(db);
+        let mut db = Database::open(&temp_dir.path().join("mutation.db")).unwrap();
+        assert_eq!(db.delete_run(deleted).unwrap(), Some(2));
+        assert_eq!(db.delete_run(deleted).unwrap(), None);
+        let count = |run_id: i64| -> i64 {
+            db.conn
+                .query_row(
+                    "SELECT COUNT(*) FROM mutants WHERE run_id = ?1",
+                    params![run_id],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        assert_eq!((count(kept), count(deleted)), (2, 0));
+        assert_eq!(db.list_runs().unwrap().len(), 1);
+
+        // Mutants of a run that does not exist are rejected.
+        let orphan = MutantData {
+            diff: "diff".to_string(),
+            patch_hash: "h3".to_string(),
+            file_path: "src/test.cpp".to_string(),
+            operator: "op".to_string(),
+        };
+        assert!(db.insert_mutant_batch(deleted, &[orphan]).is_err());
+    }
+
+    #[test]
+    fn test_query_mutants() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = open_test_db(temp_dir.path());
+        let project_id = db.get_bitcoin_core_project_id().unwrap();
+        let run_id = db
+            .create_run(project_id, "abc", "0.0.0", None, None, None)
+            .unwrap();
+        let mutants: Vec<MutantData> = [
+            ("h1", "a.cpp", "op1"),
+            ("h2", "a.cpp", "op2"),
+            ("h3", "b.cpp", "op1"),
+        ]
+        .iter()
+        .map(|(hash, file, operator)| MutantData {
+            diff: "diff".to_string(),
+            patch_hash: hash.to_string(),
+            file_path: file.to_string(),
+            operator: operator.to_string(),
+        })
+        .collect();
+        db.insert_mutant_batch(run_id, &mutants).unwrap();
+        db.update_status_mutant(run_id, "h1", "survived", "make check")
+            .unwrap();
+        db.update_status_mutant(run_id, "h2", "killed", "make check")
+            .unwrap();
+        db.update_status_mutant(run_id, "h3", "timeout", "make check")
+            .unwrap();
+
+        let survivors = db.query_mutants(run_id, Some("survived"), None).unwrap();
+        assert_eq!(survivors.len(), 1);
+        assert_eq!(survivors[0].patch_hash, "h1");
+        assert_eq!(survivors[0].status, "survived");
+        let in_a = db.query_mutants(run_id, None, Some("a.cpp")).unwrap();
+        assert_eq!(
+            in_a.iter()
+                .map(|m| m.patch_hash.as_str())
+                .collect::<Vec<_>>(),
+            ["h1", "h2"]
+        );
+        assert!(db.query_mutants(run_id, Some("alive"), None).is_err());
+
+        let by_operator = db
+            .count_mutants_by(run_id, GroupColumn::Operator, None)
+            .unwrap();
+        assert_eq!(
+            by_operator,
+            vec![
+                MutantGroup {
+                    key: Some("op1".to_string()),
+                    total: 2,
+                    killed: 1,
+                    survived: 1
+                },
+                MutantGroup {
+                    key: Some("op2".to_string()),
+                    total: 1,
+                    killed: 1,
+                    survived: 0
+                },
+            ]
+        );
+        assert_eq!(by_operator[0].kill_rate(), Some(0.5));
+        let by_file = db
+            .count_mutants_by(run_id, GroupColumn::File, Some("b.cpp"))
+            .unwrap();
+        assert_eq!(by_file.len(), 1);
+        assert_eq!(by_file[0].key.as_deref(), Some("b.cpp"));
+    }
+
+    #[test]
+    fn test_analysis_cache_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let db = open_test_db(temp_dir.path());
+
+        assert_eq!(db.get_cached_status("abc", "hash1").unwrap(), None);
+
+        db.cache_status("abc", "hash1", "survived").unwrap();
+        assert_eq!(
+            db.get_cached_status("abc", "hash1").unwrap().as_deref(),
+            Some("survived")
+        );
+
+        // A different commit must not reuse the result.
+        assert_eq!(db.get_cached_status("def", "hash1").unwrap(), None);
+
+        // Re-analysis replaces the cached status.
+        db.cache_status("abc", "hash1", "killed").unwrap();
+        assert_eq!(
+            db.get_cached_status("abc", "hash1").unwrap().as_deref(),
+            Some("killed")
+        );
+
+        // Across commits, the latest analysis wins.
+        db.cache_status("def", "hash1", "survived").unwrap();
+        let latest = |except: &[&str]| db.get_latest_cached_status("hash1", except).unwrap();
+        assert_eq!(latest(&[]).as_deref(), Some("survived"));
+        assert_eq!(latest(&["survived"]).as_deref(), Some("killed"));
+        db.cache_status("abc", "hash1", "timeout").unwrap();
+        assert_eq!(latest(&[]).as_deref(), Some("timeout"));
+        assert_eq!(db.get_latest_cached_status("hash2", &[]).unwrap(), None);
+    }
+}
@@ -14,6 +14,49 @@ pub enum MutationError {
     #[error("Command execution failed: {0}")]
     Command(String),
 
+    #[error("Mutant failed to build: {0}")]
+    MutantBuildFailed(String),
+
+    #[error("Mutation score {score:.2} is below the --fail-under threshold of {threshold:.2}")]
+    ScoreBelowThreshold { score: f64, threshold: f64 },
+
+    #[error("Survival rate {rate:.2} of {file} is above its threshold of {threshold:.2}")]
+    FileSurvivalAboveThreshold {
+        file: String,
+        rate: f64,
+        threshold: f64,
+    },
+
+    #[error(
+        "Tests fail on the unmutated target: {0}; pass --skip-baseline-check to analyze anyway"
+    )]
+    BaselineFailed(String),
+
+    #[error(
+        "Target {target} of {folder} {reason}; was it moved since the mutants were generated?"
+    )]
+    MissingTarget {
+        target: String,
+        folder: String,
+        reason: &'static str,
+    },
+
+    #[error(
+        "{0} has uncommitted changes that restoring it after the mutants would discard; commit or stash them, or pass --force"
+    )]
+    DirtyTarget(String),
+
+    #[error("{0} folder(s) could not be analyzed")]
+    FoldersFailed(usize),
+
+    #[error("{0} mutants survive that did not survive in the baseline")]
+    NewSurvivors(usize),
+
+    #[error(
+        "{mutant} failed to build and was left applied to {target}; run `git restore {target}` when done"
+    )]
+    MutantKept { mutant: String, target: String },
+
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
@@ -33,4 +76,17 @@ pub enum MutationError {
     Other(#[from] anyhow::Error),
 }
 
+impl MutationError {
+    /// Process exit code for the error, so CI can tell a low mutation score
+    /// apart from the tool failing.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            MutationError::ScoreBelowThreshold { .. }
+            | MutationError::FileSurvivalAboveThreshold { .. }
+            | MutationError::NewSurvivors(_) => 3,
+            _ => 1,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, MutationError>;
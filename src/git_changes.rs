@@ -1,7 +1,17 @@
 use crate::error::{MutationError, Result};
+use log::{debug, info, warn};
 use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::process::Command;
 use std::str;
+use std::time::Duration;
+
+/// Times a PR fetch is attempted when the remote cannot be reached.
+const FETCH_ATTEMPTS: u32 = 3;
+
+/// Delay before the second fetch attempt, doubled before each later one.
+const FETCH_BACKOFF: Duration = Duration::from_secs(2);
 
 pub async fn run_git_command(args: &[&str]) -> Result<Vec<String>> {
     let output = Command::new("git")
@@ -28,45 +38,174 @@ pub async fn get_commit_hash() -> Result<String> {
     Ok(lines.into_iter().next().unwrap_or_default())
 }
 
+/// Name and URL of the repository being mutated, taken from its `origin`
+/// remote, e.g. `bitcoin/bitcoin` and `https://github.com/bitcoin/bitcoin`.
+/// Without a remote, the repository is named after its top-level directory.
+pub async fn get_repository() -> Result<(String, Option<String>)> {
+    if let Ok(lines) = run_git_command(&["remote", "get-url", "origin"]).await {
+        if let Some(url) = lines.first().filter(|url| !url.is_empty()) {
+            let (name, url) = parse_remote_url(url);
+            return Ok((name, Some(url)));
+        }
+    }
+    let lines = run_git_command(&["rev-parse", "--show-toplevel"]).await?;
+    let toplevel = lines.into_iter().next().unwrap_or_default();
+    let name = toplevel
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or("unknown");
+    Ok((name.to_string(), None))
+}
+
+/// The `owner/repo` name of a remote and its URL, with SSH remotes
+/// (`git@host:owner/repo.git`) written as HTTPS so both forms match.
+fn parse_remote_url(url: &str) -> (String, String) {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let url = match url
+        .strip_prefix("git@")
+        .and_then(|rest| rest.split_once(':'))
+    {
+        Some((host, path)) => format!("https://{}/{}", host, path),
+        None => url.to_string(),
+    };
+    let mut parts = url.rsplit(['/', ':']);
+    let repo = parts.next().unwrap_or_default();
+    let name = match parts.next() {
+        Some(owner) if !owner.is_empty() => format!("{}/{}", owner, repo),
+        _ => repo.to_string(),
+    };
+    (name, url)
+}
+
+/// Why fetching a PR from a remote failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FetchFailure {
+    /// A network error, still there after [`FETCH_ATTEMPTS`] attempts.
+    Unreachable(String),
+    /// There is no such remote.
+    NoRemote,
+    /// The remote has no `pull/<pr>/head`.
+    RefNotFound,
+    Other(String),
+}
+
+impl fmt::Display for FetchFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchFailure::Unreachable(error) => write!(
+                f,
+                "remote unreachable after {} attempts ({})",
+                FETCH_ATTEMPTS, error
+            ),
+            FetchFailure::NoRemote => write!(f, "no such remote"),
+            FetchFailure::RefNotFound => write!(f, "PR not found"),
+            FetchFailure::Other(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// Classify the stderr of a failed `git fetch`.
+fn classify_fetch_failure(stderr: &str) -> FetchFailure {
+    const NETWORK_ERRORS: &[&str] = &[
+        "Could not resolve host",
+        "Could not resolve hostname",
+        "Failed to connect",
+        "Connection refused",
+        "Connection reset",
+        "Connection timed out",
+        "Operation timed out",
+        "Network is unreachable",
+        "remote end hung up unexpectedly",
+        "early EOF",
+        "TLS",
+        "SSL",
+    ];
+    // The first `fatal:` or `error:` line names the problem; the rest is advice.
+    let error = stderr
+        .lines()
+        .find(|line| line.starts_with("fatal:") || line.starts_with("error:"))
+        .unwrap_or(stderr)
+        .trim()
+        .to_string();
+    if stderr.contains("couldn't find remote ref") {
+        FetchFailure::RefNotFound
+    } else if stderr.contains("does not appear to be a git repository") {
+        FetchFailure::NoRemote
+    } else if NETWORK_ERRORS.iter().any(|marker| stderr.contains(marker)) {
+        FetchFailure::Unreachable(error)
+    } else {
+        FetchFailure::Other(error)
+    }
+}
+
+/// Fetch `pull/<pr>/head` from `remote` into the local branch `pr/<pr>`,
+/// retrying with a growing delay while the remote cannot be reached.
+async fn fetch_pr(remote: &str, pr: u32) -> std::result::Result<(), FetchFailure> {
+    // Fetched into FETCH_HEAD only: fetching into `pr/<N>` is refused while it
+    // is checked out, and after a force-push.
+    let refspec = format!("+pull/{}/head", pr);
+    let mut delay = FETCH_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let output = Command::new("git")
+            .args(["fetch", remote, &refspec])
+            .output()
+            .map_err(|e| FetchFailure::Other(format!("Failed to execute git fetch: {}", e)))?;
+        if output.status.success() {
+            return Ok(());
+        }
+        match classify_fetch_failure(&String::from_utf8_lossy(&output.stderr)) {
+            FetchFailure::Unreachable(error) if attempt < FETCH_ATTEMPTS => {
+                warn!(
+                    "Fetching from {} failed ({}), retrying in {}s",
+                    remote,
+                    error,
+                    delay.as_secs()
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            failure => return Err(failure),
+        }
+    }
+}
+
+/// Files changed by the branch against `master`. With `pr_number`, the PR is
+/// first fetched from `upstream`, or else `origin`, and checked out as the
+/// branch `pr/<N>`, reset to the fetched head; if neither remote has it, this
+/// fails rather than using a stale local `pr/<N>`.
 pub async fn get_changed_files(pr_number: Option<u32>) -> Result<Vec<String>> {
     let mut used_remote = "upstream"; // Track which remote we successfully used
 
     if let Some(pr) = pr_number {
-        // Try to fetch the PR from upstream first
-        let fetch_upstream_args = &["fetch", "upstream", &format!("pull/{}/head:pr/{}", pr, pr)];
-        match run_git_command(fetch_upstream_args).await {
-            Ok(_) => {
-                println!("Successfully fetched from upstream");
-                println!("Checking out...");
-                let checkout_args = &["checkout", &format!("pr/{}", pr)];
-                run_git_command(checkout_args).await?;
-            }
-            Err(upstream_err) => {
-                println!("Failed to fetch from upstream: {:?}", upstream_err);
-                println!("Trying to fetch from origin...");
-
-                // Try to fetch from origin as fallback
-                let fetch_origin_args =
-                    &["fetch", "origin", &format!("pull/{}/head:pr/{}", pr, pr)];
-                match run_git_command(fetch_origin_args).await {
-                    Ok(_) => {
-                        println!("Successfully fetched from origin");
-                        used_remote = "origin";
-                        println!("Checking out...");
-                        let checkout_args = &["checkout", &format!("pr/{}", pr)];
-                        run_git_command(checkout_args).await?;
-                    }
-                    Err(origin_err) => {
-                        println!("Failed to fetch from origin: {:?}", origin_err);
-                        println!("Attempting to rebase existing pr/{} branch...", pr);
-                        let rebase_args = &["rebase", &format!("pr/{}", pr)];
-                        run_git_command(rebase_args).await?;
-                        // In rebase case, we don't know which remote was used originally
-                        // Try upstream first, fall back to origin if it fails
-                    }
+        let mut failures = Vec::new();
+        for remote in ["upstream", "origin"] {
+            match fetch_pr(remote, pr).await {
+                Ok(()) => {
+                    info!("Fetched PR #{} from {}", pr, remote);
+                    used_remote = remote;
+                    break;
+                }
+                Err(failure) => {
+                    warn!("Failed to fetch PR #{} from {}: {}", pr, remote, failure);
+                    failures.push(format!("{}: {}", remote, failure));
                 }
             }
         }
+        if failures.len() == 2 {
+            return Err(MutationError::Git(format!(
+                "Cannot fetch PR #{} (pull/{}/head): {}",
+                pr,
+                pr,
+                failures.join("; ")
+            )));
+        }
+        info!("Checking out...");
+        let checkout_args = &["checkout", "-B", &format!("pr/{}", pr), "FETCH_HEAD"];
+        run_git_command(checkout_args).await?;
     }
 
     // Try diff with the appropriate remote
@@ -80,7 +219,7 @@ pub async fn get_changed_files(pr_number: Option<u32>) -> Result<Vec<String>> {
         Ok(result) => Ok(result),
         Err(_) if used_remote == "upstream" => {
             // If upstream diff failed, try origin
-            println!("Diff with upstream/master failed, trying origin/master...");
+            debug!("Diff with upstream/master failed, trying origin/master...");
             let diff_args_origin = &["diff", "--name-only", "origin/master...HEAD"];
             run_git_command(diff_args_origin).await
         }
@@ -88,37 +227,106 @@ pub async fn get_changed_files(pr_number: Option<u32>) -> Result<Vec<String>> {
     }
 }
 
-pub async fn get_lines_touched(file_path: &str) -> Result<Vec<usize>> {
-    // Try upstream first
-    let diff_args_upstream = &[
-        "diff",
-        "--unified=0",
-        "upstream/master...HEAD",
-        "--",
-        file_path,
-    ];
+/// The last commit of `HEAD` made before `date` (anything `git rev-list
+/// --before` accepts, e.g. `2024-01-01`).
+pub async fn get_commit_before(date: &str) -> Result<String> {
+    let before = format!("--before={}", date);
+    let lines = run_git_command(&["rev-list", "-1", &before, "HEAD"]).await?;
+    lines
+        .into_iter()
+        .next()
+        .filter(|commit| !commit.is_empty())
+        .ok_or_else(|| {
+            MutationError::InvalidInput(format!(
+                "No commit before {}: the history starts later",
+                date
+            ))
+        })
+}
+
+/// Files changed between `base` and `HEAD`, deleted ones excluded.
+pub async fn get_changed_files_since(base: &str) -> Result<Vec<String>> {
+    let range = format!("{}...HEAD", base);
+    run_git_command(&["diff", "--name-only", "--diff-filter=d", &range]).await
+}
 
-    let diff_output = match run_git_command(diff_args_upstream).await {
-        Ok(output) => output,
-        Err(_) => {
-            // Fall back to origin if upstream fails
-            println!("Diff with upstream/master failed, trying origin/master...");
-            let diff_args_origin = &[
-                "diff",
-                "--unified=0",
-                "origin/master...HEAD",
-                "--",
-                file_path,
-            ];
-            run_git_command(diff_args_origin).await?
+/// Lines of each of `files` changed by the branch (or since `base`), from a
+/// single `git diff`. With `added_only`, only new lines are returned, not the
+/// ones that modify or move existing lines. Files without changed lines are
+/// left out.
+pub async fn get_lines_touched_batch(
+    files: &[&str],
+    base: Option<&str>,
+    added_only: bool,
+) -> Result<HashMap<String, Vec<usize>>> {
+    if files.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let diff_args = |range| {
+        let mut args = vec!["diff", "--unified=0", range, "--"];
+        args.extend_from_slice(files);
+        args
+    };
+    let diff_output = if let Some(base) = base {
+        run_git_command(&diff_args(&format!("{}...HEAD", base))).await?
+    } else {
+        // Try upstream first
+        match run_git_command(&diff_args("upstream/master...HEAD")).await {
+            Ok(output) => output,
+            Err(_) => {
+                // Fall back to origin if upstream fails
+                debug!("Diff with upstream/master failed, trying origin/master...");
+                run_git_command(&diff_args("origin/master...HEAD")).await?
+            }
         }
     };
 
-    let mut lines = Vec::new();
+    parse_lines_touched_by_file(&diff_output, added_only)
+}
+
+/// [`parse_lines_touched`] for each file of a multi-file diff, named by its
+/// `+++ b/<path>` header. Deleted files have no lines and are left out.
+fn parse_lines_touched_by_file(
+    diff_output: &[String],
+    added_only: bool,
+) -> Result<HashMap<String, Vec<usize>>> {
+    let mut lines_by_file = HashMap::new();
+    for section in diff_output.split(|line| line.starts_with("diff --git ")) {
+        let Some(path) = section
+            .iter()
+            .take_while(|line| !line.starts_with("@@"))
+            .find_map(|line| line.strip_prefix("+++ b/"))
+        else {
+            continue;
+        };
+        let lines = parse_lines_touched(section, added_only)?;
+        if !lines.is_empty() {
+            lines_by_file.insert(path.to_string(), lines);
+        }
+    }
+    Ok(lines_by_file)
+}
+
+/// One hunk of a `--unified=0` diff.
+struct Hunk<'a> {
+    start_line: usize,
+    num_lines: usize,
+    removed: Vec<&'a str>,
+    added: Vec<&'a str>,
+}
+
+/// Lines of the new file covered by the hunks of a `--unified=0` diff.
+///
+/// With `added_only`, the `+` lines of each hunk that replace one of its `-`
+/// lines count as modified, and lines whose content was removed elsewhere in
+/// the diff as moved; only the remaining ones are returned.
+fn parse_lines_touched(diff_output: &[String], added_only: bool) -> Result<Vec<usize>> {
+    let mut hunks: Vec<Hunk> = Vec::new();
     let line_range_regex = Regex::new(r"@@.*\+(\d+)(?:,(\d+))?.*@@")?;
     for line in diff_output {
         if line.starts_with("@@") {
-            if let Some(captures) = line_range_regex.captures(&line) {
+            if let Some(captures) = line_range_regex.captures(line) {
                 let start_line: usize = captures[1]
                     .parse()
                     .map_err(|_| MutationError::Git("Invalid line number in diff".to_string()))?;
@@ -130,7 +338,37 @@ pub async fn get_lines_touched(file_path: &str) -> Result<Vec<usize>> {
                 } else {
                     1
                 };
-                lines.extend(start_line..start_line + num_lines);
+                hunks.push(Hunk {
+                    start_line,
+                    num_lines,
+                    removed: Vec::new(),
+                    added: Vec::new(),
+                });
+            }
+        } else if let Some(hunk) = hunks.last_mut() {
+            if let Some(content) = line.strip_prefix('-') {
+                hunk.removed.push(content.trim());
+            } else if let Some(content) = line.strip_prefix('+') {
+                hunk.added.push(content.trim());
+            }
+        }
+    }
+
+    if !added_only {
+        return Ok(hunks
+            .iter()
+            .flat_map(|h| h.start_line..h.start_line + h.num_lines)
+            .collect());
+    }
+    let removed: HashSet<&str> = hunks
+        .iter()
+        .flat_map(|h| h.removed.iter().copied())
+        .collect();
+    let mut lines = Vec::new();
+    for hunk in &hunks {
+        for (i, content) in hunk.added.iter().enumerate() {
+            if i >= hunk.removed.len() && !removed.contains(content) {
+                lines.push(hunk.start_line + i);
             }
         }
     }
@@ -140,6 +378,109 @@ pub async fn get_lines_touched(file_path: &str) -> Result<Vec<usize>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{enter_dir, git, git_command, init_git_repo};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_changed_files_since() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let git_at = |args: &[&str], date: &str| {
+            let status = git_command()
+                .args(args)
+                .env("GIT_AUTHOR_DATE", date)
+                .env("GIT_COMMITTER_DATE", date)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "-q"]);
+        fs::write("a.cpp", "int a;\n").unwrap();
+        git(&["add", "."]);
+        git_at(&["commit", "-qm", "old"], "2023-06-01T00:00:00");
+        fs::write("a.cpp", "int a;\nint b;\n").unwrap();
+        fs::write("c.cpp", "int c;\n").unwrap();
+        git(&["add", "."]);
+        git_at(&["commit", "-qm", "new"], "2024-03-01T00:00:00");
+
+        let err = get_commit_before("2023-01-01").await.unwrap_err();
+        assert!(err.to_string().contains("No commit before 2023-01-01"));
+
+        let base = get_commit_before("2024-01-01").await.unwrap();
+        let files = get_changed_files_since(&base).await.unwrap();
+        assert_eq!(files, vec!["a.cpp", "c.cpp"]);
+        let lines = get_lines_touched_batch(&["a.cpp", "c.cpp"], Some(&base), false)
+            .await
+            .unwrap();
+        assert_eq!(lines["a.cpp"], vec![2]);
+        assert_eq!(lines["c.cpp"], vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pr_failures() {
+        assert_eq!(
+            classify_fetch_failure(
+                "fatal: unable to access 'https://github.com/bitcoin/bitcoin/': \
+                 Could not resolve host: github.com\n"
+            ),
+            FetchFailure::Unreachable(
+                "fatal: unable to access 'https://github.com/bitcoin/bitcoin/': \
+                 Could not resolve host: github.com"
+                    .to_string()
+            )
+        );
+        assert!(matches!(
+            classify_fetch_failure("error: RPC failed; curl 56 Connection reset by peer\n"),
+            FetchFailure::Unreachable(_)
+        ));
+        assert!(matches!(
+            classify_fetch_failure("! [rejected]        pull/1/head -> pr/1  (non-fast-forward)\n"),
+            FetchFailure::Other(_)
+        ));
+
+        // Neither remote has the PR: no local branch is used instead.
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        init_git_repo(&[]);
+        git(&["clone", "-q", "--bare", ".", "origin.git"]);
+        git(&["remote", "add", "origin", "origin.git"]);
+        git(&["branch", "pr/1"]);
+        let err = get_changed_files(Some(1)).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Git command failed: Cannot fetch PR #1 (pull/1/head): \
+             upstream: no such remote; origin: PR not found"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pr_rerun() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        init_git_repo(&[("a.cpp", "int a;\n")]);
+        git(&["clone", "-q", "--bare", ".", "origin.git"]);
+        git(&["remote", "add", "origin", "origin.git"]);
+        git(&["fetch", "-q", "origin"]);
+        git(&["checkout", "-q", "-b", "work"]);
+        fs::write("b.cpp", "int b;\n").unwrap();
+        git(&["add", "b.cpp"]);
+        git(&["commit", "-qm", "pr"]);
+        git(&["push", "-q", "origin", "HEAD:refs/pull/1/head"]);
+        git(&["checkout", "-q", "master"]);
+
+        assert_eq!(get_changed_files(Some(1)).await.unwrap(), vec!["b.cpp"]);
+        // Running again with `pr/1` checked out, after the PR was force-pushed.
+        fs::write("c.cpp", "int c;\n").unwrap();
+        git(&["add", "c.cpp"]);
+        git(&["commit", "-q", "--amend", "-m", "pr"]);
+        git(&["push", "-q", "-f", "origin", "HEAD:refs/pull/1/head"]);
+        git(&["reset", "-q", "--hard", "HEAD~1"]);
+        assert_eq!(
+            get_changed_files(Some(1)).await.unwrap(),
+            vec!["b.cpp", "c.cpp"]
+        );
+    }
 
     #[tokio::test]
     async fn test_get_lines_touched_parsing() {
@@ -168,4 +509,94 @@ mod tests {
             assert_eq!(num_lines, 5);
         }
     }
+
+    #[test]
+    fn test_parse_remote_url() {
+        let upstream = (
+            "bitcoin/bitcoin".to_string(),
+            "https://github.com/bitcoin/bitcoin".to_string(),
+        );
+        assert_eq!(
+            parse_remote_url("https://github.com/bitcoin/bitcoin.git"),
+            upstream
+        );
+        assert_eq!(
+            parse_remote_url("git@github.com:bitcoin/bitcoin.git"),
+            upstream
+        );
+        assert_eq!(
+            parse_remote_url("/home/alice/src/bitcoin/"),
+            (
+                "src/bitcoin".to_string(),
+                "/home/alice/src/bitcoin".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_lines_touched_added_only() {
+        let diff: Vec<String> = [
+            "diff --git a/src/a.cpp b/src/a.cpp",
+            "--- a/src/a.cpp",
+            "+++ b/src/a.cpp",
+            "@@ -3 +3,3 @@ int f()",
+            "-    return a;",
+            "+    return b;",
+            "+    if (x) return c;",
+            "+    return d;",
+            "@@ -10 +11,0 @@ int g()",
+            "-    int y = 1;",
+            "@@ -20,0 +21,2 @@ int h()",
+            "+    int y = 1;",
+            "+    int z = 2;",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(
+            parse_lines_touched(&diff, false).unwrap(),
+            vec![3, 4, 5, 21, 22]
+        );
+        // Line 3 modifies `return a;` and line 21 moves `int y = 1;`.
+        assert_eq!(parse_lines_touched(&diff, true).unwrap(), vec![4, 5, 22]);
+    }
+
+    #[test]
+    fn test_parse_lines_touched_by_file() {
+        let diff: Vec<String> = [
+            "diff --git a/src/a.cpp b/src/a.cpp",
+            "index 1111111..2222222 100644",
+            "--- a/src/a.cpp",
+            "+++ b/src/a.cpp",
+            "@@ -3 +3 @@ int f()",
+            "-    return a;",
+            "+    return b;",
+            "diff --git a/src/old.cpp b/src/old.cpp",
+            "deleted file mode 100644",
+            "--- a/src/old.cpp",
+            "+++ /dev/null",
+            "@@ -1,2 +0,0 @@",
+            "-int x;",
+            "-int y;",
+            "diff --git a/src/b.h b/src/b.h",
+            "--- a/src/b.h",
+            "+++ b/src/b.h",
+            "@@ -0,0 +1,2 @@",
+            "+    return a;",
+            "+int z;",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let lines = parse_lines_touched_by_file(&diff, false).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines["src/a.cpp"], vec![3]);
+        assert_eq!(lines["src/b.h"], vec![1, 2]);
+
+        // Lines moved from another file still count as new lines of this one.
+        let lines = parse_lines_touched_by_file(&diff, true).unwrap();
+        assert_eq!(lines["src/b.h"], vec![1, 2]);
+        assert!(!lines.contains_key("src/a.cpp"));
+    }
 }
@@ -11,25 +11,16 @@
 //! ## Example
 //!
 //! ```rust,no_run
-//! use bcore_mutation::mutation;
-//! use std::collections::HashMap;
+//! use bcore_mutation::mutation::{self, MutationOptions};
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
 //!     // Generate mutants for a specific file with AST filtering
-//!     mutation::run_mutation(
-//!         None,                        // PR number
-//!         Some("src/test.cpp".into()), // file path
-//!         false,                       // one_mutant
-//!         false,                       // only_security_mutations
-//!         None,                        // range_lines
-//!         None,                        // coverage
-//!         false,                       // test_only
-//!         HashMap::new(),              // skip_lines
-//!         true,                        // enable_ast_filtering
-//!         None,                        // custom_expert_rule
-//!         None,                        // sqlite_path
-//!     ).await?;
+//!     mutation::run_mutation(MutationOptions {
+//!         file: Some("src/test.cpp".into()),
+//!         ..Default::default()
+//!     })
+//!     .await?;
 //!
 //!     Ok(())
 //! }
@@ -37,21 +28,28 @@
 
 pub mod analyze;
 pub mod ast_analysis;
+pub mod config;
 pub mod coverage;
 pub mod db;
 pub mod error;
 pub mod git_changes;
+pub mod manifest;
 pub mod mutation;
 pub mod operators;
+pub mod path_filter;
+pub mod preprocessor;
+pub mod progress;
 pub mod report;
+#[cfg(test)]
+mod test_util;
 
 pub use error::{MutationError, Result};
 
 /// Re-export commonly used types
 pub mod prelude {
-    pub use crate::analyze::run_analysis;
+    pub use crate::analyze::{run_analysis, AnalysisOptions};
     pub use crate::ast_analysis::{AridNodeDetector, AstNode, AstNodeType};
     pub use crate::coverage::parse_coverage_file;
     pub use crate::error::{MutationError, Result};
-    pub use crate::mutation::run_mutation;
+    pub use crate::mutation::{run_mutation, MutationOptions};
 }
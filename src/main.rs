@@ -1,23 +1,58 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand};
+use indicatif_log_bridge::LogWrapper;
+use log::info;
+use regex::Regex;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
 mod analyze;
 mod ast_analysis;
+mod config;
 mod coverage;
 mod db;
 mod error;
 mod git_changes;
+mod manifest;
 mod mutation;
 mod operators;
+mod path_filter;
+mod preprocessor;
+mod progress;
 mod report;
+#[cfg(test)]
+mod test_util;
 
+use config::{Config, FileThresholds, CONFIG_FILE};
+use db::GroupColumn;
 use error::{MutationError, Result};
+use operators::{parse_operator_priority, OperatorCategory, OperatorMode};
+use report::{ReportFormat, ReportMode, RunSource};
 
 #[derive(Parser)]
 #[command(name = "bcore-mutation")]
 #[command(about = "Mutation testing tool designed for Bitcoin Core")]
+#[command(args_override_self = true)]
 struct Cli {
+    /// Show debug output such as test command logs (-vv for trace); RUST_LOG overrides
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Print a JSON summary of the command to stdout instead of progress logs
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Seed all randomness (e.g. --shuffle without a value) for reproducible runs
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+
+    /// Read default options from this file instead of ./bcore-mutation.toml
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -30,11 +65,15 @@ enum Commands {
         #[arg(short, long, default_value = "0")]
         pr: u32,
 
+        /// Mutate the files changed since the last commit before this date (e.g. 2024-01-01)
+        #[arg(long, value_name = "DATE", conflicts_with_all = ["pr", "file"])]
+        since: Option<String>,
+
         /// Only create mutants for unit and functional tests
         #[arg(short = 't', long)]
         test_only: bool,
 
-        /// Path for the coverage file (*.info generated with cmake -P build/Coverage.cmake)
+        /// Path for the coverage file (*.info generated with cmake -P build/Coverage.cmake), a *.gcov file or a directory of them
         #[arg(short, long)]
         cov: Option<PathBuf>,
 
@@ -42,10 +81,22 @@ enum Commands {
         #[arg(long)]
         skip_lines: Option<PathBuf>,
 
+        /// Only mutate lines the PR/branch adds, not lines it modifies or moves
+        #[arg(long, conflicts_with = "file")]
+        added_only: bool,
+
+        /// Skip every line matching this regex, wherever it is (repeatable)
+        #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+        exclude_lines_matching: Vec<Regex>,
+
         /// File path to mutate
         #[arg(short, long)]
         file: Option<PathBuf>,
 
+        /// Mutate the files listed in this file, one path per line
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["pr", "file", "since"])]
+        files_from: Option<PathBuf>,
+
         /// Specify a range of lines from a file to be mutated
         #[arg(short, long, num_args = 2)]
         range: Option<Vec<usize>>,
@@ -54,6 +105,36 @@ enum Commands {
         #[arg(long)]
         one_mutant: bool,
 
+        /// Print each mutant as `line : operator : before -> after` instead of writing it
+        #[arg(long, conflicts_with = "sqlite")]
+        preview: bool,
+
+        /// Only print how many mutants each file would get, without writing them
+        #[arg(long, conflicts_with_all = ["sqlite", "preview"])]
+        dry_run: bool,
+
+        /// At the end, list the operators that produced no mutant in any file
+        #[arg(long)]
+        operators_report: bool,
+
+        /// Also flip true/false in const and constexpr declarations
+        #[arg(long)]
+        mutate_constants: bool,
+
+        /// Also apply these opt-in operator categories (comma-separated): qualifier,
+        /// return
+        #[arg(long, value_name = "CATEGORIES", value_delimiter = ',')]
+        operator_categories: Vec<OperatorCategory>,
+
+        /// Only apply the operator with this name (`pattern ==> replacement`) or description
+        #[arg(long, value_name = "NAME")]
+        only_operator: Option<String>,
+
+        /// With --one-mutant, give the operator with this name or description
+        /// this priority (0 is tried first); repeatable
+        #[arg(long, value_name = "NAME=PRIORITY", value_parser = parse_operator_priority)]
+        operator_priority: Vec<(String, u8)>,
+
         /// Apply only security-based mutations (usually to test fuzzing)
         #[arg(short, long)]
         only_security_mutations: bool,
@@ -66,9 +147,53 @@ enum Commands {
         #[arg(long, value_name = "PATTERN")]
         add_expert_rule: Option<String>,
 
+        /// Print each line filtered out by arid node detection, with the reason
+        #[arg(long, conflicts_with = "disable_ast_filtering")]
+        verbose_arid: bool,
+
+        /// Create the mutation folders in this directory instead of the current one
+        #[arg(long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+
         /// Persist results to a SQLite database (default path: mutation.db)
         #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "mutation.db")]
         sqlite: Option<PathBuf>,
+
+        /// Write each mutant as a unified diff (*.mutant.N.patch) instead of a full file copy
+        #[arg(long)]
+        patch: bool,
+
+        /// Also run multi-line operators over windows of N lines (e.g. conditions split across lines)
+        #[arg(long, value_name = "N", default_value = "0")]
+        multiline_window: usize,
+
+        /// Stop generating mutants for a file once it has N of them
+        #[arg(long, value_name = "N")]
+        max_mutants: Option<usize>,
+
+        /// Visit lines in random order (optionally with a fixed seed), so --max-mutants samples the whole file
+        #[arg(long, value_name = "SEED", num_args = 0..=1)]
+        shuffle: Option<Option<u64>>,
+
+        /// Mutate changed files matching this glob even if excluded (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+
+        /// Do not mutate changed files matching this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Do not mutate changed files larger than N bytes
+        #[arg(long, value_name = "N")]
+        max_file_bytes: Option<u64>,
+
+        /// Do not mutate changed files with TEXT in their first lines (repeatable, replaces the defaults)
+        #[arg(
+            long = "generated-marker",
+            value_name = "TEXT",
+            default_values = mutation::DEFAULT_GENERATED_MARKERS
+        )]
+        generated_markers: Vec<String>,
     },
     /// Analyze mutants
     Analyze {
@@ -76,9 +201,47 @@ enum Commands {
         #[arg(short, long)]
         folder: Option<PathBuf>,
 
-        /// Timeout value per mutant in seconds
-        #[arg(short, long, default_value = "300")]
-        timeout: u64,
+        /// Directory searched for muts* folders when --folder is not given
+        #[arg(
+            long,
+            value_name = "DIR",
+            default_value = ".",
+            conflicts_with = "folder"
+        )]
+        root: PathBuf,
+
+        /// Also find muts* folders in nested directories below --root
+        #[arg(long, conflicts_with = "folder")]
+        recursive: bool,
+
+        /// Test only this mutant file once and print the test output
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with_all = ["folder", "run_id", "recursive"]
+        )]
+        mutant: Option<PathBuf>,
+
+        /// Timeout value per mutant in seconds (default: the unmutated test's
+        /// duration times --timeout-factor)
+        #[arg(short, long)]
+        timeout: Option<u64>,
+
+        /// Reuse results for mutants with the same patch from earlier commits (survivors are retested)
+        #[arg(long, requires = "sqlite")]
+        incremental: bool,
+
+        /// Stop testing new mutants after this many seconds; the rest are skipped
+        #[arg(long, value_name = "SECS")]
+        max_duration: Option<u64>,
+
+        /// Coverage file or directory (as for mutate --cov); mutants of uncovered lines are skipped
+        #[arg(long, value_name = "PATH", conflicts_with = "mutant")]
+        cov: Option<PathBuf>,
+
+        /// Multiple of the unmutated test's duration used as the timeout per mutant
+        #[arg(long, default_value = "3")]
+        timeout_factor: f64,
 
         /// Number of jobs to be used to compile Bitcoin Core
         #[arg(short, long, default_value = "0")]
@@ -92,11 +255,15 @@ enum Commands {
         #[arg(long, default_value = "0.75")]
         survival_threshold: f64,
 
-        /// SQLite database path to read mutants from (requires --run_id)
+        /// Exit with code 3 if the final mutation score is below this (e.g. 0.8)
+        #[arg(long, value_name = "SCORE")]
+        fail_under: Option<f64>,
+
+        /// SQLite database to read mutants from (with --run_id) or to cache results in
         #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "mutation.db")]
         sqlite: Option<PathBuf>,
 
-        /// Run ID to analyze from the SQLite database (requires --sqlite)
+        /// Run ID to analyze from the SQLite database, or to record folder results in (requires --sqlite)
         #[arg(long)]
         run_id: Option<i64>,
 
@@ -107,26 +274,241 @@ enum Commands {
         /// Only analyze mutants that survived a previous run (requires --run_id)
         #[arg(long)]
         survivors_only: bool,
+
+        /// Randomize the mutant order (optionally with a fixed seed)
+        #[arg(long, value_name = "SEED", num_args = 0..=1)]
+        shuffle: Option<Option<u64>>,
+
+        /// List the resolved command and mutants per folder without running anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Only analyze mutants with these indices (e.g. 10-20,42)
+        #[arg(long, value_name = "INDICES")]
+        only: Option<String>,
+
+        /// Report format: json (diff_not_killed.json) or junit (mutation-junit.xml)
+        #[arg(long, default_value = "json")]
+        format: ReportFormat,
+
+        /// Write the report to this path instead of the format's default (`-` for stdout)
+        #[arg(long, value_name = "PATH")]
+        report: Option<PathBuf>,
+
+        /// Replace an existing JSON report (overwrite) or add to it (append)
+        #[arg(long, value_name = "MODE", default_value = "overwrite")]
+        report_mode: ReportMode,
+
+        /// Write the target file annotated with its surviving mutants to each mutation folder
+        #[arg(long)]
+        annotate: bool,
+
+        /// Command that builds each mutant before --command runs; a failure is a build error, not a kill
+        #[arg(long, value_name = "CMD")]
+        build_command: Option<String>,
+
+        /// Leave mutants that fail to build out of the score instead of counting them as killed
+        #[arg(long)]
+        exclude_build_failures: bool,
+
+        /// Stop at the first mutant that fails to build and leave it applied for debugging
+        #[arg(long)]
+        keep_on_failure: bool,
+
+        /// Analyze targets with uncommitted changes, discarding them when the target is restored
+        #[arg(long)]
+        force: bool,
+
+        /// Analyze mutants even if the tests fail on the unmutated target
+        #[arg(long)]
+        skip_baseline_check: bool,
+
+        /// Print one line per mutant without a progress bar (the default when stdout is not a terminal)
+        #[arg(long)]
+        no_progress: bool,
+
+        /// Command prefix to run the build and test commands in a container or sandbox (each runs as `PREFIX sh -c '<command>'`)
+        #[arg(long, value_name = "PREFIX")]
+        sandbox: Option<String>,
+
+        /// Keep analyzing the remaining folders when one fails, and exit nonzero at the end
+        #[arg(long)]
+        keep_going: bool,
+
+        /// Also write each operator's mutant count, kill rate and share of survivors as JSON
+        #[arg(long, value_name = "PATH")]
+        operator_stats: Option<PathBuf>,
+    },
+    /// Generate the surviving-mutants report for a run stored in SQLite
+    Report {
+        /// SQLite database path
+        #[arg(long, value_name = "PATH", default_value = "mutation.db")]
+        sqlite: PathBuf,
+
+        /// Run ID to report on
+        #[arg(long)]
+        run_id: i64,
+
+        /// Replace an existing diff_not_killed.json (overwrite) or add to it (append)
+        #[arg(long, value_name = "MODE", default_value = "overwrite")]
+        report_mode: ReportMode,
+    },
+    /// List the runs stored in SQLite with their mutant counts and score
+    Runs {
+        /// SQLite database path
+        #[arg(
+            long,
+            value_name = "PATH",
+            default_value = "mutation.db",
+            global = true
+        )]
+        sqlite: PathBuf,
+
+        #[command(subcommand)]
+        action: Option<RunsAction>,
+    },
+    /// Query the mutants of a run stored in SQLite
+    Mutants {
+        /// SQLite database path
+        #[arg(long, value_name = "PATH", default_value = "mutation.db")]
+        sqlite: PathBuf,
+
+        /// Run ID to query
+        #[arg(long)]
+        run_id: i64,
+
+        /// Only show mutants with this status (e.g. survived)
+        #[arg(long)]
+        status: Option<String>,
+
+        /// Only show mutants of this file
+        #[arg(long, value_name = "PATH")]
+        file: Option<String>,
+
+        /// Show counts and kill rates per operator or file instead of the mutants
+        #[arg(long, value_name = "COLUMN")]
+        group_by: Option<GroupColumn>,
+    },
+    /// List the mutants that survive a run but did not survive a baseline run
+    Compare {
+        /// Baseline run: a run ID in --sqlite or a JSON report
+        #[arg(long, value_name = "RUN_OR_JSON")]
+        baseline: RunSource,
+
+        /// Run to check against the baseline: a run ID in --sqlite or a JSON report
+        #[arg(long, value_name = "RUN_OR_JSON")]
+        current: RunSource,
+
+        /// SQLite database path for run IDs
+        #[arg(long, value_name = "PATH", default_value = "mutation.db")]
+        sqlite: PathBuf,
+    },
+    /// Check that mutation folders are consistent before analyzing them
+    Verify {
+        /// Folder with the mutants
+        #[arg(short, long)]
+        folder: Option<PathBuf>,
+
+        /// Directory searched for muts* folders when --folder is not given
+        #[arg(
+            long,
+            value_name = "DIR",
+            default_value = ".",
+            conflicts_with = "folder"
+        )]
+        root: PathBuf,
+
+        /// Also find muts* folders in nested directories below --root
+        #[arg(long, conflicts_with = "folder")]
+        recursive: bool,
+    },
+    /// Show which lines of a file arid node detection filters out, and why
+    Ast {
+        /// File to analyze
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Add custom expert rule for arid node detection
+        #[arg(long, value_name = "PATTERN")]
+        add_expert_rule: Option<String>,
+    },
+    /// Print the mutation operators of a mode without mutating anything
+    #[command(alias = "operators")]
+    ListOperators {
+        /// Operator set: regex, security (--only-security-mutations), test
+        /// (Python and unit test files), multiline (--multiline-window) or
+        /// qualifier and return (--operator-categories)
+        #[arg(long, default_value = "regex")]
+        mode: OperatorMode,
+
+        /// Only print the operator with this name (`pattern ==> replacement`) or description
+        #[arg(long, value_name = "NAME")]
+        only_operator: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum RunsAction {
+    /// Delete a run and its mutants
+    Delete {
+        /// Run ID to delete
+        #[arg(long)]
+        run_id: i64,
     },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+async fn run() -> Result<()> {
+    let (cli, config) = parse_cli()?;
+    init_logging(cli.verbose, cli.json);
+    if let Some((ref path, _)) = config {
+        info!("Using options from {}", path.display());
+    }
 
     match cli.command {
         Commands::Mutate {
             pr,
+            since,
             test_only,
             cov,
             skip_lines,
+            added_only,
+            exclude_lines_matching,
             file,
+            files_from,
             range,
             one_mutant,
+            preview,
+            dry_run,
+            operators_report,
+            mutate_constants,
+            operator_categories,
+            only_operator,
+            operator_priority,
             only_security_mutations,
             disable_ast_filtering,
             add_expert_rule,
+            verbose_arid,
+            output_dir,
             sqlite,
+            patch,
+            multiline_window,
+            max_mutants,
+            shuffle,
+            include,
+            exclude,
+            max_file_bytes,
+            generated_markers,
         } => {
             let skip_lines_map = if let Some(path) = skip_lines {
                 read_skip_lines(&path)?
@@ -163,34 +545,91 @@ async fn main() -> Result<()> {
             }
 
             if let Some(ref expert_rule) = add_expert_rule {
-                println!("Custom expert rule will be applied: {}", expert_rule);
+                info!("Custom expert rule will be applied: {}", expert_rule);
             }
 
-            mutation::run_mutation(
-                if pr == 0 { None } else { Some(pr) },
+            let options = mutation::MutationOptions {
+                pr_number: if pr == 0 { None } else { Some(pr) },
+                since,
+                added_only,
                 file,
+                files_from,
                 one_mutant,
                 only_security_mutations,
                 range_lines,
                 coverage,
                 test_only,
-                skip_lines_map,
-                !disable_ast_filtering,
-                add_expert_rule,
-                sqlite,
-            )
-            .await?;
+                skip_lines: skip_lines_map,
+                exclude_lines_matching,
+                mutate_constants,
+                operator_categories,
+                only_operator,
+                operator_priorities: operator_priority,
+                preview,
+                dry_run,
+                enable_ast_filtering: !disable_ast_filtering,
+                custom_expert_rule: add_expert_rule,
+                output_dir,
+                verbose_arid,
+                sqlite_path: sqlite,
+                patch,
+                multiline_window,
+                max_mutants,
+                shuffle,
+                include,
+                exclude,
+                max_file_bytes,
+                generated_markers,
+                seed: cli.seed,
+                operators_report,
+            };
+            let summary = mutation::run_mutation(options).await?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                if preview {
+                    summary.print_preview();
+                }
+                if dry_run {
+                    summary.print_counts();
+                }
+                summary.print_unmatched_operators();
+            }
         }
         Commands::Analyze {
             folder,
+            root,
+            recursive,
+            mutant,
             timeout,
+            timeout_factor,
+            max_duration,
+            cov,
+            incremental,
             jobs,
             command,
             survival_threshold,
+            fail_under,
             sqlite,
             run_id,
             file_path,
             survivors_only,
+            shuffle,
+            dry_run,
+            only,
+            format,
+            report,
+            report_mode,
+            annotate,
+            build_command,
+            exclude_build_failures,
+            keep_on_failure,
+            force,
+            skip_baseline_check,
+            no_progress,
+            sandbox,
+            keep_going,
+            operator_stats,
         } => {
             if run_id.is_some() && sqlite.is_none() {
                 return Err(MutationError::InvalidInput(
@@ -204,14 +643,294 @@ async fn main() -> Result<()> {
                 ));
             }
 
-            analyze::run_analysis(folder, command, jobs, timeout, survival_threshold, sqlite, run_id, file_path, survivors_only)
-                .await?;
+            if fail_under.is_some_and(|score| !(0.0..=1.0).contains(&score)) {
+                return Err(MutationError::InvalidInput(
+                    "--fail-under must be between 0 and 1".to_string(),
+                ));
+            }
+
+            if timeout_factor <= 0.0 {
+                return Err(MutationError::InvalidInput(
+                    "--timeout-factor must be positive".to_string(),
+                ));
+            }
+
+            if build_command.is_some() && command.is_none() {
+                return Err(MutationError::InvalidInput(
+                    "--build-command requires --command".to_string(),
+                ));
+            }
+
+            // Both would be printed to stdout, one after the other.
+            if cli.json && report.as_deref() == Some(Path::new("-")) {
+                return Err(MutationError::InvalidInput(
+                    "--report - cannot be combined with --json, which prints the summary to stdout"
+                        .to_string(),
+                ));
+            }
+
+            if dry_run && run_id.is_some() && folder.is_none() {
+                return Err(MutationError::InvalidInput(
+                    "--dry-run only applies to folder analysis".to_string(),
+                ));
+            }
+
+            let file_thresholds = match config {
+                Some((_, ref config)) => FileThresholds::new(&config.survival_thresholds)?,
+                None => FileThresholds::default(),
+            };
+            let options = analyze::AnalysisOptions {
+                command,
+                jobs,
+                timeout_secs: timeout.unwrap_or(300),
+                // An explicit --timeout takes precedence over the measured one.
+                timeout_factor: timeout.is_none().then_some(timeout_factor),
+                skip_baseline_check,
+                deadline: max_duration.map(|secs| Instant::now() + Duration::from_secs(secs)),
+                coverage: cov
+                    .as_deref()
+                    .map(coverage::parse_coverage_file)
+                    .transpose()?,
+                incremental,
+                progress: !no_progress && std::io::stdout().is_terminal(),
+                sandbox,
+                keep_going,
+                survival_threshold,
+                file_survival_thresholds: file_thresholds.clone(),
+                sqlite_path: sqlite,
+                run_id,
+                file_path,
+                survivors_only,
+                shuffle,
+                seed: cli.seed,
+                dry_run,
+                only: only
+                    .as_deref()
+                    .map(analyze::parse_mutant_indices)
+                    .transpose()?,
+                format,
+                report_path: report,
+                report_mode,
+                annotate,
+                build_command,
+                exclude_build_failures,
+                keep_on_failure,
+                force,
+                operator_stats_path: operator_stats,
+                root,
+                recursive,
+            };
+            if let Some(mutant) = mutant {
+                let run = analyze::analyze_mutant(&mutant, &options).await?;
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&run)?);
+                } else {
+                    print!("{}", run.output);
+                    println!("{}: {}", run.mutant.display(), run.verdict());
+                }
+                return Ok(());
+            }
+            let summary = analyze::run_analysis(folder, options).await?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else if summary.survived > 0 {
+                // Survivors are logged among every folder's progress; list them together.
+                info!("\nSURVIVING MUTANTS:");
+                for (file, mutant) in summary.survivors() {
+                    info!("  {}: {}", file, mutant.name);
+                }
+            }
+            summary.check_failed()?;
+            if let Some(threshold) = fail_under {
+                summary.check_fail_under(threshold, &file_thresholds)?;
+            }
+        }
+        Commands::Report {
+            sqlite,
+            run_id,
+            report_mode,
+        } => {
+            report::generate_report_from_db(
+                &sqlite,
+                run_id,
+                ReportFormat::Json.default_path(),
+                report_mode,
+            )
+            .await?;
+        }
+        Commands::Runs { sqlite, action } => match action {
+            None => report::print_runs(&sqlite, cli.json)?,
+            Some(RunsAction::Delete { run_id }) => report::delete_run(&sqlite, run_id)?,
+        },
+        Commands::Mutants {
+            sqlite,
+            run_id,
+            status,
+            file,
+            group_by,
+        } => {
+            let query = report::MutantQuery {
+                run_id,
+                status,
+                file_path: file,
+                group_by,
+            };
+            report::print_mutants(&sqlite, &query, cli.json)?;
+        }
+        Commands::Compare {
+            baseline,
+            current,
+            sqlite,
+        } => report::print_comparison(&baseline, &current, &sqlite, cli.json)?,
+        Commands::Verify {
+            folder,
+            root,
+            recursive,
+        } => {
+            analyze::run_verify(folder, &root, recursive, cli.json).await?;
+        }
+        Commands::Ast {
+            file,
+            add_expert_rule,
+        } => {
+            report::print_ast(&file, add_expert_rule.as_deref(), cli.json)?;
+        }
+        Commands::ListOperators {
+            mode,
+            only_operator,
+        } => {
+            report::print_operators(mode, only_operator.as_deref(), cli.json)?;
         }
     }
 
     Ok(())
 }
 
+/// Parse the command line, with the subcommand's defaults taken from the
+/// config file. Returns the config file used, if any, and its contents.
+fn parse_cli() -> Result<(Cli, Option<(PathBuf, Config)>)> {
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    let negated = take_negated_switches(&mut args);
+    let matches = Cli::command().get_matches_from(&args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let path = match cli.config {
+        Some(ref path) => path.clone(),
+        None if Path::new(CONFIG_FILE).exists() => PathBuf::from(CONFIG_FILE),
+        None => return Ok((cli, None)),
+    };
+
+    let Some(subcommand) = matches.subcommand_name() else {
+        return Ok((cli, None));
+    };
+    let config = Config::load(&path)?;
+    let mut config_args = config.args_for(subcommand)?;
+    config_args.retain(|arg| !negated.iter().any(|name| *arg == format!("--{}", name)));
+    let command = Cli::command();
+    let known: Vec<&str> = command
+        .find_subcommand(subcommand)
+        .into_iter()
+        .flat_map(|cmd| cmd.get_arguments())
+        .filter_map(|arg| arg.get_long())
+        .collect();
+    for arg in &config_args {
+        let name = arg
+            .trim_start_matches("--")
+            .split('=')
+            .next()
+            .unwrap_or_default();
+        if !known.contains(&name) {
+            return Err(MutationError::InvalidInput(format!(
+                "Unknown option '{}' for {} in {}",
+                name,
+                subcommand,
+                path.display()
+            )));
+        }
+    }
+
+    // Config flags go right after the subcommand, so the ones given on the
+    // command line come later and override them.
+    let position = args
+        .iter()
+        .position(|arg| arg == subcommand)
+        .map_or(args.len(), |i| i + 1);
+    args.splice(
+        position..position,
+        config_args.into_iter().map(OsString::from),
+    );
+    Ok((Cli::parse_from(&args), Some((path, config))))
+}
+
+/// Remove the `--no-<flag>` arguments that turn off a switch of the subcommand,
+/// along with the `--<flag>` given before them, and return the names of the
+/// switches turned off. This is how a switch set in the config file is
+/// overridden.
+fn take_negated_switches(args: &mut Vec<OsString>) -> Vec<String> {
+    let command = Cli::command();
+    let Some(subcommand) = args
+        .iter()
+        .skip(1)
+        .find_map(|arg| command.find_subcommand(arg))
+    else {
+        return Vec::new();
+    };
+    let find = |name: &str| {
+        subcommand
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(name))
+    };
+    let is_switch =
+        |name: &str| find(name).is_some_and(|arg| matches!(arg.get_action(), ArgAction::SetTrue));
+
+    let mut negated = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let name = args[i]
+            .to_str()
+            .and_then(|arg| arg.strip_prefix("--no-"))
+            // Flags such as `--no-progress` exist in their own right.
+            .filter(|name| is_switch(name) && find(&format!("no-{}", name)).is_none())
+            .map(str::to_string);
+        let Some(name) = name else {
+            i += 1;
+            continue;
+        };
+        let flag = OsString::from(format!("--{}", name));
+        let rest = args.split_off(i + 1);
+        args.pop();
+        args.retain(|arg| *arg != flag);
+        i = args.len();
+        args.extend(rest);
+        negated.push(name);
+    }
+    negated
+}
+
+/// Log to stderr: progress at info level (only warnings with `--json`), debug
+/// output with `-v`. Messages other than info are prefixed with their level,
+/// and printed above the progress bar, if any.
+fn init_logging(verbose: u8, json: bool) {
+    let level = match verbose {
+        0 if json => "warn",
+        0 => "info",
+        1 => "debug",
+        _ => "trace",
+    };
+    let logger = env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(format!("warn,bcore_mutation={}", level)),
+    )
+    .format(|buf, record| match record.level() {
+        log::Level::Info => writeln!(buf, "{}", record.args()),
+        level => writeln!(buf, "{}: {}", level.as_str().to_lowercase(), record.args()),
+    })
+    .build();
+    let max_level = logger.filter();
+    LogWrapper::new(progress::multi_progress().clone(), logger)
+        .try_init()
+        .expect("logging is initialized once");
+    log::set_max_level(max_level);
+}
+
 fn read_skip_lines(path: &PathBuf) -> Result<HashMap<String, Vec<usize>>> {
     let content = std::fs::read_to_string(path)?;
     let map: HashMap<String, Vec<usize>> = serde_json::from_str(&content)?;
@@ -0,0 +1,92 @@
+//! `mutants.json`: the list of mutants written to a mutation folder.
+//!
+//! `mutate` writes one manifest per folder; `analyze` reads it to get the
+//! mutants in generation order along with their patch hashes, instead of
+//! re-scanning the folder and re-diffing every mutant.
+
+use crate::error::{MutationError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// File name of the manifest inside a mutation folder.
+pub const MANIFEST_FILE: &str = "mutants.json";
+
+/// One generated mutant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Mutant file name, relative to the mutation folder.
+    pub file: String,
+    /// First (1-indexed) line of the target file changed by the mutant.
+    pub line: usize,
+    pub operator: String,
+    /// What the operator does, as listed by `list-operators`.
+    #[serde(default)]
+    pub description: String,
+    pub patch_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub target_file: String,
+    pub mutants: Vec<ManifestEntry>,
+    /// Arid node detection statistics of the target, when it was AST filtered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ast_stats: Option<BTreeMap<String, usize>>,
+    /// Mutants of the target left out as equivalent to the original code.
+    #[serde(default)]
+    pub equivalent: usize,
+}
+
+impl Manifest {
+    /// Write the manifest into `folder`, replacing any previous one.
+    pub fn write(&self, folder: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(folder.join(MANIFEST_FILE), json)?;
+        Ok(())
+    }
+
+    /// Read the manifest of `folder`, or `None` if it was generated without one.
+    pub fn read(folder: &Path) -> Result<Option<Manifest>> {
+        let path = folder.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = fs::read_to_string(&path)?;
+        let manifest = serde_json::from_str(&json).map_err(|e| {
+            MutationError::InvalidInput(format!("Invalid {}: {}", path.display(), e))
+        })?;
+        Ok(Some(manifest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        assert!(Manifest::read(temp_dir.path()).unwrap().is_none());
+
+        let manifest = Manifest {
+            target_file: "src/wallet/wallet.cpp".to_string(),
+            mutants: vec![ManifestEntry {
+                file: "wallet.mutant.0.cpp".to_string(),
+                line: 42,
+                operator: "==  ==> !=".to_string(),
+                description: "relational ==→!=".to_string(),
+                patch_hash: "abc".to_string(),
+            }],
+            ast_stats: Some(BTreeMap::from([("arid_lines".to_string(), 3)])),
+            equivalent: 2,
+        };
+        manifest.write(temp_dir.path()).unwrap();
+        assert_eq!(Manifest::read(temp_dir.path()).unwrap(), Some(manifest));
+
+        fs::write(temp_dir.path().join(MANIFEST_FILE), "not json").unwrap();
+        assert!(Manifest::read(temp_dir.path()).is_err());
+    }
+}
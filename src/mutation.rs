@@ -1,16 +1,38 @@
-use crate::ast_analysis::{filter_mutatable_lines, AridNodeDetector};
-use crate::db::{compute_patch_hash, generate_diff, Database, MutantData};
+use crate::ast_analysis::{filter_mutatable_lines, AridNodeDetector, DetailedAnalysis};
+use crate::coverage::covered_lines;
+use crate::db::{compute_patch_hash, generate_diff_from_file, Database, MutantData};
 use crate::error::{MutationError, Result};
-use crate::git_changes::{get_changed_files, get_commit_hash, get_lines_touched};
+use crate::git_changes::{
+    get_changed_files, get_changed_files_since, get_commit_before, get_commit_hash,
+    get_lines_touched_batch, get_repository,
+};
+use crate::manifest::{Manifest, ManifestEntry};
 use crate::operators::{
-    get_do_not_mutate_patterns, get_do_not_mutate_py_patterns, get_do_not_mutate_unit_patterns,
-    get_regex_operators, get_security_operators, get_skip_if_contain_patterns, get_test_operators,
-    should_mutate_test_line,
+    check_operator_name, describe_operator, get_do_not_mutate_patterns,
+    get_do_not_mutate_py_patterns, get_do_not_mutate_unit_patterns, get_skip_if_contain_patterns,
+    mutate_constant_declarations, override_priorities, should_mutate_test_line, MutationOperator,
+    OperatorCategory, OperatorMode, SKIP_NEXT_ANNOTATION,
 };
+use crate::path_filter::{PathFilter, IGNORE_FILE};
+use crate::preprocessor::disabled_lines;
+use crate::report::ReportFormat;
+use log::{debug, info, warn};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
 use regex::Regex;
-use std::collections::HashMap;
-use std::fs;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Number of lines whose mutants are built in parallel before being written.
+/// Bounds how many full mutated copies of a file are held in memory at once.
+const GENERATION_CHUNK_LINES: usize = 64;
 
 #[derive(Debug)]
 pub struct FileToMutate {
@@ -19,6 +41,230 @@ pub struct FileToMutate {
     pub is_unit_test: bool,
 }
 
+/// Options controlling how mutants are generated.
+#[derive(Debug, Clone)]
+pub struct MutationOptions {
+    /// Bitcoin Core PR number whose changed lines are mutated (`None` = current branch).
+    pub pr_number: Option<u32>,
+    /// Mutate the files changed since the last commit before this date instead
+    /// of those of the PR/branch.
+    pub since: Option<String>,
+    /// Only mutate the lines the PR/branch adds, not the ones it modifies or moves.
+    pub added_only: bool,
+    /// Mutate this file instead of the files changed by the PR/branch.
+    pub file: Option<PathBuf>,
+    /// Mutate the files listed in this file, one path per line, instead of the
+    /// files changed by the PR/branch.
+    pub files_from: Option<PathBuf>,
+    /// Create at most one mutant per line.
+    pub one_mutant: bool,
+    /// Apply only security-based operators. AST filtering and coverage still
+    /// narrow down the lines they mutate.
+    pub only_security_mutations: bool,
+    /// Only mutate lines in this (0-indexed) range.
+    pub range_lines: Option<(usize, usize)>,
+    /// Covered lines per file; uncovered lines are not mutated.
+    pub coverage: Option<HashMap<String, Vec<usize>>>,
+    /// Only mutate unit and functional tests.
+    pub test_only: bool,
+    /// Lines (1-indexed) to skip per file.
+    pub skip_lines: HashMap<String, Vec<usize>>,
+    /// Skip the lines of any file that match one of these.
+    pub exclude_lines_matching: Vec<Regex>,
+    /// Flip `true`/`false` in `const`/`constexpr` declarations too.
+    pub mutate_constants: bool,
+    /// Opt-in operator categories added to the regex operators.
+    pub operator_categories: Vec<OperatorCategory>,
+    /// Only apply the operator with this name or description.
+    pub only_operator: Option<String>,
+    /// `--one-mutant` priorities given to the operators with these names or
+    /// descriptions.
+    pub operator_priorities: Vec<(String, u8)>,
+    /// Print the mutants that would be generated instead of writing them.
+    pub preview: bool,
+    /// Only count the mutants each file would get, without writing them.
+    pub dry_run: bool,
+    /// Filter out arid lines with the AST-based detector, whatever the
+    /// operator set.
+    pub enable_ast_filtering: bool,
+    /// Extra expert rule for the arid node detector.
+    pub custom_expert_rule: Option<String>,
+    /// Directory the `muts-*` folders are created in, instead of the current one.
+    pub output_dir: Option<PathBuf>,
+    /// Print each line the arid node detector filters out, with the reason.
+    pub verbose_arid: bool,
+    /// Persist the run and its mutants to this SQLite database.
+    pub sqlite_path: Option<PathBuf>,
+    /// Write each mutant as a unified diff (`*.mutant.N.patch`) instead of a full copy.
+    pub patch: bool,
+    /// Run the multi-line operators over windows of this many lines (0 or 1 disables them).
+    pub multiline_window: usize,
+    /// Stop generating mutants for a file once it has this many.
+    pub max_mutants: Option<usize>,
+    /// Visit lines in random order, so `max_mutants` samples across the file:
+    /// `Some(None)` picks a random seed.
+    pub shuffle: Option<Option<u64>>,
+    /// Globs of changed files to mutate even if excluded (e.g. a vendored subtree).
+    pub include: Vec<String>,
+    /// Globs of changed files not to mutate, on top of the vendored subtrees.
+    pub exclude: Vec<String>,
+    /// Changed files larger than this many bytes are not mutated.
+    pub max_file_bytes: Option<u64>,
+    /// Changed files with one of these in their first lines are taken as
+    /// generated and not mutated.
+    pub generated_markers: Vec<String>,
+    /// Seed for all randomness: `shuffle` without its own seed uses it.
+    pub seed: Option<u64>,
+    /// List the operators that produced no mutant at the end of the run.
+    pub operators_report: bool,
+}
+
+impl Default for MutationOptions {
+    fn default() -> Self {
+        Self {
+            pr_number: None,
+            since: None,
+            added_only: false,
+            file: None,
+            files_from: None,
+            one_mutant: false,
+            only_security_mutations: false,
+            range_lines: None,
+            coverage: None,
+            test_only: false,
+            skip_lines: HashMap::new(),
+            exclude_lines_matching: Vec::new(),
+            mutate_constants: false,
+            operator_categories: Vec::new(),
+            only_operator: None,
+            operator_priorities: Vec::new(),
+            preview: false,
+            dry_run: false,
+            enable_ast_filtering: true,
+            custom_expert_rule: None,
+            output_dir: None,
+            verbose_arid: false,
+            sqlite_path: None,
+            patch: false,
+            multiline_window: 0,
+            max_mutants: None,
+            shuffle: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_file_bytes: None,
+            generated_markers: DEFAULT_GENERATED_MARKERS
+                .iter()
+                .map(|marker| marker.to_string())
+                .collect(),
+            seed: None,
+            operators_report: false,
+        }
+    }
+}
+
+/// C and C++ source extensions. These get AST-based arid node filtering.
+const C_FAMILY_EXTENSIONS: &[&str] = &["cpp", "cc", "c", "h", "hpp", "ipp"];
+
+/// Extensions a mutant file can have; mutants keep the extension of their source.
+pub const MUTANT_EXTENSIONS: &[&str] = &["cpp", "cc", "c", "h", "hpp", "ipp", "py"];
+
+/// Extension of mutants written as unified diffs with `--patch`.
+pub const PATCH_EXTENSION: &str = "patch";
+
+/// Extension (without the dot) to write mutants of `file_path` with. Unknown
+/// extensions fall back to `cpp`.
+pub fn mutant_extension(file_path: &str) -> &'static str {
+    let ext = Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    MUTANT_EXTENSIONS
+        .iter()
+        .find(|known| **known == ext)
+        .copied()
+        .unwrap_or("cpp")
+}
+
+/// Whether `file_path` is a C or C++ source or header.
+pub fn is_c_family_source(file_path: &str) -> bool {
+    Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| C_FAMILY_EXTENSIONS.contains(&ext))
+}
+
+/// Why `file` is one of the tool's own artifacts, if it is: a report, the
+/// SQLite database, or something inside a mutants folder or the `db` directory.
+fn own_artifact(file: &Path, sqlite_path: Option<&Path>) -> Option<&'static str> {
+    let file: PathBuf = file.components().filter(|c| c.as_os_str() != ".").collect();
+    let mut dirs = file.parent().into_iter().flat_map(|p| p.components());
+    if dirs.clone().next().is_some_and(|c| c.as_os_str() == "db") {
+        return Some("is inside the db directory");
+    }
+    if dirs.any(|c| c.as_os_str().to_string_lossy().starts_with("muts-")) {
+        return Some("is inside a mutants folder");
+    }
+
+    let is_report = [ReportFormat::Json, ReportFormat::Junit]
+        .iter()
+        .any(|format| file.file_name() == Some(format.default_path().as_os_str()));
+    if is_report {
+        return Some("is a mutation report");
+    }
+
+    let same_file = |db: &Path| match (fs::canonicalize(&file), fs::canonicalize(db)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => {
+            file == db
+                .components()
+                .filter(|c| c.as_os_str() != ".")
+                .collect::<PathBuf>()
+        }
+    };
+    if file.file_name() == Some("mutation.db".as_ref()) || sqlite_path.is_some_and(same_file) {
+        return Some("is the SQLite database");
+    }
+    None
+}
+
+/// Markers of generated files, e.g. the `AUTOGENERATED by
+/// contrib/seeds/generate-seeds.py` header of `src/chainparamsseeds.h`.
+pub const DEFAULT_GENERATED_MARKERS: &[&str] = &[
+    "// GENERATED",
+    "AUTOGENERATED",
+    "@generated",
+    "automatically generated",
+    "DO NOT EDIT",
+];
+
+/// Number of lines at the top of a file searched for generated file markers.
+const GENERATED_MARKER_LINES: usize = 10;
+
+/// Why the changed `file` is not worth mutating, if it is not: it is larger
+/// than `max_bytes`, or one of its first lines contains a generated file
+/// marker. Unreadable files are left to `mutate_file`.
+fn generated_file(file: &Path, max_bytes: Option<u64>, markers: &[String]) -> Option<String> {
+    let size = fs::metadata(file).ok()?.len();
+    if let Some(max_bytes) = max_bytes.filter(|max| size > *max) {
+        return Some(format!(
+            "{} bytes, over --max-file-bytes {}",
+            size, max_bytes
+        ));
+    }
+
+    let head = BufReader::new(File::open(file).ok()?);
+    for line in head.lines().take(GENERATED_MARKER_LINES) {
+        let line = line.ok()?;
+        let found = markers
+            .iter()
+            .find(|marker| !marker.is_empty() && line.contains(marker.as_str()));
+        if let Some(marker) = found {
+            return Some(format!("generated file marker '{}'", marker));
+        }
+    }
+    None
+}
+
 /// Chunk size for DB batch inserts.
 const DB_BATCH_SIZE: usize = 100;
 
@@ -28,26 +274,200 @@ fn build_config_json(range_lines: Option<(usize, usize)>) -> Option<String> {
     range_lines.map(|(start, end)| format!("{{\"range\":[{},{}]}}", start, end))
 }
 
-pub async fn run_mutation(
-    pr_number: Option<u32>,
-    file: Option<PathBuf>,
-    one_mutant: bool,
-    only_security_mutations: bool,
-    range_lines: Option<(usize, usize)>,
-    coverage: Option<HashMap<String, Vec<usize>>>,
-    test_only: bool,
-    skip_lines: HashMap<String, Vec<usize>>,
-    enable_ast_filtering: bool,
-    custom_expert_rule: Option<String>,
-    sqlite_path: Option<PathBuf>,
-) -> Result<()> {
+/// Summary of a `mutate` run, printed as JSON with `--json`.
+#[derive(Debug, Default, Serialize)]
+pub struct MutationSummary {
+    /// Run created in the SQLite database, if any.
+    pub run_id: Option<i64>,
+    /// Seed the run's randomness was derived from, if any.
+    pub seed: Option<u64>,
+    /// Number of mutants generated across all files.
+    pub total: usize,
+    pub files: Vec<FileSummary>,
+    /// With `--operators-report`, the operators that produced no mutant in any file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unmatched_operators: Option<Vec<String>>,
+}
+
+impl MutationSummary {
+    /// Print the number of mutants of each file and the total, largest first.
+    pub fn print_counts(&self) {
+        let mut files: Vec<&FileSummary> = self.files.iter().collect();
+        files.sort_by(|a, b| b.mutants.cmp(&a.mutants).then_with(|| a.file.cmp(&b.file)));
+        for file in files {
+            println!("{:>8}  {}", file.mutants, file.file);
+        }
+        println!("{:>8}  total", self.total);
+    }
+
+    /// Print the `--preview` line of every mutant, under the name of its file.
+    pub fn print_preview(&self) {
+        for file in self.files.iter().filter(|file| !file.preview.is_empty()) {
+            println!("{}:", file.file);
+            for line in &file.preview {
+                println!("{}", line);
+            }
+        }
+    }
+
+    /// The operators applied to any file that produced no mutant in all of
+    /// them, in operator order.
+    fn unmatched_operators(&self) -> Vec<String> {
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for (name, count) in self.files.iter().flat_map(|file| &file.operators) {
+            match counts.iter_mut().find(|(seen, _)| seen == name) {
+                Some((_, total)) => *total += count,
+                None => counts.push((name, *count)),
+            }
+        }
+        counts
+            .into_iter()
+            .filter(|(_, count)| *count == 0)
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    /// Print the operators that produced no mutant, as listed by `--operators-report`.
+    pub fn print_unmatched_operators(&self) {
+        let Some(ref unmatched) = self.unmatched_operators else {
+            return;
+        };
+        let applied = self
+            .files
+            .iter()
+            .flat_map(|file| &file.operators)
+            .map(|(name, _)| name)
+            .collect::<HashSet<_>>()
+            .len();
+        println!(
+            "{} of {} operators produced no mutant:",
+            unmatched.len(),
+            applied
+        );
+        for name in unmatched {
+            println!(
+                "  {}: {}",
+                describe_operator(name).unwrap_or_default(),
+                name
+            );
+        }
+    }
+}
+
+/// Number of mutants generated for one file.
+#[derive(Debug, Default, Serialize)]
+pub struct FileSummary {
+    pub file: String,
+    pub mutants: usize,
+    /// Mutants left out because they are equivalent to the original code.
+    pub equivalent: usize,
+    /// Mutants per operator applied to the file, in operator order; operators
+    /// that produced none are included.
+    #[serde(skip)]
+    pub operators: Vec<(String, usize)>,
+    /// With AST filtering, the detector's statistics (rule and pattern counts,
+    /// cache size) along with the file's `lines` and the `arid_lines` filtered out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ast_stats: Option<BTreeMap<String, usize>>,
+    /// With `--preview`, each mutant as `line : operator : before -> after`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub preview: Vec<String>,
+}
+
+/// Count the mutants of each of `operators`.
+fn operator_counts<'a>(
+    operators: impl IntoIterator<Item = &'a MutationOperator>,
+    mutants: &[Mutant],
+) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for operator in operators {
+        let name = operator.name();
+        if !counts.iter().any(|(seen, _)| *seen == name) {
+            counts.push((name, 0));
+        }
+    }
+    for mutant in mutants {
+        if let Some((_, count)) = counts.iter_mut().find(|(name, _)| *name == mutant.operator) {
+            *count += 1;
+        }
+    }
+    counts
+}
+
+/// Read the paths listed in `list`, one per line. Blank lines and lines
+/// starting with `#` are ignored. Fails if any listed file does not exist.
+fn read_file_list(list: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(list).map_err(|e| {
+        MutationError::InvalidInput(format!("Cannot read {}: {}", list.display(), e))
+    })?;
+    let files: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    let missing: Vec<&str> = files
+        .iter()
+        .filter(|file| !Path::new(file).is_file())
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(MutationError::InvalidInput(format!(
+            "Files listed in {} do not exist: {}",
+            list.display(),
+            missing.join(", ")
+        )));
+    }
+    Ok(files)
+}
+
+pub async fn run_mutation(options: MutationOptions) -> Result<MutationSummary> {
+    // Resolve the shuffle seed once so every file uses (and reports) the same one.
+    let mut options = options;
+    if let Some(None) = options.shuffle {
+        options.shuffle = Some(Some(options.seed.unwrap_or_else(rand::random)));
+    }
+    if let Some(Some(seed)) = options.shuffle {
+        info!("Shuffling lines with seed {}", seed);
+    } else if let Some(seed) = options.seed {
+        info!("Using seed {}", seed);
+    }
+    let seed = options.shuffle.flatten().or(options.seed);
+
+    let pr_number = options.pr_number;
+    let range_lines = options.range_lines;
+    let listed_files = options
+        .files_from
+        .as_deref()
+        .map(read_file_list)
+        .transpose()?;
+    if let Some(ref name) = options.only_operator {
+        check_operator_name(name).map_err(MutationError::InvalidInput)?;
+    }
+
     // Set up database if requested.
     let mut db_and_run: Option<(Database, i64)> = None;
-    if let Some(ref path) = sqlite_path {
+    if let Some(ref path) = options.sqlite_path {
         let db = Database::open(path)?;
         db.ensure_schema()?;
         db.seed_projects()?;
-        let project_id = db.get_bitcoin_core_project_id()?;
+        let project_id = match get_repository().await {
+            Ok((name, url)) => {
+                debug!(
+                    "SQLite: project {} ({})",
+                    name,
+                    url.as_deref().unwrap_or("no remote")
+                );
+                db.get_or_create_project(&name, url.as_deref())?
+            }
+            Err(e) => {
+                warn!(
+                    "could not identify the repository ({}), using Bitcoin Core",
+                    e
+                );
+                db.get_bitcoin_core_project_id()?
+            }
+        };
         let commit_hash = get_commit_hash().await.unwrap_or_else(|_| "unknown".to_string());
         let tool_version = env!("CARGO_PKG_VERSION");
         let config_json = build_config_json(range_lines);
@@ -57,145 +477,205 @@ pub async fn run_mutation(
             tool_version,
             pr_number,
             config_json.as_deref(),
+            seed,
         )?;
-        println!("SQLite: created run id={} in {}", run_id, path.display());
+        info!("SQLite: created run id={} in {}", run_id, path.display());
         db_and_run = Some((db, run_id));
     }
 
     let mut all_mutants: Vec<MutantData> = Vec::new();
+    let mut summary = MutationSummary {
+        run_id: db_and_run.as_ref().map(|(_, run_id)| *run_id),
+        seed,
+        ..Default::default()
+    };
 
-    if let Some(file_path) = file {
-        let file_str = file_path.to_string_lossy().to_string();
-        let is_unit_test = file_str.contains("test") && !file_str.contains(".py");
-
-        let mutants = mutate_file(
-            &file_str,
-            None,
-            None,
-            one_mutant,
-            only_security_mutations,
-            range_lines,
-            &coverage,
-            is_unit_test,
-            &skip_lines,
-            enable_ast_filtering,
-            custom_expert_rule,
-        )
-        .await?;
-        all_mutants.extend(mutants);
+    let whole_files = match (listed_files, &options.file) {
+        (Some(files), _) => Some(files),
+        (None, Some(file_path)) => Some(vec![file_path.to_string_lossy().to_string()]),
+        (None, None) => None,
+    };
+    if let Some(files) = whole_files {
+        for file_str in files {
+            let is_unit_test = file_str.contains("test") && !file_str.contains(".py");
+
+            let (file_summary, mutants) =
+                mutate_file(&file_str, None, is_unit_test, &options).await?;
+            summary.files.push(file_summary);
+            all_mutants.extend(mutants);
+        }
     } else {
-        let files_changed = get_changed_files(pr_number).await?;
+        // With --since, the base is the last commit before the date instead of master.
+        let base = match options.since {
+            Some(ref date) => {
+                let commit = get_commit_before(date).await?;
+                info!("Mutating the files changed since {} ({})", date, commit);
+                Some(commit)
+            }
+            None => None,
+        };
+        let files_changed = match base {
+            Some(ref base) => get_changed_files_since(base).await?,
+            None => get_changed_files(pr_number).await?,
+        };
+        let ignore_file = Path::new(IGNORE_FILE);
+        let path_filter = PathFilter::new(
+            &options.include,
+            &options.exclude,
+            ignore_file.exists().then_some(ignore_file),
+        )?;
         let mut files_to_mutate = Vec::new();
 
         for file_changed in files_changed {
-            // Skip certain file types
-            if file_changed.contains("doc")
-                || file_changed.contains("contrib")
-                || file_changed.contains("fuzz")
-                || file_changed.contains("bench")
-                || file_changed.contains("util")
-                || file_changed.contains("sanitizer_supressions")
-                || file_changed.contains("test_framework.py")
-                || file_changed.ends_with(".txt")
-            {
+            if path_filter.is_excluded(&file_changed) {
+                info!("Skipping excluded file {}", file_changed);
+                continue;
+            }
+            if let Some(reason) = generated_file(
+                Path::new(&file_changed),
+                options.max_file_bytes,
+                &options.generated_markers,
+            ) {
+                info!("Skipping {}: {}", file_changed, reason);
                 continue;
             }
 
-            let lines_touched = get_lines_touched(&file_changed).await?;
             let is_unit_test = file_changed.contains("test")
                 && !file_changed.contains(".py")
                 && !file_changed.contains("util");
 
-            if test_only && !(is_unit_test || file_changed.contains(".py")) {
+            if options.test_only && !(is_unit_test || file_changed.contains(".py")) {
                 continue;
             }
 
             files_to_mutate.push(FileToMutate {
                 file_path: file_changed,
-                lines_touched,
+                lines_touched: Vec::new(),
                 is_unit_test,
             });
         }
 
+        // One diff for all the files, rather than one per file.
+        let paths: Vec<&str> = files_to_mutate
+            .iter()
+            .map(|file| file.file_path.as_str())
+            .collect();
+        let mut lines_touched =
+            get_lines_touched_batch(&paths, base.as_deref(), options.added_only).await?;
+        for file in &mut files_to_mutate {
+            file.lines_touched = lines_touched.remove(&file.file_path).unwrap_or_default();
+        }
+
         for file_info in files_to_mutate {
-            let mutants = mutate_file(
+            let (file_summary, mutants) = mutate_file(
                 &file_info.file_path,
                 Some(file_info.lines_touched),
-                pr_number,
-                one_mutant,
-                only_security_mutations,
-                range_lines,
-                &coverage,
                 file_info.is_unit_test,
-                &skip_lines,
-                enable_ast_filtering,
-                custom_expert_rule.clone(),
+                &options,
             )
             .await?;
+            summary.files.push(file_summary);
             all_mutants.extend(mutants);
         }
     }
+    summary.total = summary.files.iter().map(|file| file.mutants).sum();
+    if options.operators_report {
+        summary.unmatched_operators = Some(summary.unmatched_operators());
+    }
 
     // Persist mutants to the database in chunks.
     if let Some((ref mut db, run_id)) = db_and_run {
         let total = all_mutants.len();
         let mut inserted = 0usize;
+        let mut skipped = 0usize;
         for chunk in all_mutants.chunks(DB_BATCH_SIZE) {
-            db.insert_mutant_batch(run_id, chunk)?;
-            inserted += chunk.len();
+            let (new, duplicates) = db.insert_mutant_batch(run_id, chunk)?;
+            inserted += new;
+            skipped += duplicates;
         }
-        println!(
-            "SQLite: inserted {}/{} mutants for run_id={}",
-            inserted, total, run_id
+        info!(
+            "SQLite: inserted {}/{} mutants for run_id={} ({} duplicate patches skipped)",
+            inserted, total, run_id, skipped
         );
     }
 
-    Ok(())
+    Ok(summary)
 }
 
+/// Generate the mutants of one file. `touched_lines` restricts mutation to those
+/// lines (all lines when `None`); mutants of an explicit `--file` are named
+/// without the PR number. Returns how many mutants were generated, or would be
+/// with `--dry-run` and `--preview`, which write nothing, and by which
+/// operators, along with the written ones.
 pub async fn mutate_file(
     file_to_mutate: &str,
     touched_lines: Option<Vec<usize>>,
-    pr_number: Option<u32>,
-    one_mutant: bool,
-    only_security_mutations: bool,
-    range_lines: Option<(usize, usize)>,
-    coverage: &Option<HashMap<String, Vec<usize>>>,
     is_unit_test: bool,
-    skip_lines: &HashMap<String, Vec<usize>>,
-    enable_ast_filtering: bool,
-    custom_expert_rule: Option<String>,
-) -> Result<Vec<MutantData>> {
-    println!("\n\nGenerating mutants for {}...", file_to_mutate);
+    options: &MutationOptions,
+) -> Result<(FileSummary, Vec<MutantData>)> {
+    let pr_number = if options.file.is_some() {
+        None
+    } else {
+        options.pr_number
+    };
+    let one_mutant = options.one_mutant;
+    let range_lines = options.range_lines;
+    let enable_ast_filtering = options.enable_ast_filtering;
+
+    if let Some(reason) = own_artifact(Path::new(file_to_mutate), options.sqlite_path.as_deref()) {
+        return Err(MutationError::InvalidInput(format!(
+            "{} {}; refusing to mutate the tool's own output",
+            file_to_mutate, reason
+        )));
+    }
 
-    let source_code = fs::read_to_string(file_to_mutate)?;
-    let lines: Vec<&str> = source_code.lines().collect();
-    println!("File has {} lines", lines.len());
+    info!("\n\nGenerating mutants for {}...", file_to_mutate);
 
-    // Initialize AST-based arid node detection for C++ files
-    let mut arid_detector = if enable_ast_filtering
-        && (file_to_mutate.ends_with(".cpp") || file_to_mutate.ends_with(".h"))
-    {
+    let source_code = fs::read_to_string(file_to_mutate)?;
+    let source = SourceFile::new(&source_code);
+    let lines = &source.lines;
+    debug!("File has {} lines", lines.len());
+
+    // Initialize AST-based arid node detection for C++ files. Security mutants
+    // are filtered too; they are meant to be aggressive, so fuzzing runs usually
+    // pass --disable-ast-filtering to keep every line.
+    let mut arid_detector = if enable_ast_filtering && is_c_family_source(file_to_mutate) {
+        if options.only_security_mutations {
+            info!("AST filtering security mutants (--disable-ast-filtering keeps them all)");
+        }
         let mut detector = AridNodeDetector::new()?;
 
         // Add custom expert rule if provided
-        if let Some(rule) = custom_expert_rule {
-            detector.add_expert_rule(&rule, "Custom user rule")?;
+        if let Some(ref rule) = options.custom_expert_rule {
+            detector.add_expert_rule(rule, "Custom user rule")?;
         }
 
         Some(detector)
     } else {
         if !enable_ast_filtering {
-            println!("AST filtering disabled - generating all possible mutants");
+            info!("AST filtering disabled - generating all possible mutants");
         }
         None
     };
 
-    // Filter out arid lines using AST analysis (for C++ files)
+    // Filter out arid lines using AST analysis (for C++ files). With
+    // --verbose-arid, keep the reason each line was filtered out.
+    let mut arid_analysis: Option<DetailedAnalysis> = None;
+    let mut ast_stats = None;
     let ast_filtered_lines = if let Some(ref mut detector) = arid_detector {
-        let string_lines: Vec<String> = lines.iter().map(|s| s.to_string()).collect();
-        let mutatable_line_numbers = filter_mutatable_lines(&string_lines, detector);
-        println!(
+        let mutatable_line_numbers: Vec<usize> = if options.verbose_arid {
+            let analysis = arid_analysis.insert(detector.analyze_file_detailed(&source_code));
+            analysis
+                .lines
+                .iter()
+                .filter(|line| !line.is_arid)
+                .map(|line| line.line_number)
+                .collect()
+        } else {
+            let string_lines: Vec<String> = lines.iter().map(|s| s.to_string()).collect();
+            filter_mutatable_lines(&string_lines, detector)
+        };
+        info!(
             "AST analysis filtered to {} mutatable lines (from {})",
             mutatable_line_numbers.len(),
             lines.len()
@@ -204,184 +684,734 @@ pub async fn mutate_file(
         // Show some examples of filtered out lines
         let filtered_out_count = lines.len() - mutatable_line_numbers.len();
         if filtered_out_count > 0 {
-            println!(
+            debug!(
                 "Filtered out {} arid lines (logging, reserve calls, etc.)",
                 filtered_out_count
             );
         }
 
+        let mut stats: BTreeMap<String, usize> = detector.get_stats().into_iter().collect();
+        stats.insert("lines".to_string(), lines.len());
+        stats.insert("arid_lines".to_string(), filtered_out_count);
+        debug!("AST Analysis Stats: {:?}", stats);
+        ast_stats = Some(stats);
+
         Some(mutatable_line_numbers)
     } else {
         None
     };
 
     // Select operators based on file type and options
-    let operators = if only_security_mutations {
-        println!("Using security operators");
-        get_security_operators()?
-    } else if file_to_mutate.contains(".py") || is_unit_test {
-        println!("Using test operators (Python or unit test file)");
-        get_test_operators()?
-    } else {
-        println!("Using regex operators");
-        get_regex_operators()?
-    };
+    let operator_mode = OperatorMode::for_file(
+        file_to_mutate,
+        is_unit_test,
+        options.only_security_mutations,
+    );
+    debug!("Using {:?} operators", operator_mode);
+    let mut operators = operator_mode.operators()?;
+    if options.mutate_constants {
+        mutate_constant_declarations(&mut operators);
+    }
+    if operator_mode == OperatorMode::Regex {
+        for category in &options.operator_categories {
+            operators.extend(category.operators()?);
+        }
+    }
 
-    println!("Loaded {} operators", operators.len());
+    debug!("Loaded {} operators", operators.len());
 
-    let skip_lines_for_file = skip_lines.get(file_to_mutate);
     let mut touched_lines = touched_lines.unwrap_or_else(|| (1..=lines.len()).collect());
 
+    if let Some(analysis) = arid_analysis {
+        analysis.print_arid_lines_in(file_to_mutate, &touched_lines);
+    }
+
     // Apply AST filtering if available
     if let Some(ast_lines) = ast_filtered_lines {
         // Intersect touched_lines with AST-filtered lines
         touched_lines.retain(|line_num| ast_lines.contains(line_num));
-        println!(
+        debug!(
             "After AST filtering: {} lines to process",
             touched_lines.len()
         );
     }
 
-    // Get coverage data for this file
-    let lines_with_test_coverage = if let Some(cov) = coverage {
-        cov.iter()
-            .find(|(path, _)| file_to_mutate.contains(path.as_str()))
-            .map(|(_, lines)| lines.clone())
-            .unwrap_or_default()
-    } else {
-        Vec::new()
+    // Get coverage data for this file. It only comes from an explicit coverage
+    // file and applies to every operator set; a file missing from it is
+    // mutated in full.
+    let lines_with_test_coverage = match options.coverage {
+        Some(ref cov) => match covered_lines(cov, file_to_mutate) {
+            Some(lines) => lines.clone(),
+            None => {
+                warn!(
+                    "no coverage data for {}; mutating it without coverage",
+                    file_to_mutate
+                );
+                Vec::new()
+            }
+        },
+        None => Vec::new(),
     };
 
     if !lines_with_test_coverage.is_empty() {
-        println!(
+        info!(
             "Using coverage data with {} covered lines",
             lines_with_test_coverage.len()
         );
     }
 
-    let mut mutant_count = 0;
-    let mut collected: Vec<MutantData> = Vec::new();
-
     if one_mutant {
-        println!("One mutant mode enabled");
+        info!("One mutant mode enabled");
     }
 
-    for line_num in touched_lines {
+    // Coverage and the line range narrow down the lines to mutate. Skip lines are
+    // passed on separately, as they also rule out multi-line mutants.
+    touched_lines.retain(|&line_num| {
         let line_idx = line_num.saturating_sub(1);
+        (lines_with_test_coverage.is_empty() || lines_with_test_coverage.contains(&line_num))
+            && range_lines.is_none_or(|(start, end)| (start..=end).contains(&line_idx))
+    });
+    let multiline = options.multiline_window > 1 && operator_mode == OperatorMode::Regex;
+    let mut multiline_operators = if multiline {
+        OperatorMode::Multiline.operators()?
+    } else {
+        Vec::new()
+    };
+    if let Some(ref name) = options.only_operator {
+        operators.retain(|operator| operator.is_named(name));
+        multiline_operators.retain(|operator| operator.is_named(name));
+    }
+    override_priorities(&mut operators, &options.operator_priorities);
+    override_priorities(&mut multiline_operators, &options.operator_priorities);
+    let source_options = SourceOptions {
+        lines: Some(touched_lines),
+        skip_lines: options
+            .skip_lines
+            .get(file_to_mutate)
+            .cloned()
+            .unwrap_or_default(),
+        exclude_lines_matching: options.exclude_lines_matching.clone(),
+        is_unit_test,
+        is_python: file_to_mutate.contains(".py"),
+        one_mutant,
+        max_mutants: options.max_mutants,
+        multiline_operators,
+        multiline_window: options.multiline_window,
+        shuffle_seed: options
+            .shuffle
+            .map(|seed| seed.unwrap_or_else(rand::random)),
+    };
+    let SourceMutants {
+        mutants,
+        equivalent,
+        truncated,
+    } = mutate_source(&source_code, &operators, &source_options)?;
+    let file_summary = FileSummary {
+        file: file_to_mutate.to_string(),
+        mutants: mutants.len(),
+        equivalent,
+        operators: operator_counts(
+            operators.iter().chain(&source_options.multiline_operators),
+            &mutants,
+        ),
+        ast_stats,
+        preview: Vec::new(),
+    };
 
-        // Check coverage if provided
-        if !lines_with_test_coverage.is_empty() && !lines_with_test_coverage.contains(&line_num) {
-            continue;
-        }
+    if options.preview {
+        info!(
+            "{} mutants would be generated for {}",
+            mutants.len(),
+            file_to_mutate
+        );
+        let preview = mutants
+            .iter()
+            .map(|mutant| preview_line(&source, mutant))
+            .collect();
+        return Ok((
+            FileSummary {
+                preview,
+                ..file_summary
+            },
+            Vec::new(),
+        ));
+    }
+    if options.dry_run {
+        info!(
+            "{} mutants would be generated for {}",
+            mutants.len(),
+            file_to_mutate
+        );
+        return Ok((file_summary, Vec::new()));
+    }
 
-        // Check range if provided
-        if let Some((start, end)) = range_lines {
-            if line_idx < start || line_idx > end {
-                continue;
-            }
+    let folder = mutant_folder(
+        file_to_mutate,
+        pr_number,
+        range_lines,
+        options.output_dir.as_deref(),
+    )?;
+    let mut mutant_count = 0;
+    let mut collected: Vec<MutantData> = Vec::new();
+    let mut manifest_entries: Vec<ManifestEntry> = Vec::new();
+    for mutant in &mutants {
+        if let Some((data, entry)) = record_mutant(
+            file_to_mutate,
+            &source,
+            mutant,
+            &mut mutant_count,
+            &folder,
+            options,
+        )
+        .await?
+        {
+            collected.push(data);
+            manifest_entries.push(entry);
         }
+    }
 
-        // Check skip lines (skip_lines uses 1-indexed line numbers)
-        if let Some(skip) = skip_lines_for_file {
-            if skip.contains(&line_num) {
-                continue;
-            }
+    if let Some(max_mutants) = options.max_mutants.filter(|_| truncated) {
+        warn!(
+            "reached --max-mutants ({}) for {}; some mutants were not generated",
+            max_mutants, file_to_mutate
+        );
+    }
+
+    if !manifest_entries.is_empty() {
+        Manifest {
+            target_file: file_to_mutate.to_string(),
+            mutants: manifest_entries,
+            ast_stats: file_summary.ast_stats.clone(),
+            equivalent,
         }
+        .write(&folder)?;
+    }
 
-        if line_idx >= lines.len() {
-            continue;
+    info!("Generated {} mutants...", mutant_count);
+    let file_summary = FileSummary {
+        mutants: collected.len(),
+        ..file_summary
+    };
+    Ok((file_summary, collected))
+}
+
+/// The file being mutated, split into lines. Mutants are written with the
+/// file's own line endings, so they only differ from it by the mutation.
+struct SourceFile<'a> {
+    lines: Vec<&'a str>,
+    /// The terminator of each line: `"\r\n"`, `"\n"`, or `""` for a last line
+    /// without one.
+    endings: Vec<&'static str>,
+}
+
+/// A mutant: lines `line..=end_line` (1-indexed) of the source replaced with
+/// `replacement`. Only the edit is kept; mutated files are streamed from the
+/// source lines instead of being built in memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mutant {
+    pub line: usize,
+    pub end_line: usize,
+    /// Operator that produced the mutant, as `pattern ==> replacement`.
+    pub operator: String,
+    pub replacement: String,
+}
+
+/// `line : operator : before -> after`, as printed by `--preview`.
+fn preview_line(source: &SourceFile, mutant: &Mutant) -> String {
+    let before = source.lines[mutant.line - 1..mutant.end_line]
+        .iter()
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let after = mutant
+        .replacement
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "{:>5} : {} : {} -> {}",
+        mutant.line,
+        mutant.operator,
+        before,
+        if after.is_empty() {
+            "<deleted>"
+        } else {
+            &after
         }
+    )
+}
+
+/// Which lines [`mutate_source`] mutates and how.
+#[derive(Debug, Clone, Default)]
+pub struct SourceOptions {
+    /// Lines (1-indexed) that may be mutated; all of them when `None`.
+    pub lines: Option<Vec<usize>>,
+    /// Lines (1-indexed) no mutant may touch, multi-line ones included.
+    pub skip_lines: Vec<usize>,
+    /// Lines matching one of these are skipped like `skip_lines`.
+    pub exclude_lines_matching: Vec<Regex>,
+    /// Apply the skip rules of unit tests.
+    pub is_unit_test: bool,
+    /// Apply the skip rules of Python scripts.
+    pub is_python: bool,
+    /// Create at most one mutant per line.
+    pub one_mutant: bool,
+    /// Stop once this many mutants were created.
+    pub max_mutants: Option<usize>,
+    /// Operators run over windows of `multiline_window` lines, after the
+    /// single-line ones.
+    pub multiline_operators: Vec<MutationOperator>,
+    pub multiline_window: usize,
+    /// Visit lines in an order shuffled with this seed.
+    pub shuffle_seed: Option<u64>,
+}
+
+/// The mutants generated by [`mutate_source`].
+#[derive(Debug, Default)]
+pub struct SourceMutants {
+    pub mutants: Vec<Mutant>,
+    /// Mutants left out because they are equivalent to the original code.
+    pub equivalent: usize,
+    /// Whether `max_mutants` cut off a mutant that would have been generated.
+    pub truncated: bool,
+}
 
-        let line_before_mutation = lines[line_idx];
+/// Generate the mutants of `source_code` without touching the filesystem.
+/// Mutants that would produce byte-identical files are only returned once.
+pub fn mutate_source(
+    source_code: &str,
+    operators: &[MutationOperator],
+    options: &SourceOptions,
+) -> Result<SourceMutants> {
+    let source = SourceFile::new(source_code);
+    let lines = &source.lines;
+    let mut line_order = options
+        .lines
+        .clone()
+        .unwrap_or_else(|| (1..=lines.len()).collect());
+    let allowed: HashSet<usize> = line_order.iter().copied().collect();
+    let mut skip_lines: HashSet<usize> = options.skip_lines.iter().copied().collect();
+    // Code in `#if 0` blocks is never compiled, so its mutants cannot be killed.
+    if !options.is_python {
+        skip_lines.extend(disabled_lines(lines));
+    }
+    // Lines annotated with `bcore-mutation: skip` are left out by `should_skip_line`.
+    skip_lines.extend(
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.contains(SKIP_NEXT_ANNOTATION))
+            .map(|(i, _)| i + 2),
+    );
+    let line_skipped = |line_num: usize| {
+        skip_lines.contains(&line_num)
+            || options
+                .exclude_lines_matching
+                .iter()
+                .any(|regex| regex.is_match(lines[line_num - 1]))
+    };
+
+    // Whether a (1-indexed) line may be mutated.
+    let line_selected = |line_num: usize| {
+        (1..=lines.len()).contains(&line_num)
+            && allowed.contains(&line_num)
+            && !line_skipped(line_num)
+    };
+
+    // With one mutant per line, operators are tried by priority (in list order
+    // on ties) and a line keeps its first mutant that is neither equivalent nor
+    // a duplicate.
+    let prioritized = |operators: &[MutationOperator]| {
+        let mut operators = operators.to_vec();
+        if options.one_mutant {
+            operators.sort_by_key(|operator| operator.priority);
+        }
+        operators
+    };
+    let operators = prioritized(operators);
+    let multiline_operators = prioritized(&options.multiline_operators);
+
+    let max_mutants = options.max_mutants.unwrap_or(usize::MAX);
+    let mut truncated = false;
+    let mut rng = options.shuffle_seed.map(StdRng::seed_from_u64);
+    if let Some(ref mut rng) = rng {
+        line_order.shuffle(rng);
+    }
 
+    // Filter once up front; only the lines left are mutated in parallel.
+    let is_test_file = options.is_python || options.is_unit_test;
+    let mut candidate_lines = Vec::new();
+    for &line_num in &line_order {
+        if !line_selected(line_num) {
+            continue;
+        }
+        let line = lines[line_num - 1];
         // Check if line should be skipped (traditional approach)
-        if should_skip_line(line_before_mutation, file_to_mutate, is_unit_test)? {
+        if should_skip_line(line, options.is_python, options.is_unit_test)? {
             continue;
         }
+        // Special handling for test operators
+        if is_test_file && !should_mutate_test_line(line) {
+            continue;
+        }
+        candidate_lines.push(line_num);
+    }
 
-        let mut line_had_match = false;
-
-        for operator in &operators {
-            // Special handling for test operators
-            if file_to_mutate.contains(".py") || is_unit_test {
-                if !should_mutate_test_line(line_before_mutation) {
+    let mut mutants = Vec::new();
+    // Hashes of the mutated contents so far: different operators can produce
+    // byte-identical files, which must only be tested once.
+    let mut seen_contents: HashSet<String> = HashSet::new();
+    let mut deduped = 0;
+    let mut equivalent = 0;
+
+    // Lines are mutated in parallel a chunk at a time, so that a low
+    // `max_mutants` does not pay for the whole file. Mutants are kept in line
+    // order so their numbering stays deterministic.
+    'chunks: for chunk in candidate_lines.chunks(GENERATION_CHUNK_LINES) {
+        let line_mutants: Vec<_> = chunk
+            .par_iter()
+            .map(|&line_num| {
+                let (had_match, mutants) = mutate_line(lines, line_num - 1, &operators);
+                (line_num, had_match, mutants)
+            })
+            .collect();
+
+        for (line_num, had_match, line_mutants) in line_mutants {
+            for mutant in line_mutants {
+                if let Some(reason) = equivalent_mutation(lines[line_num - 1], &mutant.replacement)
+                {
+                    debug!(
+                        "Line {}: skipping equivalent mutant '{}' ({})",
+                        line_num,
+                        mutant.replacement.trim(),
+                        reason
+                    );
+                    equivalent += 1;
                     continue;
                 }
+                if !seen_contents.insert(source.mutant_hash(&mutant)) {
+                    deduped += 1;
+                    continue;
+                }
+                if mutants.len() >= max_mutants {
+                    truncated = true;
+                    break 'chunks;
+                }
+                mutants.push(mutant);
+                if options.one_mutant {
+                    break;
+                }
             }
 
-            if operator.pattern.is_match(line_before_mutation) {
-                line_had_match = true;
-                let line_mutated = operator
-                    .pattern
-                    .replace(line_before_mutation, &operator.replacement);
-
-                // Create mutated file content
-                let mut mutated_lines = lines.clone();
-                mutated_lines[line_idx] = &line_mutated;
-                let mut mutated_content = mutated_lines.join("\n");
-                if source_code.ends_with('\n') {
-                    mutated_content.push('\n');
+            // Debug output for lines that didn't match any patterns
+            let line = lines[line_num - 1];
+            if !had_match && !line.trim().is_empty() {
+                debug!(
+                    "Line {} '{}' didn't match any patterns (see list-operators)",
+                    line_num,
+                    line.trim()
+                );
+            }
+        }
+    }
+
+    // Multi-line operators run against a window of lines starting at each line.
+    // Only matches that start on the window's first line and span a line break
+    // count, so single-line constructs and overlapping windows are not duplicated.
+    if options.multiline_window > 1 && !truncated {
+        let mut window_starts: Vec<usize> = (0..lines.len()).collect();
+        if let Some(ref mut rng) = rng {
+            window_starts.shuffle(rng);
+        }
+
+        'windows: for line_idx in window_starts {
+            let window_end = (line_idx + options.multiline_window).min(lines.len());
+            let window = lines[line_idx..window_end].join("\n");
+            let first_line_len = lines[line_idx].len();
+
+            for operator in &multiline_operators {
+                if !operator.applies_to(&window) {
+                    continue;
+                }
+                let Some(captures) = operator.pattern.captures(&window) else {
+                    continue;
+                };
+                let matched = captures.get(0).unwrap();
+                if matched.start() > first_line_len || !matched.as_str().contains('\n') {
+                    continue;
+                }
+
+                // The mutant replaces every line the match touches.
+                let span_end = line_idx + window[..matched.end()].matches('\n').count();
+                let span: Vec<usize> = (line_idx + 1..=span_end + 1).collect();
+                if !span.iter().any(|n| line_selected(*n)) {
+                    continue;
+                }
+                let mut skipped = false;
+                for &n in &span {
+                    skipped |=
+                        should_skip_line(lines[n - 1], options.is_python, options.is_unit_test)?;
+                    skipped |= line_skipped(n);
+                }
+                if skipped {
+                    continue;
+                }
+
+                let mut replacement = String::new();
+                captures.expand(&operator.replacement, &mut replacement);
+                let span_text = lines[line_idx..=span_end].join("\n");
+                let span_mutated = format!(
+                    "{}{}{}",
+                    &span_text[..matched.start()],
+                    replacement,
+                    &span_text[matched.end()..]
+                );
+                if span_mutated == span_text {
+                    continue;
                 }
 
-                mutant_count = write_mutation(
-                    file_to_mutate,
-                    &mutated_content,
-                    mutant_count,
-                    pr_number,
-                    range_lines,
-                )?;
-
-                // Collect mutant metadata for DB persistence.
-                let diff = match generate_diff(file_to_mutate, &mutated_content).await {
-                    Ok(d) => d,
-                    Err(e) => {
-                        eprintln!(
-                            "  Warning: could not generate diff for mutant at line {}: {}",
-                            line_num, e
-                        );
-                        continue;
-                    }
+                let mutant = Mutant {
+                    line: line_idx + 1,
+                    end_line: span_end + 1,
+                    operator: operator.name(),
+                    replacement: span_mutated,
                 };
-                let patch_hash = compute_patch_hash(&diff);
-                let operator_label =
-                    format!("{} ==> {}", operator.pattern.as_str(), operator.replacement);
-                collected.push(MutantData {
-                    diff,
-                    patch_hash,
-                    file_path: file_to_mutate.to_string(),
-                    operator: operator_label,
-                });
-
-                if one_mutant {
-                    break; // Break only from operator loop, continue to next line
+                if !seen_contents.insert(source.mutant_hash(&mutant)) {
+                    deduped += 1;
+                    continue;
+                }
+                if mutants.len() >= max_mutants {
+                    truncated = true;
+                    break 'windows;
+                }
+                mutants.push(mutant);
+
+                if options.one_mutant {
+                    break;
                 }
             }
         }
+    }
 
-        // Debug output for lines that didn't match any patterns
-        if !line_had_match && !line_before_mutation.trim().is_empty() {
-            println!(
-                "Line {} '{}' didn't match any patterns",
-                line_num,
-                line_before_mutation.trim()
-            );
+    if deduped > 0 {
+        info!("Skipped {} duplicate mutants", deduped);
+    }
+    if equivalent > 0 {
+        info!("Skipped {} equivalent mutants", equivalent);
+    }
+    Ok(SourceMutants {
+        mutants,
+        equivalent,
+        truncated,
+    })
+}
+
+impl<'a> SourceFile<'a> {
+    fn new(source_code: &'a str) -> Self {
+        let (lines, endings) = source_code
+            .split_inclusive('\n')
+            .map(|line| {
+                if let Some(line) = line.strip_suffix("\r\n") {
+                    (line, "\r\n")
+                } else if let Some(line) = line.strip_suffix('\n') {
+                    (line, "\n")
+                } else {
+                    (line, "")
+                }
+            })
+            .unzip();
+        SourceFile { lines, endings }
+    }
+
+    /// Write the source with `edit` applied: the lines before it, the
+    /// replacement and the lines after it. Line breaks in a multi-line
+    /// replacement are `\n` and get the line ending of the first replaced line.
+    fn write_mutant(&self, mutant: &Mutant, out: &mut dyn Write) -> io::Result<()> {
+        let write_lines = |out: &mut dyn Write, range: std::ops::Range<usize>| {
+            for (line, ending) in self.lines[range.clone()].iter().zip(&self.endings[range]) {
+                out.write_all(line.as_bytes())?;
+                out.write_all(ending.as_bytes())?;
+            }
+            Ok::<_, io::Error>(())
+        };
+        write_lines(out, 0..mutant.line - 1)?;
+        let newline = self.endings[mutant.line - 1];
+        let newline = if newline.is_empty() { "\n" } else { newline };
+        out.write_all(mutant.replacement.replace('\n', newline).as_bytes())?;
+        out.write_all(self.endings[mutant.end_line - 1].as_bytes())?;
+        write_lines(out, mutant.end_line..self.lines.len())
+    }
+
+    /// SHA-256 of the mutated file, as [`compute_patch_hash`] would give for it.
+    fn mutant_hash(&self, mutant: &Mutant) -> String {
+        let mut hasher = Sha256::new();
+        self.write_mutant(mutant, &mut hasher)
+            .expect("hashing does not fail");
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Why rewriting the line `before` into `after` obviously does not change the
+/// program, if it does not: an identity operation swapped for another
+/// (`x - 0` into `x + 0`, `x * 1` into `x / 1`), or a changed capacity hint
+/// (`v.reserve(10)` into `v.reserve(10 + 1)`). Such mutants always survive and
+/// are skipped without being written.
+fn equivalent_mutation(before: &str, after: &str) -> Option<&'static str> {
+    let mut prefix = before
+        .bytes()
+        .zip(after.bytes())
+        .take_while(|(b, a)| b == a)
+        .count();
+    while !before.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let mut suffix = before[prefix..]
+        .bytes()
+        .rev()
+        .zip(after[prefix..].bytes().rev())
+        .take_while(|(b, a)| b == a)
+        .count();
+    while !before.is_char_boundary(before.len() - suffix) {
+        suffix -= 1;
+    }
+    let removed = before[prefix..before.len() - suffix].trim();
+    let added = after[prefix..after.len() - suffix].trim();
+    let rest = before[before.len() - suffix..].trim_start();
+
+    // The operand right after the swapped operator is a lone 0 or 1.
+    let operand_is = |digit: char| {
+        let mut chars = rest.chars();
+        chars.next() == Some(digit)
+            && !chars
+                .next()
+                .is_some_and(|c| c.is_alphanumeric() || c == '.' || c == '\'' || c == '_')
+    };
+    match (removed, added) {
+        ("+", "-") | ("-", "+") if operand_is('0') => return Some("adds or subtracts 0"),
+        ("*", "/") | ("/", "*") if operand_is('1') => return Some("multiplies or divides by 1"),
+        _ => {}
+    }
+
+    // `($1 + 1)`/`($1 - 1)` applied to the argument of `reserve(N)`.
+    let argument_of = before[..prefix].trim_end_matches(|c: char| c.is_ascii_digit() || c == '-');
+    if removed.is_empty()
+        && matches!(added, "+ 1" | "- 1")
+        && argument_of.ends_with("reserve(")
+        && rest.starts_with(')')
+    {
+        return Some("only changes a capacity hint");
+    }
+    None
+}
+
+/// Apply `operators` to line `line_idx` and return whether any matched, along
+/// with the mutants in operator order.
+fn mutate_line(
+    lines: &[&str],
+    line_idx: usize,
+    operators: &[MutationOperator],
+) -> (bool, Vec<Mutant>) {
+    let line_before_mutation = lines[line_idx];
+    let mut had_match = false;
+    let mut mutants = Vec::new();
+
+    for operator in operators {
+        if !operator.applies_to(line_before_mutation) {
+            continue;
+        }
+        had_match = true;
+        let line_mutated = operator.mutate(line_before_mutation);
+
+        // An operator can rewrite a line to itself (e.g. `if (1==1)`); such a
+        // mutant has an empty diff and must not be written or stored.
+        if line_mutated == line_before_mutation {
+            continue;
         }
 
-        // Note: Removed the early break that was stopping line processing
-        // Now each line gets processed independently
+        mutants.push(Mutant {
+            line: line_idx + 1,
+            end_line: line_idx + 1,
+            operator: operator.name(),
+            replacement: line_mutated.into_owned(),
+        });
     }
 
-    // Print AST analysis statistics
-    if let Some(detector) = arid_detector {
-        let stats = detector.get_stats();
-        println!("AST Analysis Stats: {:?}", stats);
+    (had_match, mutants)
+}
+
+/// Write a mutant (as a full copy, or as a patch with `--patch`) and return the
+/// metadata stored in SQLite along with its manifest entry. Returns `None` when
+/// its diff cannot be generated, leaving no file behind.
+async fn record_mutant(
+    file_to_mutate: &str,
+    source: &SourceFile<'_>,
+    mutant: &Mutant,
+    mutant_count: &mut usize,
+    folder: &Path,
+    options: &MutationOptions,
+) -> Result<Option<(MutantData, ManifestEntry)>> {
+    let line_num = mutant.line;
+    let mutant_file = mutant_file_name(file_to_mutate, *mutant_count, options.patch)?;
+
+    // A full copy is diffed where it was written; in patch mode the mutated
+    // file only exists long enough to be diffed.
+    let tmp;
+    let mutated_path = if options.patch {
+        let mut out = BufWriter::new(NamedTempFile::new()?);
+        source.write_mutant(mutant, &mut out)?;
+        tmp = out.into_inner().map_err(io::IntoInnerError::into_error)?;
+        tmp.path().to_path_buf()
+    } else {
+        *mutant_count = write_mutation(folder, file_to_mutate, *mutant_count, false, |out| {
+            source.write_mutant(mutant, out)
+        })?;
+        folder.join(&mutant_file)
+    };
+
+    let diff = match generate_diff_from_file(file_to_mutate, &mutated_path).await {
+        Ok(d) => d,
+        Err(e) => {
+            warn!(
+                "could not generate diff for mutant at line {}: {}",
+                line_num, e
+            );
+            // Its index goes to the next mutant.
+            if !options.patch {
+                fs::remove_file(&mutated_path)?;
+                *mutant_count -= 1;
+            }
+            return Ok(None);
+        }
+    };
+    // In patch mode the diff is the mutant.
+    if options.patch {
+        *mutant_count = write_mutation(folder, file_to_mutate, *mutant_count, true, |out| {
+            out.write_all(diff.as_bytes())
+        })?;
     }
 
-    println!("Generated {} mutants...", mutant_count);
-    Ok(collected)
+    let patch_hash = compute_patch_hash(&diff);
+    let operator = mutant.operator.clone();
+    let entry = ManifestEntry {
+        file: mutant_file,
+        line: line_num,
+        description: describe_operator(&operator).unwrap_or_default(),
+        operator: operator.clone(),
+        patch_hash: patch_hash.clone(),
+    };
+    Ok(Some((
+        MutantData {
+            patch_hash,
+            diff,
+            file_path: file_to_mutate.to_string(),
+            operator,
+        },
+        entry,
+    )))
 }
 
-fn should_skip_line(line: &str, file_path: &str, is_unit_test: bool) -> Result<bool> {
+fn should_skip_line(line: &str, is_python: bool, is_unit_test: bool) -> Result<bool> {
     let trimmed = line.trim_start();
 
     // Check basic patterns to skip
@@ -399,7 +1429,7 @@ fn should_skip_line(line: &str, file_path: &str, is_unit_test: bool) -> Result<b
     }
 
     // Language-specific checks
-    if file_path.contains(".py") || is_unit_test {
+    if is_python || is_unit_test {
         let patterns = if is_unit_test {
             get_do_not_mutate_unit_patterns()
         } else {
@@ -453,27 +1483,40 @@ fn get_folder_path(file_to_mutate: &str) -> String {
     }
 }
 
+/// Write a mutant to `folder`, with `write_contents` streaming its contents,
+/// and return the next mutant index. With `patch`, the contents are a unified
+/// diff saved as `*.mutant.N.patch`.
 fn write_mutation(
+    folder: &Path,
     file_to_mutate: &str,
-    mutated_content: &str,
     mutant_index: usize,
+    patch: bool,
+    write_contents: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> Result<usize> {
+    let mutant_file = mutant_file_name(file_to_mutate, mutant_index, patch)?;
+
+    create_mutation_folder(folder, file_to_mutate)?;
+    let mut out = BufWriter::new(File::create(folder.join(mutant_file))?);
+    write_contents(&mut out)?;
+    out.flush()?;
+
+    Ok(mutant_index + 1)
+}
+
+/// Name of the mutation folder of `file_to_mutate`: `muts-pr-{pr}-{name}-{ext}`
+/// for a PR, `muts-{name}-{start}-{end}-{ext}` for a line range and
+/// `muts-{name}-{ext}` otherwise, where `name` is the path of the file without
+/// `src/` or its extension, with `/` replaced by `-`.
+pub fn mutant_folder_name(
+    file_to_mutate: &str,
     pr_number: Option<u32>,
     range_lines: Option<(usize, usize)>,
-) -> Result<usize> {
-    let file_extension = if file_to_mutate.ends_with(".h") {
-        ".h"
-    } else if file_to_mutate.ends_with(".py") {
-        ".py"
-    } else {
-        ".cpp"
-    };
+) -> Result<String> {
+    let ext = mutant_extension(file_to_mutate);
 
     let folders = get_folder_path(file_to_mutate);
 
-    let base_file_name = Path::new(file_to_mutate)
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .ok_or_else(|| MutationError::InvalidInput("Invalid file path".to_string()))?;
+    let base_file_name = file_stem(file_to_mutate)?;
 
     // Combine folders with base filename
     let file_name = if folders.is_empty() {
@@ -481,30 +1524,51 @@ fn write_mutation(
     } else {
         format!("{}/{}", folders, base_file_name)
     };
+    let file_name = file_name.replace('/', "-");
 
-    let ext = file_extension.trim_start_matches('.');
-    let folder = if let Some(pr) = pr_number {
-        format!("muts-pr-{}-{}-{}", pr, file_name.replace('/', "-"), ext)
-    } else if let Some(range) = range_lines {
-        format!("muts-pr-{}-{}-{}", file_name.replace('/', "-"), range.0, range.1)
+    Ok(if let Some(pr) = pr_number {
+        format!("muts-pr-{}-{}-{}", pr, file_name, ext)
+    } else if let Some((start, end)) = range_lines {
+        format!("muts-{}-{}-{}-{}", file_name, start, end, ext)
     } else {
-        format!("muts-{}-{}", file_name.replace('/', "-"), ext)
-    };
-
-    create_mutation_folder(&folder, file_to_mutate)?;
+        format!("muts-{}-{}", file_name, ext)
+    })
+}
 
-    let mutator_file = format!(
-        "{}/{}.mutant.{}{}",
-        folder, base_file_name, mutant_index, file_extension
-    );
-    fs::write(mutator_file, mutated_content)?;
+/// The mutation folder of `file_to_mutate`, in `output_dir` if given.
+fn mutant_folder(
+    file_to_mutate: &str,
+    pr_number: Option<u32>,
+    range_lines: Option<(usize, usize)>,
+    output_dir: Option<&Path>,
+) -> Result<PathBuf> {
+    let name = mutant_folder_name(file_to_mutate, pr_number, range_lines)?;
+    Ok(match output_dir {
+        Some(dir) => dir.join(name),
+        None => PathBuf::from(name),
+    })
+}
 
-    Ok(mutant_index + 1)
+/// File name of mutant number `mutant_index` of `file_to_mutate` within its
+/// mutation folder.
+fn mutant_file_name(file_to_mutate: &str, mutant_index: usize, patch: bool) -> Result<String> {
+    let ext = mutant_extension(file_to_mutate);
+    Ok(format!(
+        "{}.mutant.{}.{}",
+        file_stem(file_to_mutate)?,
+        mutant_index,
+        if patch { PATCH_EXTENSION } else { ext }
+    ))
 }
 
-fn create_mutation_folder(folder_name: &str, file_to_mutate: &str) -> Result<()> {
-    let folder_path = Path::new(folder_name);
+fn file_stem(file_to_mutate: &str) -> Result<&str> {
+    Path::new(file_to_mutate)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| MutationError::InvalidInput("Invalid file path".to_string()))
+}
 
+fn create_mutation_folder(folder_path: &Path, file_to_mutate: &str) -> Result<()> {
     if !folder_path.exists() {
         fs::create_dir_all(folder_path)?;
 
@@ -518,28 +1582,109 @@ fn create_mutation_folder(folder_name: &str, file_to_mutate: &str) -> Result<()>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::enter_dir;
     use tempfile::tempdir;
 
+    /// `source` with `mutant` applied, keeping its line endings.
+    fn apply(mutant: &Mutant, source: &str) -> String {
+        let mut content = Vec::new();
+        SourceFile::new(source)
+            .write_mutant(mutant, &mut content)
+            .unwrap();
+        String::from_utf8(content).unwrap()
+    }
+
     #[test]
     fn test_should_skip_line() {
         // Test basic skip patterns
-        assert!(should_skip_line("// This is a comment", "test.cpp", false).unwrap());
-        assert!(should_skip_line("assert(condition);", "test.cpp", false).unwrap());
-        assert!(should_skip_line("LogPrintf(\"test\");", "test.cpp", false).unwrap());
-        assert!(should_skip_line("LogDebug(\"test\");", "test.cpp", false).unwrap());
+        assert!(should_skip_line("// This is a comment", false, false).unwrap());
+        assert!(should_skip_line("assert(condition);", false, false).unwrap());
+        assert!(should_skip_line("LogPrintf(\"test\");", false, false).unwrap());
+        assert!(should_skip_line("LogDebug(\"test\");", false, false).unwrap());
 
         // Test normal lines that shouldn't be skipped
-        assert!(!should_skip_line("int x = 5;", "test.cpp", false).unwrap());
-        assert!(!should_skip_line("return value;", "test.cpp", false).unwrap());
+        assert!(!should_skip_line("int x = 5;", false, false).unwrap());
+        assert!(!should_skip_line("return value;", false, false).unwrap());
+    }
+
+    #[test]
+    fn test_equivalent_mutation() {
+        let equivalent = |before: &str, after: &str| equivalent_mutation(before, after).is_some();
+        assert!(equivalent("x = a - 0;", "x = a + 0;"));
+        assert!(equivalent("x = a+0;", "x = a-0;"));
+        assert!(equivalent("n = size * 1 + 2;", "n = size / 1 + 2;"));
+        assert!(equivalent("v.reserve(10);", "v.reserve(10 + 1);"));
+        assert!(equivalent(
+            "  vec.reserve(1); // é",
+            "  vec.reserve(1 - 1); // é"
+        ));
+
+        assert!(!equivalent("x = a - 0.5;", "x = a + 0.5;"));
+        assert!(!equivalent("x = a - 0x10;", "x = a + 0x10;"));
+        assert!(!equivalent("x = a - 1;", "x = a + 1;"));
+        assert!(!equivalent("x = 0 - a;", "x = 0 + a;"));
+        assert!(!equivalent("x = a * 10;", "x = a / 10;"));
+        assert!(!equivalent("v.resize(10);", "v.resize(10 + 1);"));
+        assert!(!equivalent("v.reserve(n);", "v.reserve(n + 1);"));
+
+        let operators = OperatorMode::Regex.operators().unwrap();
+        let SourceMutants {
+            mutants,
+            equivalent,
+            ..
+        } = mutate_source(
+            "v.reserve(16);\nx = a - 0;\ny = b - 1;\n",
+            &operators,
+            &SourceOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(equivalent, 3);
+        let replacements: Vec<&str> = mutants.iter().map(|m| m.replacement.as_str()).collect();
+        assert!(!replacements.contains(&"v.reserve(16 + 1);"));
+        assert!(!replacements.contains(&"x = a + 0;"));
+        assert!(replacements.contains(&"y = b + 1;"));
+    }
+
+    #[test]
+    fn test_skip_annotations() {
+        assert!(should_skip_line("x += 1; // bcore-mutation: skip", false, false).unwrap());
+        assert!(should_skip_line("x += 1  # bcore-mutation: skip", true, false).unwrap());
+
+        let source = [
+            "a = b + c;",
+            "a = b + c; // bcore-mutation: skip",
+            "// bcore-mutation: skip-next",
+            "a = b + c;",
+            "a = b + c; // bcore-mutation: skip-next",
+            "a = b + c;",
+            "a = b + c;",
+            "a = b + c;",
+        ]
+        .join("\n");
+        let operators = vec![MutationOperator::new(r"b \+ c", "b - c").unwrap()];
+        let mutants = mutate_source(&source, &operators, &SourceOptions::default())
+            .unwrap()
+            .mutants;
+        let lines: Vec<usize> = mutants.iter().map(|m| m.line).collect();
+        assert_eq!(lines, vec![1, 7, 8]);
+
+        // Nor do multi-line mutants reach into annotated lines.
+        let options = SourceOptions {
+            multiline_operators: vec![MutationOperator::new(r"c;\na", "c; a").unwrap()],
+            multiline_window: 2,
+            ..Default::default()
+        };
+        let mutants = mutate_source(&source, &[], &options).unwrap().mutants;
+        let lines: Vec<usize> = mutants.iter().map(|m| m.line).collect();
+        assert_eq!(lines, vec![7]);
     }
 
     #[test]
     fn test_create_mutation_folder() {
         let temp_dir = tempdir().unwrap();
         let folder_path = temp_dir.path().join("test_muts");
-        let folder_name = folder_path.to_str().unwrap();
 
-        create_mutation_folder(folder_name, "test/file.cpp").unwrap();
+        create_mutation_folder(&folder_path, "test/file.cpp").unwrap();
 
         assert!(folder_path.exists());
         assert!(folder_path.join("original_file.txt").exists());
@@ -548,12 +1693,531 @@ mod tests {
         assert_eq!(content, "test/file.cpp");
     }
 
+    #[tokio::test]
+    async fn test_multiline_window_mutates_split_condition() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let source = "int f(int a, int b) {\n    if (a > 0 &&\n        b > 0) {\n        return 1;\n    }\n    return 0;\n}\n";
+        fs::write("split.cpp", source).unwrap();
+
+        let collapsed = |options: &MutationOptions| {
+            let options = options.clone();
+            async move {
+                mutate_file("split.cpp", None, false, &options)
+                    .await
+                    .unwrap()
+                    .1
+                    .into_iter()
+                    .filter(|m| m.diff.contains("+    if (1==1) {"))
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let mut options = MutationOptions {
+            enable_ast_filtering: false,
+            ..Default::default()
+        };
+        assert!(collapsed(&options).await.is_empty());
+
+        options.multiline_window = 3;
+        let mutants = collapsed(&options).await;
+        assert_eq!(mutants.len(), 1);
+        assert!(mutants[0].diff.contains("-    if (a > 0 &&"));
+        assert!(mutants[0].diff.contains("-        b > 0) {"));
+    }
+
+    #[tokio::test]
+    async fn test_record_mutant_without_diff_leaves_no_file() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let folder = Path::new("muts-missing-cpp");
+        let source = SourceFile::new("a = b;\n");
+        let mutant = Mutant {
+            line: 1,
+            end_line: 1,
+            operator: "b ==> c".to_string(),
+            replacement: "a = c;".to_string(),
+        };
+
+        // missing.cpp does not exist, so it cannot be diffed.
+        let mut mutant_count = 0;
+        let recorded = record_mutant(
+            "missing.cpp",
+            &source,
+            &mutant,
+            &mut mutant_count,
+            folder,
+            &MutationOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(recorded.is_none());
+        assert_eq!(mutant_count, 0);
+        assert!(folder.join("original_file.txt").exists());
+        assert!(!folder.join("missing.mutant.0.cpp").exists());
+    }
+
+    #[tokio::test]
+    async fn test_max_mutants_caps_each_file() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let source: String = (0..20).map(|i| format!("x = a + {};\n", i)).collect();
+        fs::write("big.cpp", source).unwrap();
+
+        let mut options = MutationOptions {
+            enable_ast_filtering: false,
+            one_mutant: true,
+            max_mutants: Some(5),
+            ..Default::default()
+        };
+        let first = mutate_file("big.cpp", None, false, &options)
+            .await
+            .unwrap()
+            .1;
+        assert_eq!(first.len(), 5);
+
+        // With a seed the cap samples lines across the file, reproducibly.
+        options.shuffle = Some(Some(7));
+        let sampled = mutate_file("big.cpp", None, false, &options)
+            .await
+            .unwrap()
+            .1;
+        let again = mutate_file("big.cpp", None, false, &options)
+            .await
+            .unwrap()
+            .1;
+        assert_eq!(sampled.len(), 5);
+        let hashes =
+            |ms: &[MutantData]| ms.iter().map(|m| m.patch_hash.clone()).collect::<Vec<_>>();
+        assert_eq!(hashes(&sampled), hashes(&again));
+        assert_ne!(hashes(&sampled), hashes(&first));
+    }
+
+    #[tokio::test]
+    async fn test_security_mutations_with_ast_and_coverage() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        // The arid node detector drops the reserve call.
+        fs::write("fuzz.cpp", "vec.reserve(n + 1);\nreturn x + y;\n").unwrap();
+
+        let mutated_lines = |enable_ast_filtering: bool, covered: Option<Vec<usize>>| {
+            let options = MutationOptions {
+                only_security_mutations: true,
+                enable_ast_filtering,
+                coverage: covered.map(|lines| HashMap::from([("fuzz.cpp".to_string(), lines)])),
+                ..Default::default()
+            };
+            async move {
+                let _ = fs::remove_dir_all("muts-fuzz-cpp");
+                mutate_file("fuzz.cpp", None, false, &options)
+                    .await
+                    .unwrap();
+                let manifest = Manifest::read(Path::new("muts-fuzz-cpp")).unwrap();
+                let mut lines: Vec<usize> = manifest
+                    .map(|m| m.mutants.iter().map(|e| e.line).collect())
+                    .unwrap_or_default();
+                lines.dedup();
+                lines
+            }
+        };
+
+        assert_eq!(mutated_lines(true, None).await, vec![2]);
+        assert_eq!(mutated_lines(false, None).await, vec![1, 2]);
+        assert_eq!(mutated_lines(false, Some(vec![1])).await, vec![1]);
+        assert!(mutated_lines(true, Some(vec![1])).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mutate_file_writes_manifest() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        fs::write("calc.cpp", "x = a + b;\nif (x > 0) return;\n").unwrap();
+
+        let options = MutationOptions {
+            enable_ast_filtering: false,
+            ..Default::default()
+        };
+        let (_, mutants) = mutate_file("calc.cpp", None, false, &options)
+            .await
+            .unwrap();
+
+        let manifest = Manifest::read(Path::new("muts-calc-cpp")).unwrap().unwrap();
+        assert_eq!(manifest.target_file, "calc.cpp");
+        assert_eq!(manifest.mutants.len(), mutants.len());
+        for (entry, mutant) in manifest.mutants.iter().zip(&mutants) {
+            assert_eq!(entry.patch_hash, mutant.patch_hash);
+            assert_eq!(entry.operator, mutant.operator);
+            assert!(Path::new("muts-calc-cpp").join(&entry.file).exists());
+        }
+        assert!(manifest.mutants.iter().any(|e| e.line == 2));
+        assert!(manifest.ast_stats.is_none());
+
+        // With AST filtering, the summary and manifest count the arid lines.
+        fs::write(
+            "calc.cpp",
+            "x = a + b;\nLogPrintf(\"done\\n\");\nif (x > 0) return;\n",
+        )
+        .unwrap();
+        let options = MutationOptions::default();
+        let (summary, _) = mutate_file("calc.cpp", None, false, &options)
+            .await
+            .unwrap();
+        let stats = summary.ast_stats.unwrap();
+        assert_eq!((stats["lines"], stats["arid_lines"]), (3, 1));
+        assert!(stats["total_expert_rules"] > 0);
+        let manifest = Manifest::read(Path::new("muts-calc-cpp")).unwrap().unwrap();
+        assert_eq!(manifest.ast_stats, Some(stats));
+    }
+
+    #[tokio::test]
+    async fn test_mutate_file_dry_run() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        fs::write("calc.cpp", "x = a + b;\nif (x > 0) return;\n").unwrap();
+
+        let mut options = MutationOptions {
+            dry_run: true,
+            enable_ast_filtering: false,
+            ..Default::default()
+        };
+        let (summary, mutants) = mutate_file("calc.cpp", None, false, &options)
+            .await
+            .unwrap();
+        let count = summary.mutants;
+        assert!(count > 0);
+        assert!(mutants.is_empty());
+        assert!(!Path::new("muts-calc-cpp").exists());
+
+        options.dry_run = false;
+        let (written, mutants) = mutate_file("calc.cpp", None, false, &options)
+            .await
+            .unwrap();
+        assert_eq!((written.mutants, mutants.len()), (count, count));
+
+        options.only_operator = Some("arithmetic +→-".to_string());
+        let (summary, _) = mutate_file("calc.cpp", None, false, &options)
+            .await
+            .unwrap();
+        assert_eq!(summary.mutants, 1);
+    }
+
+    #[tokio::test]
+    async fn test_files_from() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        fs::create_dir("src").unwrap();
+        fs::write("src/a.cpp", "x = a && b;\n").unwrap();
+        fs::write("src/b.cpp", "y = a || b;\n").unwrap();
+        fs::write(
+            "files.txt",
+            "# from another tool\nsrc/a.cpp\n\n  src/b.cpp  \n",
+        )
+        .unwrap();
+
+        let options = MutationOptions {
+            files_from: Some(PathBuf::from("files.txt")),
+            dry_run: true,
+            enable_ast_filtering: false,
+            ..Default::default()
+        };
+        let summary = run_mutation(options.clone()).await.unwrap();
+        let files: Vec<&str> = summary.files.iter().map(|f| f.file.as_str()).collect();
+        assert_eq!(files, ["src/a.cpp", "src/b.cpp"]);
+        assert!(summary.files.iter().all(|f| f.mutants > 0));
+
+        // Every path is checked before anything is mutated.
+        fs::write("files.txt", "src/a.cpp\nsrc/gone.cpp\nsrc/old.cpp\n").unwrap();
+        let err = run_mutation(options).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("do not exist: src/gone.cpp, src/old.cpp"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_operators() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        fs::write("a.cpp", "x = a && b;\n").unwrap();
+        fs::write("b.cpp", "y = a || b;\n").unwrap();
+        let options = MutationOptions {
+            dry_run: true,
+            enable_ast_filtering: false,
+            ..Default::default()
+        };
+
+        let mut summary = MutationSummary::default();
+        for file in ["a.cpp", "b.cpp"] {
+            let (file_summary, _) = mutate_file(file, None, false, &options).await.unwrap();
+            summary.files.push(file_summary);
+        }
+        let operators = OperatorMode::Regex.operators().unwrap();
+        assert_eq!(summary.files[0].operators.len(), operators.len());
+        assert!(summary.files[0]
+            .operators
+            .contains(&("&& ==> ||".to_string(), 1)));
+        assert!(summary.files[1]
+            .operators
+            .contains(&("&& ==> ||".to_string(), 0)));
+
+        // Matching in any file is enough.
+        let unmatched = summary.unmatched_operators();
+        assert!(!unmatched.iter().any(|name| name == "&& ==> ||"));
+        assert!(!unmatched.iter().any(|name| name == r"\|\| ==> &&"));
+        assert!(unmatched.iter().any(|name| name == "true ==> false"));
+        assert_eq!(unmatched.len(), operators.len() - 2);
+    }
+
+    #[tokio::test]
+    async fn test_mutate_file_uses_mutant_folder_name() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        fs::write("calc.cpp", "x = a + b;\nif (x > 0) return;\n").unwrap();
+
+        // Plain, PR and range runs each write to the folder the shared naming
+        // gives, and only the PR one is named `muts-pr-*`.
+        let mut names = HashSet::new();
+        for (pr_number, range_lines) in [(None, None), (Some(7), None), (None, Some((1, 2)))] {
+            let options = MutationOptions {
+                pr_number,
+                range_lines,
+                enable_ast_filtering: false,
+                ..Default::default()
+            };
+            mutate_file("calc.cpp", None, false, &options)
+                .await
+                .unwrap();
+
+            let name = mutant_folder_name("calc.cpp", pr_number, range_lines).unwrap();
+            assert!(
+                Manifest::read(Path::new(&name)).unwrap().is_some(),
+                "{}",
+                name
+            );
+            assert_eq!(
+                name.starts_with("muts-pr-"),
+                pr_number.is_some(),
+                "{}",
+                name
+            );
+            names.insert(name);
+        }
+        let folders = fs::read_dir(".")
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().path().is_dir())
+            .count();
+        assert_eq!((names.len(), folders), (3, 3));
+    }
+
+    #[tokio::test]
+    async fn test_mutate_file_skips_identical_mutants() {
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        // Several "remove the early return" operators delete this same line.
+        fs::write("guard.cpp", "int x = f();\nif (x) return;\n").unwrap();
+
+        let options = MutationOptions {
+            enable_ast_filtering: false,
+            ..Default::default()
+        };
+        let (_, mutants) = mutate_file("guard.cpp", None, false, &options)
+            .await
+            .unwrap();
+
+        let hashes: HashSet<&str> = mutants.iter().map(|m| m.patch_hash.as_str()).collect();
+        assert_eq!(hashes.len(), mutants.len());
+        let removals = mutants
+            .iter()
+            .filter(|m| m.diff.contains("-if (x) return;") && !m.diff.contains("+if"))
+            .count();
+        assert_eq!(removals, 1);
+    }
+
+    #[test]
+    fn test_mutate_line() {
+        let source = "int a = 1;\nif (x > y) return;\n";
+        let lines: Vec<&str> = source.lines().collect();
+        let operators = vec![
+            MutationOperator::new(r"x > y", "x >= y").unwrap(),
+            MutationOperator::new(r"x > y", "x > y").unwrap(),
+            MutationOperator::new(r"return;", "").unwrap(),
+        ];
+
+        // Identity rewrites are dropped.
+        let (had_match, mutants) = mutate_line(&lines, 1, &operators);
+        assert!(had_match);
+        assert_eq!(mutants.len(), 2);
+        let mut content = Vec::new();
+        SourceFile::new(source)
+            .write_mutant(&mutants[0], &mut content)
+            .unwrap();
+        assert_eq!(content, b"int a = 1;\nif (x >= y) return;\n");
+
+        let (had_match, mutants) = mutate_line(&lines, 0, &operators);
+        assert!(!had_match && mutants.is_empty());
+    }
+
+    #[test]
+    fn test_mutate_source() {
+        let source = "int a = 1;\r\nif (x > y)\r\n    return;\r\n// x > y\r\n";
+        let operators = vec![
+            MutationOperator::new(r"x > y", "x >= y").unwrap(),
+            MutationOperator::new(r"x > y", "x >= y").unwrap(),
+            MutationOperator::new(r"a = 1", "a = 0").unwrap(),
+        ];
+
+        // Comments are skipped and identical mutants only returned once.
+        let mutants = mutate_source(source, &operators, &SourceOptions::default())
+            .unwrap()
+            .mutants;
+        assert_eq!(mutants.len(), 2);
+        assert_eq!((mutants[0].line, mutants[1].line), (1, 2));
+        assert_eq!(mutants[1].operator, "x > y ==> x >= y");
+        assert_eq!(
+            apply(&mutants[1], source),
+            "int a = 1;\r\nif (x >= y)\r\n    return;\r\n// x > y\r\n"
+        );
+
+        let options = SourceOptions {
+            lines: Some(vec![1, 2]),
+            skip_lines: vec![1],
+            ..Default::default()
+        };
+        let mutants = mutate_source(source, &operators, &options).unwrap().mutants;
+        assert_eq!(mutants.len(), 1);
+        assert_eq!(mutants[0].line, 2);
+
+        let options = SourceOptions {
+            exclude_lines_matching: vec![Regex::new(r"^if\s*\(").unwrap()],
+            ..Default::default()
+        };
+        let mutants = mutate_source(source, &operators, &options).unwrap().mutants;
+        assert_eq!(mutants.len(), 1);
+        assert_eq!(mutants[0].line, 1);
+
+        // Code that is never compiled is not mutated.
+        let source = "#if 0\nint a = 1;\n#endif\nif (x > y)\n";
+        let mutants = mutate_source(source, &operators, &SourceOptions::default())
+            .unwrap()
+            .mutants;
+        assert_eq!(mutants.len(), 1);
+        assert_eq!(mutants[0].line, 4);
+    }
+
+    #[test]
+    fn test_max_mutants_truncation() {
+        let source = "int a = 1;\nif (x > y)\n";
+        let operators = vec![
+            MutationOperator::new(r"x > y", "x >= y").unwrap(),
+            MutationOperator::new(r"a = 1", "a = 0").unwrap(),
+        ];
+        let generate = |max_mutants| {
+            let options = SourceOptions {
+                max_mutants: Some(max_mutants),
+                ..Default::default()
+            };
+            mutate_source(source, &operators, &options).unwrap()
+        };
+
+        // A cap equal to the number of mutants cuts nothing off.
+        let generated = generate(2);
+        assert_eq!(generated.mutants.len(), 2);
+        assert!(!generated.truncated);
+
+        let generated = generate(1);
+        assert_eq!(generated.mutants.len(), 1);
+        assert!(generated.truncated);
+    }
+
+    #[test]
+    fn test_one_mutant_follows_priority() {
+        let source = "if (x > y) ok = true;\n";
+        let mut operators = OperatorMode::Regex.operators().unwrap();
+        let options = SourceOptions {
+            one_mutant: true,
+            ..Default::default()
+        };
+
+        // `true→false` is listed first, but the boundary flip ranks higher.
+        let mutants = mutate_source(source, &operators, &options).unwrap().mutants;
+        assert_eq!(mutants.len(), 1);
+        assert_eq!(mutants[0].replacement, "if (x >= y) ok = true;");
+
+        override_priorities(&mut operators, &[("boolean true→false".to_string(), 0)]);
+        let mutants = mutate_source(source, &operators, &options).unwrap().mutants;
+        assert_eq!(mutants[0].replacement, "if (x > y) ok = false;");
+    }
+
+    #[test]
+    fn test_preview_line() {
+        let source = SourceFile::new("if (a &&\n    b) {\n    v.erase(it);\n");
+        let mutant = |line, end_line, operator: &str, replacement: &str| Mutant {
+            line,
+            end_line,
+            operator: operator.to_string(),
+            replacement: replacement.to_string(),
+        };
+        assert_eq!(
+            preview_line(&source, &mutant(1, 2, "&& ==> ||", "if (a ||\n    b) {")),
+            "    1 : && ==> || : if (a && b) { -> if (a || b) {"
+        );
+        assert_eq!(
+            preview_line(&source, &mutant(3, 3, r".*\berase\(.+ ==> ", "")),
+            r"    3 : .*\berase\(.+ ==>  : v.erase(it); -> <deleted>"
+        );
+    }
+
+    #[test]
+    fn test_source_file_line_endings() {
+        let mutant = |source: &str, edit: Mutant| apply(&edit, source);
+        let edit = |start: usize, end: usize, replacement: &str| Mutant {
+            line: start + 1,
+            end_line: end + 1,
+            operator: String::new(),
+            replacement: replacement.to_string(),
+        };
+
+        let crlf = "int a = 1;\r\nif (x > y)\r\n    return;\r\n";
+        assert_eq!(
+            mutant(crlf, edit(1, 1, "if (x >= y)")),
+            "int a = 1;\r\nif (x >= y)\r\n    return;\r\n"
+        );
+        assert_eq!(
+            mutant(crlf, edit(1, 2, "if (x > y)\n    {}")),
+            "int a = 1;\r\nif (x > y)\r\n    {}\r\n"
+        );
+
+        let no_final_newline = "int a = 1;\nreturn a;";
+        assert_eq!(
+            mutant(no_final_newline, edit(0, 0, "int a = 0;")),
+            "int a = 0;\nreturn a;"
+        );
+        assert_eq!(mutant("a;\n\n", edit(0, 0, "b;")), "b;\n\n");
+
+        // Mixed line endings: every line keeps its own.
+        let mixed = "int a = 1;\nif (x > y)\r\n    return;\n";
+        assert_eq!(
+            mutant(mixed, edit(1, 1, "if (x >= y)")),
+            "int a = 1;\nif (x >= y)\r\n    return;\n"
+        );
+        assert_eq!(
+            mutant(mixed, edit(0, 0, "int a = 0;")),
+            "int a = 0;\nif (x > y)\r\n    return;\n"
+        );
+    }
+
     #[test]
     fn test_write_mutation() {
         let temp_dir = tempdir().unwrap();
-        std::env::set_current_dir(&temp_dir).unwrap();
+        let _cwd = enter_dir(temp_dir.path());
 
-        let result = write_mutation("test.cpp", "mutated content", 0, None, None).unwrap();
+        let write =
+            |contents: &'static str| move |out: &mut dyn Write| out.write_all(contents.as_bytes());
+        let folder = mutant_folder("test.cpp", None, None, None).unwrap();
+        let result =
+            write_mutation(&folder, "test.cpp", 0, false, write("mutated content")).unwrap();
         assert_eq!(result, 1);
 
         let folder_path = Path::new("muts-test-cpp");
@@ -562,5 +2226,122 @@ mod tests {
 
         let content = fs::read_to_string(folder_path.join("test.mutant.0.cpp")).unwrap();
         assert_eq!(content, "mutated content");
+
+        // Other C/C++ extensions are preserved rather than rewritten to .cpp.
+        let folder = mutant_folder("db.cc", None, None, None).unwrap();
+        write_mutation(&folder, "db.cc", 0, false, write("mutated content")).unwrap();
+        assert!(Path::new("muts-db-cc/db.mutant.0.cc").exists());
+
+        // Patch mode keeps the folder and swaps the mutant extension.
+        write_mutation(&folder, "db.cc", 1, true, write("@@ diff")).unwrap();
+        assert!(Path::new("muts-db-cc/db.mutant.1.patch").exists());
+    }
+
+    #[test]
+    fn test_mutant_folder_name() {
+        let name = |pr, range| mutant_folder_name("src/wallet/wallet.cpp", pr, range).unwrap();
+        assert_eq!(name(None, None), "muts-wallet-wallet-cpp");
+        assert_eq!(name(Some(123), None), "muts-pr-123-wallet-wallet-cpp");
+        assert_eq!(name(None, Some((10, 20))), "muts-wallet-wallet-10-20-cpp");
+        // The header and the source of a range run get different folders.
+        assert_ne!(
+            mutant_folder_name("src/wallet/wallet.h", None, Some((10, 20))).unwrap(),
+            name(None, Some((10, 20)))
+        );
+
+        assert_eq!(
+            mutant_folder("src/wallet/wallet.cpp", None, None, Some(Path::new("out"))).unwrap(),
+            Path::new("out/muts-wallet-wallet-cpp")
+        );
+    }
+
+    #[test]
+    fn test_generated_file() {
+        let temp_dir = tempdir().unwrap();
+        let markers = MutationOptions::default().generated_markers;
+        let write = |name: &str, contents: &str| {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        };
+
+        let seeds = write(
+            "chainparamsseeds.h",
+            "#ifndef SEEDS_H\n/**\n * AUTOGENERATED by contrib/seeds/generate-seeds.py\n */\n",
+        );
+        let reason = generated_file(&seeds, None, &markers).unwrap();
+        assert!(reason.contains("AUTOGENERATED"), "{}", reason);
+        // The markers can be replaced, or disabled with an empty one.
+        assert!(generated_file(&seeds, None, &["".to_string()]).is_none());
+
+        let source = write(
+            "wallet.cpp",
+            "int f() {\n    return 1; // generated later\n}\n",
+        );
+        assert!(generated_file(&source, None, &markers).is_none());
+        let reason = generated_file(&source, Some(10), &markers).unwrap();
+        assert!(reason.contains("over --max-file-bytes 10"), "{}", reason);
+        assert!(generated_file(&source, Some(1000), &markers).is_none());
+
+        // Only the first lines are searched.
+        let late = write(
+            "late.cpp",
+            &format!("{}// DO NOT EDIT\n", "int x;\n".repeat(10)),
+        );
+        assert!(generated_file(&late, None, &markers).is_none());
+        assert!(generated_file(&temp_dir.path().join("missing.cpp"), Some(0), &markers).is_none());
+    }
+
+    #[test]
+    fn test_own_artifact() {
+        let db = Some(Path::new("results/runs.db"));
+        assert_eq!(
+            own_artifact(
+                Path::new("./muts-pr-1-validation-cpp/validation.mutant.0.cpp"),
+                None
+            ),
+            Some("is inside a mutants folder")
+        );
+        assert_eq!(
+            own_artifact(Path::new("db/mutation.sql"), None),
+            Some("is inside the db directory")
+        );
+        assert_eq!(
+            own_artifact(Path::new("diff_not_killed.json"), None),
+            Some("is a mutation report")
+        );
+        assert_eq!(
+            own_artifact(Path::new("out/mutation-junit.xml"), None),
+            Some("is a mutation report")
+        );
+        assert_eq!(
+            own_artifact(Path::new("./results/runs.db"), db),
+            Some("is the SQLite database")
+        );
+        assert_eq!(
+            own_artifact(Path::new("mutation.db"), None),
+            Some("is the SQLite database")
+        );
+
+        // Sources that merely look similar are fine.
+        for file in [
+            "src/leveldb/db/db_impl.cc",
+            "src/wallet/db.cpp",
+            "src/muts-helper.cpp",
+            "test/functional/data/rpc_psbt.json",
+        ] {
+            assert_eq!(own_artifact(Path::new(file), db), None, "{}", file);
+        }
+    }
+
+    #[test]
+    fn test_mutant_extension() {
+        assert_eq!(mutant_extension("src/leveldb/db/db_impl.cc"), "cc");
+        assert_eq!(mutant_extension("src/secp256k1/src/secp256k1.c"), "c");
+        assert_eq!(mutant_extension("src/util/result.hpp"), "hpp");
+        assert_eq!(mutant_extension("test/functional/feature_block.py"), "py");
+        assert_eq!(mutant_extension("src/README"), "cpp");
+        assert!(is_c_family_source("src/crypto/sha256_impl.ipp"));
+        assert!(!is_c_family_source("test/functional/feature_block.py"));
     }
 }
@@ -1,68 +1,540 @@
-use regex::Regex;
+use crate::ast_analysis::has_declaration_shape;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// A predicate on the text an operator may mutate, with its name.
+pub type TextFilter = (&'static str, fn(&str) -> bool);
+
 #[derive(Debug, Clone)]
 pub struct MutationOperator {
     pub pattern: Regex,
     pub replacement: String,
+    /// The operator is not applied to text matching this guard.
+    pub skip_if: Option<Regex>,
+    /// Matches of `pattern` overlapping a match of this are left alone, e.g.
+    /// in string literals, while the rest of the text is still mutated.
+    pub skip_within: Option<Regex>,
+    /// The operator is only applied to text this predicate, named for
+    /// `list-operators`, accepts.
+    pub only_if: Option<TextFilter>,
+    /// What the operator does, for reviewers of its survivors (e.g. `logical AND→OR`).
+    pub description: String,
+    /// With `--one-mutant`, a line's mutant comes from the matching operator
+    /// with the lowest priority; ties go to the first one listed.
+    pub priority: u8,
 }
 
 impl MutationOperator {
+    /// An operator described by its name until [`MutationOperator::described`],
+    /// with the lowest `--one-mutant` priority.
     pub fn new(pattern: &str, replacement: &str) -> Result<Self, regex::Error> {
         Ok(MutationOperator {
             pattern: Regex::new(pattern)?,
             replacement: replacement.to_string(),
+            skip_if: None,
+            skip_within: None,
+            only_if: None,
+            description: format!("{} ==> {}", pattern, replacement),
+            priority: OTHER,
+        })
+    }
+
+    /// The same operator, described as `description`.
+    pub fn described(self, description: &str) -> Self {
+        MutationOperator {
+            description: description.to_string(),
+            ..self
+        }
+    }
+
+    /// The same operator, with `--one-mutant` priority `priority`.
+    pub fn with_priority(self, priority: u8) -> Self {
+        MutationOperator { priority, ..self }
+    }
+
+    /// The same operator, not applied to text matching `skip_if`.
+    pub fn guarded(self, skip_if: &str) -> Result<Self, regex::Error> {
+        Ok(MutationOperator {
+            skip_if: Some(Regex::new(skip_if)?),
+            ..self
+        })
+    }
+
+    /// The same operator, leaving its matches inside matches of `skip_within`
+    /// alone.
+    pub fn skipping_within(self, skip_within: &str) -> Result<Self, regex::Error> {
+        Ok(MutationOperator {
+            skip_within: Some(Regex::new(skip_within)?),
+            ..self
         })
     }
+
+    /// The same operator, only applied to text `predicate` accepts.
+    pub fn only_if(self, name: &'static str, predicate: fn(&str) -> bool) -> Self {
+        MutationOperator {
+            only_if: Some((name, predicate)),
+            ..self
+        }
+    }
+
+    /// Whether the operator can mutate `text`.
+    pub fn applies_to(&self, text: &str) -> bool {
+        !self.skip_if.as_ref().is_some_and(|g| g.is_match(text))
+            && self.only_if.is_none_or(|(_, predicate)| predicate(text))
+            && self.first_match(text).is_some()
+    }
+
+    /// The first match of `pattern` in `text` outside the `skip_within` spans.
+    pub fn first_match<'t>(&self, text: &'t str) -> Option<Captures<'t>> {
+        let Some(ref skip_within) = self.skip_within else {
+            return self.pattern.captures(text);
+        };
+        let skipped: Vec<_> = skip_within.find_iter(text).map(|m| m.range()).collect();
+        self.pattern.captures_iter(text).find(|captures| {
+            let matched = captures.get(0).unwrap().range();
+            !skipped
+                .iter()
+                .any(|span| matched.start < span.end && span.start < matched.end)
+        })
+    }
+
+    /// `text` with the replacement applied to its first match outside the
+    /// `skip_within` spans, or unchanged.
+    pub fn mutate<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        let Some(captures) = self.first_match(text) else {
+            return Cow::Borrowed(text);
+        };
+        let matched = captures.get(0).unwrap();
+        let mut mutated = text[..matched.start()].to_string();
+        captures.expand(&self.replacement, &mut mutated);
+        mutated.push_str(&text[matched.end()..]);
+        Cow::Owned(mutated)
+    }
+
+    /// How the operator is recorded with its mutants (`pattern ==> replacement`).
+    pub fn name(&self) -> String {
+        format!("{} ==> {}", self.pattern.as_str(), self.replacement)
+    }
+
+    /// Whether `name` is the operator's name or its description.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name() == name || self.description == name
+    }
+}
+
+/// Check that `name` is the name of a built-in operator, or the description of
+/// exactly one. The error lists the valid names, or those of the operators an
+/// ambiguous description matches.
+pub fn check_operator_name(name: &str) -> Result<(), String> {
+    let mut names = Vec::new();
+    let mut described = Vec::new();
+    for mode in OperatorMode::ALL {
+        for operator in mode.operators().map_err(|e| e.to_string())? {
+            if operator.name() == name {
+                return Ok(());
+            }
+            if operator.description == name {
+                described.push(operator.name());
+            }
+            names.push(operator.name());
+        }
+    }
+    described.sort();
+    described.dedup();
+    match described.len() {
+        1 => Ok(()),
+        0 => {
+            names.sort();
+            names.dedup();
+            Err(format!(
+                "unknown operator '{}'; valid operators (see list-operators):\n  {}",
+                name,
+                names.join("\n  ")
+            ))
+        }
+        _ => Err(format!(
+            "operator description '{}' is ambiguous; use one of these names:\n  {}",
+            name,
+            described.join("\n  ")
+        )),
+    }
+}
+
+/// The description of the built-in operator recorded as `name`
+/// (`pattern ==> replacement`).
+pub fn describe_operator(name: &str) -> Option<String> {
+    static DESCRIPTIONS: OnceLock<HashMap<String, String>> = OnceLock::new();
+    DESCRIPTIONS
+        .get_or_init(|| {
+            let mut descriptions = HashMap::new();
+            for mode in OperatorMode::ALL {
+                for operator in mode.operators().unwrap_or_default() {
+                    descriptions
+                        .entry(operator.name())
+                        .or_insert(operator.description);
+                }
+            }
+            descriptions
+        })
+        .get(name)
+        .cloned()
+}
+
+/// `--one-mutant` priorities, lowest first. Relational flips (boundaries first,
+/// as listed) make the most meaningful single mutant of a line, then logical,
+/// condition and control flow changes; whitespace-sensitive arithmetic swaps
+/// and call removals come last.
+const RELATIONAL: u8 = 0;
+const LOGICAL: u8 = 1;
+const CONDITION: u8 = 2;
+const CONTROL_FLOW: u8 = 3;
+const ALGORITHM: u8 = 4;
+const ARITHMETIC: u8 = 5;
+const OTHER: u8 = 6;
+
+/// Parse a `--operator-priority` value, `NAME=PRIORITY`.
+pub fn parse_operator_priority(value: &str) -> Result<(String, u8), String> {
+    let (name, priority) = value
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected NAME=PRIORITY, got '{}'", value))?;
+    let priority = priority
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid priority '{}': {}", priority, e))?;
+    check_operator_name(name)?;
+    Ok((name.to_string(), priority))
+}
+
+/// Give the operators named `name` (see [`MutationOperator::is_named`]) the
+/// priorities of `priorities`, in order.
+pub fn override_priorities(operators: &mut [MutationOperator], priorities: &[(String, u8)]) {
+    for (name, priority) in priorities {
+        for operator in operators.iter_mut().filter(|op| op.is_named(name)) {
+            operator.priority = *priority;
+        }
+    }
+}
+
+/// A set of operators, as selected by `mutate_file` and listed by the
+/// `list-operators` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorMode {
+    Regex,
+    Security,
+    Test,
+    /// Applied to windows of lines on top of `Regex`, with `--multiline-window`.
+    Multiline,
+    /// Added to `Regex` with `--operator-categories qualifier`.
+    Qualifier,
+    /// Added to `Regex` with `--operator-categories return`.
+    Return,
+}
+
+impl OperatorMode {
+    pub const ALL: [OperatorMode; 6] = [
+        OperatorMode::Regex,
+        OperatorMode::Security,
+        OperatorMode::Test,
+        OperatorMode::Multiline,
+        OperatorMode::Qualifier,
+        OperatorMode::Return,
+    ];
+
+    /// The set used for `file_path`: security operators when only security
+    /// mutations are wanted, test operators for Python and unit test files,
+    /// regex operators otherwise.
+    pub fn for_file(file_path: &str, is_unit_test: bool, only_security: bool) -> Self {
+        if only_security {
+            OperatorMode::Security
+        } else if file_path.contains(".py") || is_unit_test {
+            OperatorMode::Test
+        } else {
+            OperatorMode::Regex
+        }
+    }
+
+    pub fn operators(self) -> Result<Vec<MutationOperator>, regex::Error> {
+        match self {
+            OperatorMode::Regex => get_regex_operators(),
+            OperatorMode::Security => get_security_operators(),
+            OperatorMode::Test => get_test_operators(),
+            OperatorMode::Multiline => get_multiline_operators(),
+            OperatorMode::Qualifier => get_qualifier_operators(),
+            OperatorMode::Return => get_return_operators(),
+        }
+    }
+}
+
+impl FromStr for OperatorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "regex" => Ok(OperatorMode::Regex),
+            "security" => Ok(OperatorMode::Security),
+            "test" => Ok(OperatorMode::Test),
+            "multiline" => Ok(OperatorMode::Multiline),
+            "qualifier" => Ok(OperatorMode::Qualifier),
+            "return" => Ok(OperatorMode::Return),
+            other => Err(format!(
+                "unknown operator mode '{}' (expected regex, security, test, multiline, qualifier or return)",
+                other
+            )),
+        }
+    }
+}
+
+/// Noisy operators left out of the `Regex` set unless their category is
+/// passed to `--operator-categories`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorCategory {
+    /// Strip `const` from variable declarations.
+    Qualifier,
+    /// Replace returned values with `return {};`.
+    Return,
+}
+
+impl OperatorCategory {
+    pub fn operators(self) -> Result<Vec<MutationOperator>, regex::Error> {
+        match self {
+            OperatorCategory::Qualifier => OperatorMode::Qualifier.operators(),
+            OperatorCategory::Return => OperatorMode::Return.operators(),
+        }
+    }
+}
+
+impl FromStr for OperatorCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "qualifier" => Ok(OperatorCategory::Qualifier),
+            "return" => Ok(OperatorCategory::Return),
+            other => Err(format!(
+                "unknown operator category '{}' (expected qualifier or return)",
+                other
+            )),
+        }
+    }
+}
+
+/// Pattern of a binary arithmetic operator `$op` between two operands, with or
+/// without spaces: an identifier, a number or a closing bracket on the left,
+/// an identifier, a number or an opening parenthesis on the right. Leaves
+/// `->`, `--`, `+=`, `//`, unary signs and exponents (`1e-5`) alone. Replaced
+/// with `$1$2<op>$3$4` to keep the spacing.
+macro_rules! binary_operator {
+    ($op:literal) => {
+        concat!(
+            r"(\b[A-Za-z_]\w*|\b(?:0[xX][\da-fA-F']+|\d[\d.']*)[uUlLfF]*\b|[)\]])(\s*)",
+            $op,
+            r"(\s*)([\w(])"
+        )
+    };
 }
 
 pub fn get_regex_operators() -> Result<Vec<MutationOperator>, regex::Error> {
     let operators = vec![
-        (r"--(\b\w+\b)", r"++$1"),
-        (r"(\b\w+\b)--", r"$1++"),
+        (
+            r"--(\b\w+\b)",
+            r"++$1",
+            "pre-decrement→pre-increment",
+            OTHER,
+        ),
+        (
+            r"(\b\w+\b)--",
+            r"$1++",
+            "post-decrement→post-increment",
+            OTHER,
+        ),
         //(r"CAmount\s+(\w+)\s*=\s*([0-9]+)", r"CAmount $1 = $2 + 1"),
         //(r"CAmount\s+(\w+)\s*=\s*([0-9]+)", r"CAmount $1 = $2 - 1"),
-        ("Misbehaving", "//Misbehaving"),
-        ("continue", "break"),
-        ("break", "continue"),
-        ("std::all_of", "std::any_of"),
-        ("std::any_of", "std::all_of"),
-        ("std::min", "std::max"),
-        ("std::max", "std::min"),
-        ("std::begin", "std::end"),
-        ("std::end", "std::begin"),
-        ("true", "false"),
-        ("false", "true"),
-        (r" / ", " * "),
+        (
+            "Misbehaving",
+            "//Misbehaving",
+            "comment out Misbehaving",
+            OTHER,
+        ),
+        (
+            "continue",
+            "break",
+            "loop control continue→break",
+            CONTROL_FLOW,
+        ),
+        (
+            "break",
+            "continue",
+            "loop control break→continue",
+            CONTROL_FLOW,
+        ),
+        (
+            "std::all_of",
+            "std::any_of",
+            "algorithm std::all_of→std::any_of",
+            ALGORITHM,
+        ),
+        (
+            "std::any_of",
+            "std::all_of",
+            "algorithm std::any_of→std::all_of",
+            ALGORITHM,
+        ),
+        (
+            "std::min",
+            "std::max",
+            "algorithm std::min→std::max",
+            ALGORITHM,
+        ),
+        (
+            "std::max",
+            "std::min",
+            "algorithm std::max→std::min",
+            ALGORITHM,
+        ),
+        (
+            "std::begin",
+            "std::end",
+            "algorithm std::begin→std::end",
+            ALGORITHM,
+        ),
+        (
+            "std::end",
+            "std::begin",
+            "algorithm std::end→std::begin",
+            ALGORITHM,
+        ),
+        ("true", "false", "boolean true→false", CONDITION),
+        ("false", "true", "boolean false→true", CONDITION),
+        (
+            binary_operator!("/"),
+            "$1$2*$3$4",
+            "arithmetic /→*",
+            ARITHMETIC,
+        ),
+        // Loop bounds: only the condition clause of a `for` header
+        (
+            r"(\bfor\s*\([^;]*;[^;]*?) < ",
+            "$1 <= ",
+            "loop bound <→<=",
+            RELATIONAL,
+        ),
+        (
+            r"(\bfor\s*\([^;]*;[^;]*?) <= ",
+            "$1 < ",
+            "loop bound <=→<",
+            RELATIONAL,
+        ),
+        (
+            r"(\bfor\s*\([^;]*;[^;]*?) > ",
+            "$1 >= ",
+            "loop bound >→>=",
+            RELATIONAL,
+        ),
+        (
+            r"(\bfor\s*\([^;]*;[^;]*?) >= ",
+            "$1 > ",
+            "loop bound >=→>",
+            RELATIONAL,
+        ),
         // Boundary (off-by-one) mutations first — hardest to kill
-        (r" >= ", " > "),
-        (r" <= ", " < "),
-        (r" > ", " >= "),
-        (r" < ", " <= "),
+        (r" >= ", " > ", "relational >=→>", RELATIONAL),
+        (r" <= ", " < ", "relational <=→<", RELATIONAL),
+        (r" > ", " >= ", "relational >→>=", RELATIONAL),
+        (r" < ", " <= ", "relational <→<=", RELATIONAL),
         // Direction flips — easier to detect
-        (r" >= ", " <= "),
-        (r" <= ", " >= "),
-        (r" > ", " < "),
-        (r" < ", " > "),
+        (r" >= ", " <= ", "relational >=→<=", RELATIONAL),
+        (r" <= ", " >= ", "relational <=→>=", RELATIONAL),
+        (r" > ", " < ", "relational >→<", RELATIONAL),
+        (r" < ", " > ", "relational <→>", RELATIONAL),
         // Cross-boundary
-        (r" > ", " <= "),
-        (r" < ", " >= "),
-        (r"&&", "||"),
-        (r"\|\|", "&&"),
-        (r" == ", " != "),
-        (r" != ", " == "),
-        (" - ", " + "),
-        (r" \+ ", " - "),
-        (r" \+ ", " * "),
-        (r" \+ ", " / "),
-        (r"\((-?\d+)\)", r"($1 - 1)"),
-        (r"\((-?\d+)\)", r"($1 + 1)"),
-        (r"\b(if|else\s+if|while)\s*\(([^()]*)\)", r"$1 (1==1)"),
-        (r"\b(if|else\s+if|while)\s*\(([^()]*)\)", r"$1 (1==0)"),
-        (r".*\berase\(.+", ""),
-        (r"^.*if\s*\(.*\)\s*continue;.*$", ""),
-        (r"^.*if\s*\(.*\)\s*return;.*$", ""),
-        (r"^.*if\s*\(.*\)\s*return.*;.*$", ""),
-        (r"^(.*for\s*\(.*;.*;.*\)\s*\{.*)$", r"$1break;"),
-        (r"^(.*while\s*\(.*\)\s*\{.*)$", r"$1break;"),
+        (r" > ", " <= ", "relational >→<=", RELATIONAL),
+        (r" < ", " >= ", "relational <→>=", RELATIONAL),
+        (r"&&", "||", "logical AND→OR", LOGICAL),
+        (r"\|\|", "&&", "logical OR→AND", LOGICAL),
+        (r" == ", " != ", "relational ==→!=", RELATIONAL),
+        (r" != ", " == ", "relational !=→==", RELATIONAL),
+        (
+            binary_operator!("-"),
+            "$1$2+$3$4",
+            "arithmetic -→+",
+            ARITHMETIC,
+        ),
+        (
+            binary_operator!(r"\+"),
+            "$1$2-$3$4",
+            "arithmetic +→-",
+            ARITHMETIC,
+        ),
+        (
+            binary_operator!(r"\+"),
+            "$1$2*$3$4",
+            "arithmetic +→*",
+            ARITHMETIC,
+        ),
+        (
+            binary_operator!(r"\+"),
+            "$1$2/$3$4",
+            "arithmetic +→/",
+            ARITHMETIC,
+        ),
+        (
+            r"\((-?\d+)\)",
+            r"($1 - 1)",
+            "integer argument minus one",
+            ARITHMETIC,
+        ),
+        (
+            r"\((-?\d+)\)",
+            r"($1 + 1)",
+            "integer argument plus one",
+            ARITHMETIC,
+        ),
+        (
+            r"\b(if|else\s+if|while)\s*\(([^()]*)\)",
+            r"$1 (1==1)",
+            "condition always true",
+            CONDITION,
+        ),
+        (
+            r"\b(if|else\s+if|while)\s*\(([^()]*)\)",
+            r"$1 (1==0)",
+            "condition always false",
+            CONDITION,
+        ),
+        (r".*\berase\(.+", "", "remove erase call", OTHER),
+        (
+            r"^.*if\s*\(.*\)\s*continue;.*$",
+            "",
+            "remove conditional continue",
+            CONTROL_FLOW,
+        ),
+        (
+            r"^.*if\s*\(.*\)\s*return;.*$",
+            "",
+            "remove conditional return",
+            CONTROL_FLOW,
+        ),
+        (
+            r"^.*if\s*\(.*\)\s*return.*;.*$",
+            "",
+            "remove conditional return of a value",
+            CONTROL_FLOW,
+        ),
+        (
+            r"^(.*for\s*\(.*;.*;.*\)\s*\{.*)$",
+            r"$1break;",
+            "break out of for loop",
+            CONTROL_FLOW,
+        ),
+        (
+            r"^(.*while\s*\(.*\)\s*\{.*)$",
+            r"$1break;",
+            "break out of while loop",
+            CONTROL_FLOW,
+        ),
         /* Seems they're unproductive
         (
             r"\b(int64_t|uint64_t|int32_t|uint32_t)\s+(\w+)\s*=\s*(.*?);$",
@@ -86,61 +558,231 @@ pub fn get_regex_operators() -> Result<Vec<MutationOperator>, regex::Error> {
 
     operators
         .into_iter()
-        .map(|(pattern, replacement)| MutationOperator::new(pattern, replacement))
+        .map(|(pattern, replacement, description, priority)| {
+            let operator = MutationOperator::new(pattern, replacement)?
+                .described(description)
+                .with_priority(priority);
+            match pattern {
+                "true" | "false" => operator.guarded(CONSTANT_DECLARATION),
+                _ if replacement.starts_with("$1$2") => {
+                    operator.skipping_within(ARITHMETIC_CONTEXT)
+                }
+                _ => Ok(operator),
+            }
+        })
         .collect()
 }
 
+/// `const`/`constexpr` declarations, e.g. `static constexpr bool X = true;`:
+/// flipping their value mostly yields mutants that do not compile or are
+/// equivalent, and they crowd the survivors of header files.
+const CONSTANT_DECLARATION: &str = r"^\s*(?:(?:static|inline)\s+)*(?:constexpr|const)\b[^=;(]*=";
+
+/// Returns of a value `return {};` already gives for the usual return types:
+/// replacing them yields equivalent mutants.
+const DEFAULT_RETURN: &str =
+    r#"\breturn\s+(?:false|0|0\.0|nullptr|NULL|std::nullopt|""|std::string\(\))\s*;"#;
+
+/// String literals and template arguments holding an arithmetic expression,
+/// e.g. `"a-b"` or `std::array<uint8_t, SIZE + 1>`: mutating inside them
+/// yields equivalent mutants or code that does not compile.
+const ARITHMETIC_CONTEXT: &str =
+    r#""[^"]*[\w)]\s*[-+*/]\s*[\w(][^"]*"|\w<[^<>;()]*[\w)]\s*[-+*/]\s*[\w(][^<>;()]*>"#;
+
+/// Let the `true`/`false` operators mutate `const`/`constexpr` declarations.
+pub fn mutate_constant_declarations(operators: &mut [MutationOperator]) {
+    for operator in operators {
+        if operator
+            .skip_if
+            .as_ref()
+            .is_some_and(|g| g.as_str() == CONSTANT_DECLARATION)
+        {
+            operator.skip_if = None;
+        }
+    }
+}
+
+/// Lines where `+`/`-` likely is pointer or iterator arithmetic, or sits in
+/// template arguments: swapping the operator there mostly yields code that
+/// does not compile.
+const POINTER_ARITHMETIC: &str = r"->|\.(?:c?begin|c?end|data|c_str)\(\)|\b\w*(?:ptr|Ptr|iter|Iter)\b|\bit\b|<[^<>;]*[\w)]\s*[-+]\s*[\w(][^<>;]*>";
+
 pub fn get_security_operators() -> Result<Vec<MutationOperator>, regex::Error> {
+    let arithmetic = vec![
+        MutationOperator::new(binary_operator!("-"), "$1$2+$3$4")?
+            .described("arithmetic -→+")
+            .with_priority(ARITHMETIC)
+            .guarded(POINTER_ARITHMETIC)?,
+        MutationOperator::new(binary_operator!(r"\+"), "$1$2-$3$4")?
+            .described("arithmetic +→-")
+            .with_priority(ARITHMETIC)
+            .guarded(POINTER_ARITHMETIC)?,
+    ];
     let operators = vec![
-        ("==", "="),
-        (r" - ", " + "),
-        (r"\s\+\s", "-"),
+        ("==", "=", "comparison→assignment", OTHER),
         (
             r"std::array<\s*([\w:]+)\s*,\s*(\d+)\s*>",
             r"std::array<$1, $2 - 2>",
+            "shrink std::array by two",
+            OTHER,
         ),
         (
             r"\b((?:int16_t|uint16_t|int32_t|uint32_t|int64_t|uint64_t|int)\s*[\(\{])([^\)\}]*)[\)\}]",
             "$2",
+            "drop integer cast",
+            OTHER,
+        ),
+        (
+            r"ignore\((\s*(\d+)\s*)\)",
+            r"ignore($2 + 100)",
+            "ignore 100 more bytes",
+            OTHER,
+        ),
+        (
+            r"(\w+)\[(\w+)\]",
+            r"$1[$2 + 5]",
+            "index out of bounds",
+            OTHER,
         ),
-        (r"ignore\((\s*(\d+)\s*)\)", r"ignore($2 + 100)"),
-        (r"(\w+)\[(\w+)\]", r"$1[$2 + 5]"),
         (
             r"^\s*(?:\(void\)\s*)?[a-zA-Z_][\w:]*\s*\([\w\s,]*\)\s*;\s*$",
             "",
+            "remove function call",
+            OTHER,
+        ),
+        (
+            r"if\s*\(\s*(.*?)\s*\|\|\s*(.*?)\s*\)",
+            r"if($2||$1)",
+            "swap OR operands",
+            OTHER,
         ),
-        (r"if\s*\(\s*(.*?)\s*\|\|\s*(.*?)\s*\)", r"if($2||$1)"),
         (
             r"GetSelectionAmount\(\)",
             r"GetSelectionAmount() + std::numeric_limits<CAmount>::max() - 1",
+            "overflow selection amount",
+            OTHER,
         ),
-        (r"resetBlock\(\);", ""),
+        (r"resetBlock\(\);", "", "remove resetBlock();", OTHER),
         (
             r"\w+(\.|->)GetMedianTimePast\(\)",
             "std::numeric_limits<int64_t>::max()",
+            "median time past→max",
+            OTHER,
         ),
-        ("break", ""),
+        ("break", "", "remove break", OTHER),
     ];
 
-    operators
+    let mut operators = operators
         .into_iter()
-        .map(|(pattern, replacement)| MutationOperator::new(pattern, replacement))
-        .collect()
+        .map(|(pattern, replacement, description, priority)| {
+            Ok(MutationOperator::new(pattern, replacement)?
+                .described(description)
+                .with_priority(priority))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    operators.splice(1..1, arithmetic);
+    Ok(operators)
+}
+
+/// Arguments of a call: parentheses with up to three levels of balanced
+/// parentheses inside, e.g. `(tx.serialize().hex())`. `regex` cannot match
+/// arbitrary nesting.
+macro_rules! call_arguments {
+    () => {
+        r"\((?:[^()]|\((?:[^()]|\((?:[^()]|\([^()]*\))*\))*\))*\)"
+    };
 }
 
+/// A line that is only a function call, possibly at the end of a chain of
+/// members, indexes and calls, e.g. `self.nodes[0].sendrawtransaction(tx.serialize().hex())`.
+/// The called function is captured.
+const TEST_CALL_PATTERN: &str = concat!(
+    r"^\s*(?:\w+(?:\[[^\]]*\]|",
+    call_arguments!(),
+    r")*(?:\.|->|::))*(\w+)\s*",
+    call_arguments!(),
+    r"\s*;?\s*$"
+);
+
 pub fn get_test_operators() -> Result<Vec<MutationOperator>, regex::Error> {
     // Instead of using negative lookahead, we'll use a simpler approach
     // This will match function calls but we'll filter out assert functions in the application logic
     let operators = vec![
-        (r"^\s*(?:\w+(?:\.|->|::))*(\w+)\s*\([^)]*\)\s*;?\s*$", ""), // Function calls (will be filtered by skip logic)
+        // Function calls (will be filtered by skip logic)
+        (TEST_CALL_PATTERN, "", "remove function call", OTHER),
     ];
 
     operators
         .into_iter()
-        .map(|(pattern, replacement)| MutationOperator::new(pattern, replacement))
+        .map(|(pattern, replacement, description, priority)| {
+            Ok(MutationOperator::new(pattern, replacement)?
+                .described(description)
+                .with_priority(priority))
+        })
         .collect()
 }
 
+/// Operators matched against a window of consecutive lines joined with `\n`, for
+/// constructs that formatting splits across lines. A match must span a line break.
+pub fn get_multiline_operators() -> Result<Vec<MutationOperator>, regex::Error> {
+    let operators = vec![
+        // Condition broken across lines
+        (
+            r"\b(if|else\s+if|while)\s*\(([^()]*\n[^()]*)\)",
+            r"$1 (1==1)",
+            "multi-line condition always true",
+            CONDITION,
+        ),
+        (
+            r"\b(if|else\s+if|while)\s*\(([^()]*\n[^()]*)\)",
+            r"$1 (1==0)",
+            "multi-line condition always false",
+            CONDITION,
+        ),
+        // Function call statement broken across lines
+        (
+            r"(?m)^\s*(?:\w+(?:\.|->|::))*\w+\s*\([^;{}]*\n[^;{}]*\)\s*;[ \t]*$",
+            "",
+            "remove multi-line function call",
+            OTHER,
+        ),
+    ];
+
+    operators
+        .into_iter()
+        .map(|(pattern, replacement, description, priority)| {
+            Ok(MutationOperator::new(pattern, replacement)?
+                .described(description)
+                .with_priority(priority))
+        })
+        .collect()
+}
+
+/// A leading `const` of a declaration, e.g. `static const int x = ...`.
+const CONST_QUALIFIER: &str = r"^(\s*(?:(?:static|inline|thread_local)\s+)*)const\s+(\w)";
+
+/// Operators testing const-correctness: mutated code that still builds
+/// modifies what it should not. Most mutants fail to build or are
+/// equivalent, so they are opt-in.
+pub fn get_qualifier_operators() -> Result<Vec<MutationOperator>, regex::Error> {
+    Ok(vec![MutationOperator::new(CONST_QUALIFIER, "$1$2")?
+        .described("remove const qualifier")
+        .with_priority(OTHER)
+        .only_if("variable declaration", has_declaration_shape)])
+}
+
+/// Keep the control flow but drop the computed result. Mutants of functions
+/// whose return type has no default fail to build, hence the opt-in.
+pub fn get_return_operators() -> Result<Vec<MutationOperator>, regex::Error> {
+    Ok(vec![MutationOperator::new(
+        r"\breturn\s+[^;{}]+;",
+        "return {};",
+    )?
+    .described("return default value")
+    .with_priority(CONTROL_FLOW)
+    .guarded(DEFAULT_RETURN)?])
+}
+
 pub fn get_do_not_mutate_patterns() -> Vec<&'static str> {
     vec![
         "/",
@@ -219,8 +861,20 @@ pub fn get_do_not_mutate_unit_patterns() -> Vec<&'static str> {
     ]
 }
 
+/// Comment marking a line as not to be mutated, e.g. `x += 1; // bcore-mutation: skip`.
+/// It also matches [`SKIP_NEXT_ANNOTATION`], so the annotated line is skipped too.
+pub const SKIP_ANNOTATION: &str = "bcore-mutation: skip";
+
+/// Comment marking the line after it as not to be mutated.
+pub const SKIP_NEXT_ANNOTATION: &str = "bcore-mutation: skip-next";
+
 pub fn get_skip_if_contain_patterns() -> Vec<&'static str> {
-    vec!["EnableFuzzDeterminism", "nLostUnk", "RPCArg::Type::"]
+    vec![
+        "EnableFuzzDeterminism",
+        "nLostUnk",
+        "RPCArg::Type::",
+        SKIP_ANNOTATION,
+    ]
 }
 
 // Helper function to check if a line should be mutated by test operators
@@ -228,7 +882,8 @@ pub fn get_skip_if_contain_patterns() -> Vec<&'static str> {
 pub fn should_mutate_test_line(line: &str) -> bool {
     let line_trimmed = line.trim();
 
-    // Don't mutate lines that start with assert or other test-specific patterns
+    // Don't mutate lines that start with assert or other test-specific patterns,
+    // or that call one at the end of a chain (e.g. `self.wait_until(...)`)
     let skip_patterns = vec![
         "assert",
         "BOOST_",
@@ -241,14 +896,353 @@ pub fn should_mutate_test_line(line: &str) -> bool {
         "send_and_ping",
     ];
 
-    for pattern in skip_patterns {
+    for pattern in &skip_patterns {
         if line_trimmed.starts_with(pattern) {
             return false;
         }
     }
 
     // Only mutate if it looks like a function call
-    let function_call_pattern =
-        Regex::new(r"^\s*(?:\w+(?:\.|->|::))*(\w+)\s*\([^)]*\)\s*;?\s*$").unwrap();
-    function_call_pattern.is_match(line)
+    let function_call_pattern = Regex::new(TEST_CALL_PATTERN).unwrap();
+    match function_call_pattern.captures(line) {
+        Some(call) => !skip_patterns
+            .iter()
+            .any(|pattern| call[1].starts_with(pattern)),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operator_mode() {
+        assert_eq!(
+            OperatorMode::for_file("src/wallet/wallet.cpp", false, false),
+            OperatorMode::Regex
+        );
+        assert_eq!(
+            OperatorMode::for_file("test/functional/wallet_basic.py", false, false),
+            OperatorMode::Test
+        );
+        assert_eq!(
+            OperatorMode::for_file("src/test/util_tests.cpp", true, true),
+            OperatorMode::Security
+        );
+
+        let mode: OperatorMode = "security".parse().unwrap();
+        let operators = mode.operators().unwrap();
+        assert_eq!(operators.len(), get_security_operators().unwrap().len());
+        assert_eq!(operators[0].name(), "== ==> =");
+        assert!("arith".parse::<OperatorMode>().is_err());
+    }
+
+    #[test]
+    fn test_test_call_lines() {
+        let calls = [
+            "        self.nodes[0].sendrawtransaction(tx.serialize().hex())",
+            "        self.generate(self.nodes[0], 1)",
+            "        node.sendtoaddress(node.getnewaddress(), Decimal('0.1'))",
+            "        self.nodes[1].walletpassphrase(passphrase=self.passphrase, timeout=int(time.time() + 60))",
+            "        node.getwalletinfo()['balance']",
+            "        node.importdescriptors([{'desc': descsum_create(desc), 'timestamp': 'now'}])",
+            "    mempool.clear();",
+            "    node->chainman->ActiveChainstate().ForceFlushStateToDisk();",
+        ];
+        let expected = [true, true, true, true, false, true, true, true];
+        for (line, expected) in calls.iter().zip(expected) {
+            assert_eq!(should_mutate_test_line(line), expected, "{}", line);
+        }
+
+        let pattern = Regex::new(TEST_CALL_PATTERN).unwrap();
+        let called = pattern.captures(calls[0]).unwrap();
+        assert_eq!(&called[1], "sendrawtransaction");
+        let called = pattern.captures(calls[7]).unwrap();
+        assert_eq!(&called[1], "ForceFlushStateToDisk");
+
+        // The skip list still applies to nested calls, and only calls qualify.
+        for line in [
+            "        assert_equal(node.getbalance(), Decimal(str(amount)))",
+            "        self.wait_until(lambda: len(node.getrawmempool()) == 1)",
+            "        txid = node.sendrawtransaction(tx.serialize().hex())",
+            "        self.nodes[0].sendrawtransaction(tx.serialize().hex()) + 1",
+            "        f(a(b(c(d(e())))))",
+        ] {
+            assert!(!should_mutate_test_line(line), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_descriptions() {
+        for mode in OperatorMode::ALL {
+            for operator in mode.operators().unwrap() {
+                // Every built-in operator has a description of its own.
+                assert!(!operator.description.contains("==>"), "{}", operator.name());
+                assert_eq!(
+                    describe_operator(&operator.name()),
+                    Some(operator.description)
+                );
+            }
+        }
+
+        // Other operators are described by their name.
+        assert_eq!(
+            MutationOperator::new(r"(\w+)\s*;", "$1")
+                .unwrap()
+                .description,
+            r"(\w+)\s*; ==> $1"
+        );
+        assert_eq!(describe_operator(r"(\w+)\s*; ==> $1"), None);
+        assert_eq!(
+            describe_operator("&& ==> ||").as_deref(),
+            Some("logical AND→OR")
+        );
+        assert_eq!(describe_operator("op"), None);
+    }
+
+    #[test]
+    fn test_priority() {
+        let operators = [OperatorMode::Regex, OperatorMode::Security]
+            .into_iter()
+            .flat_map(|mode| mode.operators().unwrap())
+            .collect::<Vec<_>>();
+        let priority = |description: &str| {
+            operators
+                .iter()
+                .find(|op| op.description == description)
+                .unwrap()
+                .priority
+        };
+        let ranked = [
+            priority("relational >=→>"),
+            priority("loop bound <→<="),
+            priority("logical AND→OR"),
+            priority("condition always true"),
+            priority("loop control continue→break"),
+            priority("algorithm std::min→std::max"),
+            priority("arithmetic +→-"),
+            priority("remove resetBlock();"),
+        ];
+        assert_eq!(ranked, [0, 0, 1, 2, 3, 4, 5, 6]);
+
+        // `--operator-priority` values: the priority follows the last `=`.
+        assert_eq!(
+            parse_operator_priority("relational ==→!==2"),
+            Ok(("relational ==→!=".to_string(), 2))
+        );
+        assert!(parse_operator_priority("logical AND→OR").is_err());
+        assert!(parse_operator_priority("logical AND→OR=-1").is_err());
+        assert!(parse_operator_priority("logical AND→XOR=0")
+            .unwrap_err()
+            .starts_with("unknown operator"));
+    }
+
+    #[test]
+    fn test_check_operator_name() {
+        assert!(check_operator_name("&& ==> ||").is_ok());
+        assert!(check_operator_name("logical AND→OR").is_ok());
+        assert!(check_operator_name("remove const qualifier").is_ok());
+        let err = check_operator_name("&& ==> &").unwrap_err();
+        assert!(err.starts_with("unknown operator '&& ==> &'"));
+        assert!(err.contains("\n  && ==> ||\n"));
+
+        // A description shared by several operators does not pick one.
+        let err = check_operator_name("remove function call").unwrap_err();
+        assert!(err.starts_with("operator description 'remove function call' is ambiguous"));
+        assert_eq!(err.lines().count(), 3);
+        assert!(check_operator_name(&err.lines().nth(1).unwrap()[2..]).is_ok());
+    }
+
+    #[test]
+    fn test_binary_operators() {
+        let operators = get_regex_operators().unwrap();
+        let mutants = |line: &str| {
+            operators
+                .iter()
+                .filter(|op| op.replacement.starts_with("$1$2"))
+                .filter(|op| op.applies_to(line))
+                .map(|op| op.mutate(line).into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(mutants("x = a-b;"), vec!["x = a+b;"]);
+        assert_eq!(mutants("x = a - b;"), vec!["x = a + b;"]);
+        assert_eq!(
+            mutants("n = (x+1)/2;"),
+            vec![
+                "n = (x+1)*2;",
+                "n = (x-1)/2;",
+                "n = (x*1)/2;",
+                "n = (x/1)/2;"
+            ]
+        );
+        assert_eq!(mutants("v[i] - 0x10u"), vec!["v[i] + 0x10u"]);
+        // Only the arithmetic outside string literals and template arguments.
+        assert_eq!(mutants(r#"f("a-b", x - y);"#), vec![r#"f("a-b", x + y);"#]);
+        assert_eq!(
+            mutants("std::array<uint8_t, N + 1> buf{n - 1};"),
+            vec!["std::array<uint8_t, N + 1> buf{n + 1};"]
+        );
+        // Not binary arithmetic.
+        for line in [
+            "p->x = -1;",
+            "i--;",
+            "a += b;",
+            "f(a, -b);",
+            "double d = 1e-5;",
+            "x = a; // b",
+            "s = \"a-b\";",
+            "std::array<uint8_t, SIZE + 1> buf;",
+        ] {
+            assert!(mutants(line).is_empty(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_guard() {
+        let operators = get_security_operators().unwrap();
+        let mutants = |line: &str| {
+            operators
+                .iter()
+                .filter(|op| op.applies_to(line))
+                .map(|op| op.mutate(line).into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(mutants("int x = a - b;"), vec!["int x = a + b;"]);
+        // Pointer and iterator differences, and template arguments, are left alone.
+        assert!(mutants("size_t offset = it - vch.begin();").is_empty());
+        assert!(mutants("const size_t n = pend - pbegin_ptr;").is_empty());
+        assert!(mutants("return pindex->nHeight - 1;").is_empty());
+        assert!(mutants("std::array<uint8_t, SIZE + 1> buf;").is_empty());
+    }
+
+    #[test]
+    fn test_constant_declaration_guard() {
+        let mut operators = get_regex_operators().unwrap();
+        let flips = |operators: &[MutationOperator], line: &str| {
+            operators
+                .iter()
+                .filter(|op| op.pattern.as_str() == "true")
+                .filter(|op| op.applies_to(line))
+                .count()
+        };
+
+        assert_eq!(
+            flips(&operators, "static constexpr bool DEFAULT_X = true;"),
+            0
+        );
+        assert_eq!(
+            flips(&operators, "    const bool fInit{false}, fOk = true;"),
+            0
+        );
+        assert_eq!(flips(&operators, "    bool b = true;"), 1);
+        assert_eq!(flips(&operators, "    return f(true);"), 1);
+
+        mutate_constant_declarations(&mut operators);
+        assert_eq!(
+            flips(&operators, "static constexpr bool DEFAULT_X = true;"),
+            1
+        );
+    }
+
+    #[test]
+    fn test_const_qualifier() {
+        let category: OperatorCategory = "qualifier".parse().unwrap();
+        let operators = category.operators().unwrap();
+        let mutants = |line: &str| -> Vec<String> {
+            operators
+                .iter()
+                .filter(|op| op.applies_to(line))
+                .map(|op| {
+                    op.pattern
+                        .replace(line, op.replacement.as_str())
+                        .into_owned()
+                })
+                .collect()
+        };
+
+        assert_eq!(mutants("    const int x = 5;"), ["    int x = 5;"]);
+        assert_eq!(
+            mutants("static const CAmount MAX{21};"),
+            ["static CAmount MAX{21};"]
+        );
+        assert_eq!(
+            mutants("    const std::string& name = wallet.GetName;"),
+            ["    std::string& name = wallet.GetName;"]
+        );
+        assert!(mutants("    const auto fee = GetFee(tx);").is_empty());
+        assert!(mutants("static constexpr int N = 3;").is_empty());
+        assert!(mutants("bool IsValid(const CTransaction& tx) const").is_empty());
+        assert!(mutants("    const std::vector<CTxOut> outputs").is_empty());
+        assert!("const".parse::<OperatorCategory>().is_err());
+    }
+
+    #[test]
+    fn test_default_return() {
+        let operators = get_return_operators().unwrap();
+        assert!(get_regex_operators()
+            .unwrap()
+            .iter()
+            .all(|op| op.replacement != "return {};"));
+        assert_eq!(
+            "return".parse::<OperatorCategory>().unwrap(),
+            OperatorCategory::Return
+        );
+        let mutants = |line: &str| {
+            operators
+                .iter()
+                .filter(|op| op.replacement == "return {};")
+                .filter(|op| op.applies_to(line))
+                .map(|op| op.mutate(line).into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            mutants("    return nFees + GetDust(tx);"),
+            vec!["    return {};"]
+        );
+        assert_eq!(
+            mutants("if (!res) return util::Error{_(\"bad\")};"),
+            Vec::<String>::new()
+        );
+        assert_eq!(mutants("    return it->second;"), vec!["    return {};"]);
+        // Nothing to drop, or already the default.
+        for line in [
+            "return;",
+            "return {};",
+            "return false;",
+            "return nullptr;",
+            "return 0;",
+        ] {
+            assert!(mutants(line).is_empty(), "{}", line);
+        }
+        assert!(mutants("int returned = 1;").is_empty());
+    }
+
+    #[test]
+    fn test_for_header_bounds() {
+        let operators = get_regex_operators().unwrap();
+        let mutate = |line: &str| -> Vec<String> {
+            operators
+                .iter()
+                .filter(|op| op.pattern.as_str().starts_with(r"(\bfor"))
+                .filter(|op| op.pattern.is_match(line))
+                .map(|op| op.mutate(line).into_owned())
+                .collect()
+        };
+
+        assert_eq!(
+            mutate("for (int i = 0; i < n; ++i) {"),
+            vec!["for (int i = 0; i <= n; ++i) {"]
+        );
+        assert_eq!(
+            mutate("for (size_t i = x < y ? 1 : 0; i >= end; --i)"),
+            vec!["for (size_t i = x < y ? 1 : 0; i > end; --i)"]
+        );
+        // Comparisons outside the condition clause are left alone.
+        assert!(mutate("if (a < b) for (auto& x : xs) {").is_empty());
+        assert!(mutate("for (;;) { if (a > b) break; }").is_empty());
+    }
 }
@@ -0,0 +1,150 @@
+//! Glob-based selection of the changed files that get mutated.
+
+use crate::error::{MutationError, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+/// Gitignore-style file, read from the working directory, listing changed
+/// files not to mutate.
+pub const IGNORE_FILE: &str = ".bcore-mutation-ignore";
+
+/// Rules applied before the ignore file, which can re-include paths with `!`.
+/// Documentation, scripts, fuzz/bench targets and test utilities are not mutated.
+pub const DEFAULT_IGNORE_RULES: &[&str] = &[
+    "doc/",
+    "contrib/",
+    "fuzz/",
+    "bench/",
+    "util/",
+    "sanitizer_suppressions/",
+    "test_framework.py",
+    "*.txt",
+];
+
+/// Vendored subtrees of Bitcoin Core. Their survivors cannot be acted on in a
+/// Bitcoin Core PR, so they are not mutated unless explicitly included.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "src/secp256k1/**",
+    "src/leveldb/**",
+    "src/crc32c/**",
+    "src/minisketch/**",
+];
+
+/// Decides which changed files are mutated. A path matching an `--include`
+/// glob is always kept; otherwise it is dropped if it matches the vendored
+/// subtrees, an `--exclude` glob or the ignore rules.
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    include: GlobSet,
+    exclude: GlobSet,
+    ignore: Gitignore,
+}
+
+impl PathFilter {
+    /// Build a filter; `ignore_file` adds its rules after [`DEFAULT_IGNORE_RULES`].
+    pub fn new(include: &[String], exclude: &[String], ignore_file: Option<&Path>) -> Result<Self> {
+        let excludes = DEFAULT_EXCLUDES
+            .iter()
+            .copied()
+            .chain(exclude.iter().map(String::as_str));
+        Ok(PathFilter {
+            include: build_glob_set(include.iter().map(String::as_str))?,
+            exclude: build_glob_set(excludes)?,
+            ignore: build_ignore_rules(ignore_file)?,
+        })
+    }
+
+    pub fn is_excluded(&self, path: &str) -> bool {
+        let ignored = self
+            .ignore
+            .matched_path_or_any_parents(path, false)
+            .is_ignore();
+        (ignored || self.exclude.is_match(path)) && !self.include.is_match(path)
+    }
+}
+
+fn build_glob_set<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| {
+            MutationError::InvalidInput(format!("Invalid glob '{}': {}", pattern, e))
+        })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| MutationError::InvalidInput(format!("Invalid glob set: {}", e)))
+}
+
+fn build_ignore_rules(ignore_file: Option<&Path>) -> Result<Gitignore> {
+    let invalid =
+        |e: ignore::Error| MutationError::InvalidInput(format!("Invalid ignore rule: {}", e));
+
+    let mut builder = GitignoreBuilder::new("");
+    for rule in DEFAULT_IGNORE_RULES {
+        builder.add_line(None, rule).map_err(invalid)?;
+    }
+    if let Some(path) = ignore_file {
+        if let Some(e) = builder.add(path) {
+            return Err(invalid(e));
+        }
+    }
+    builder.build().map_err(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_path_filter() {
+        let filter = PathFilter::new(&[], &[], None).unwrap();
+        assert!(filter.is_excluded("src/secp256k1/src/secp256k1.c"));
+        assert!(filter.is_excluded("src/leveldb/db/db_impl.cc"));
+        assert!(!filter.is_excluded("src/wallet/wallet.cpp"));
+
+        let filter = PathFilter::new(
+            &["src/leveldb/**".to_string()],
+            &["src/qt/**".to_string()],
+            None,
+        )
+        .unwrap();
+        assert!(!filter.is_excluded("src/leveldb/db/db_impl.cc"));
+        assert!(filter.is_excluded("src/crc32c/src/crc32c.cc"));
+        assert!(filter.is_excluded("src/qt/bitcoin.cpp"));
+
+        assert!(PathFilter::new(&[], &["src/[".to_string()], None).is_err());
+    }
+
+    #[test]
+    fn test_default_ignore_rules() {
+        let filter = PathFilter::new(&[], &[], None).unwrap();
+        assert!(filter.is_excluded("doc/release-notes.md"));
+        assert!(filter.is_excluded("src/test/fuzz/tx_pool.cpp"));
+        assert!(filter.is_excluded("src/bench/checkblock.cpp"));
+        assert!(filter.is_excluded("src/util/strencodings.cpp"));
+        assert!(filter.is_excluded("src/test/util/setup_common.cpp"));
+        assert!(filter.is_excluded("test/functional/test_framework/test_framework.py"));
+        assert!(filter.is_excluded("src/CMakeLists.txt"));
+
+        // Only directories named `util` are skipped, not files containing it.
+        assert!(!filter.is_excluded("src/util.cpp"));
+        assert!(!filter.is_excluded("src/wallet/coinselection.cpp"));
+        assert!(!filter.is_excluded("test/functional/wallet_basic.py"));
+    }
+
+    #[test]
+    fn test_ignore_file() {
+        let temp_dir = tempdir().unwrap();
+        let ignore_file = temp_dir.path().join(IGNORE_FILE);
+        fs::write(&ignore_file, "# GUI code\nsrc/qt/\n!src/util/\n").unwrap();
+
+        let filter = PathFilter::new(&[], &[], Some(&ignore_file)).unwrap();
+        assert!(filter.is_excluded("src/qt/bitcoingui.cpp"));
+        assert!(!filter.is_excluded("src/util/strencodings.cpp"));
+        assert!(filter.is_excluded("src/bench/checkblock.cpp"));
+    }
+}
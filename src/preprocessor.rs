@@ -0,0 +1,123 @@
+//! Tracking of preprocessor conditionals whose code is never compiled, such
+//! as `#if 0` blocks, so that it is not mutated.
+
+use std::collections::HashSet;
+
+/// One `#if`/`#ifdef`/`#ifndef` ... `#endif` block.
+struct Conditional {
+    /// A previous branch is always compiled, so the remaining ones never are.
+    taken: bool,
+    /// The current branch is never compiled.
+    inactive: bool,
+}
+
+/// The (1-indexed) lines inside branches that are never compiled.
+///
+/// Only constant conditions (`0`, `1`, `false`, `true`) are evaluated. Any
+/// other condition depends on the build configuration, so its branches are
+/// treated as compiled. Nested conditionals are tracked with a stack.
+pub fn disabled_lines(lines: &[&str]) -> HashSet<usize> {
+    let mut stack: Vec<Conditional> = Vec::new();
+    let mut disabled = HashSet::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let Some((directive, condition)) = parse_directive(line) else {
+            if stack.iter().any(|c| c.inactive) {
+                disabled.insert(i + 1);
+            }
+            continue;
+        };
+        match directive {
+            "if" => {
+                let value = evaluate(condition);
+                stack.push(Conditional {
+                    taken: value == Some(true),
+                    inactive: value == Some(false),
+                });
+            }
+            "ifdef" | "ifndef" => stack.push(Conditional {
+                taken: false,
+                inactive: false,
+            }),
+            "elif" | "else" => {
+                if let Some(c) = stack.last_mut() {
+                    let value = if directive == "else" {
+                        Some(true)
+                    } else {
+                        evaluate(condition)
+                    };
+                    c.inactive = c.taken || value == Some(false);
+                    c.taken |= value == Some(true);
+                }
+            }
+            "endif" => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    disabled
+}
+
+/// Split a preprocessor line such as `#  elif 0 // old` into its directive and
+/// the rest of the line.
+fn parse_directive(line: &str) -> Option<(&str, &str)> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    Some((&rest[..end], &rest[end..]))
+}
+
+/// The value of a constant `#if`/`#elif` condition, or `None` if it depends
+/// on the build configuration.
+fn evaluate(condition: &str) -> Option<bool> {
+    let condition = condition.split("//").next().unwrap_or_default();
+    let condition = condition.split("/*").next().unwrap_or_default().trim();
+    match condition {
+        "0" | "false" => Some(false),
+        "1" | "true" => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_lines() {
+        let source = "\
+int a;
+#if 0 // unused
+int b;
+#ifdef WIN32
+int c;
+#endif
+#elif defined(FOO)
+int d;
+#else
+int e;
+#endif
+#ifdef ENABLE_WALLET
+int f;
+#elif 0
+int g;
+#else
+int h;
+#endif
+#  if 1
+int i;
+#  else
+int j;
+#  endif
+#if 0 /* disabled */
+#endif";
+        let lines: Vec<&str> = source.lines().collect();
+        let mut disabled: Vec<usize> = disabled_lines(&lines).into_iter().collect();
+        disabled.sort();
+        // b, c, g and j are never compiled.
+        assert_eq!(disabled, vec![3, 5, 15, 22]);
+    }
+}
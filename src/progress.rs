@@ -0,0 +1,65 @@
+//! Progress bar for the analysis loop, drawn below the log output.
+
+use indicatif::{MultiProgress, ProgressBar, ProgressState, ProgressStyle};
+use std::fmt::Write;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Bars are drawn through this, so the logger can print above them.
+pub fn multi_progress() -> &'static MultiProgress {
+    static MULTI: OnceLock<MultiProgress> = OnceLock::new();
+    MULTI.get_or_init(MultiProgress::new)
+}
+
+/// A bar over `len` mutants showing the elapsed time and an ETA, or a hidden
+/// one when `visible` is false. The message is left to the caller.
+pub fn mutant_bar(len: u64, visible: bool) -> ProgressBar {
+    if !visible {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template(
+        "{bar:40} {pos}/{len} mutants, {msg} [{elapsed_precise}, ETA {average_eta}]",
+    )
+    .expect("valid progress template")
+    .with_key("average_eta", |state: &ProgressState, w: &mut dyn Write| {
+        let _ = match average_eta(state.elapsed(), state.pos(), state.len().unwrap_or(0)) {
+            Some(eta) => {
+                let secs = eta.as_secs();
+                write!(
+                    w,
+                    "{:02}:{:02}:{:02}",
+                    secs / 3600,
+                    secs / 60 % 60,
+                    secs % 60
+                )
+            }
+            None => write!(w, "-"),
+        };
+    });
+    let bar = multi_progress().add(ProgressBar::new(len).with_style(style));
+    bar.enable_steady_tick(Duration::from_secs(1));
+    bar
+}
+
+/// Time left for the remaining steps at the average duration of the `done` ones.
+fn average_eta(elapsed: Duration, done: u64, len: u64) -> Option<Duration> {
+    (done > 0).then(|| elapsed.mul_f64(len.saturating_sub(done) as f64 / done as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_average_eta() {
+        assert_eq!(average_eta(Duration::from_secs(30), 0, 10), None);
+        assert_eq!(
+            average_eta(Duration::from_secs(30), 3, 10),
+            Some(Duration::from_secs(70))
+        );
+        assert_eq!(
+            average_eta(Duration::from_secs(30), 10, 10),
+            Some(Duration::ZERO)
+        );
+    }
+}
@@ -1,40 +1,220 @@
+use crate::analyze::{AnalysisOptions, FolderResult};
+use crate::ast_analysis::AridNodeDetector;
+use crate::db::{compute_patch_hash, generate_diff_from_file, Database, GroupColumn};
 use crate::error::{MutationError, Result};
-use chrono::{DateTime, Local};
+use crate::operators::{check_operator_name, describe_operator, OperatorMode};
+use chrono::Local;
+use log::info;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::str::FromStr;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Format of the report written by `analyze`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Surviving mutants appended to `diff_not_killed.json`.
+    #[default]
+    Json,
+    /// Every analyzed mutant as a JUnit `<testcase>` in `mutation-junit.xml`.
+    Junit,
+}
+
+impl ReportFormat {
+    /// File the report is written to when no `--report` path is given.
+    pub fn default_path(self) -> &'static Path {
+        match self {
+            ReportFormat::Json => Path::new("diff_not_killed.json"),
+            ReportFormat::Junit => Path::new("mutation-junit.xml"),
+        }
+    }
+}
+
+/// Whether a report destination means standard output.
+fn is_stdout(destination: &Path) -> bool {
+    destination == Path::new("-")
+}
+
+/// What `save_reports` does with an existing JSON report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportMode {
+    /// Replace it with the reports of this run.
+    #[default]
+    Overwrite,
+    /// Keep its files, replacing those analyzed again at the same commit.
+    Append,
+}
+
+impl FromStr for ReportMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "overwrite" => Ok(ReportMode::Overwrite),
+            "append" => Ok(ReportMode::Append),
+            other => Err(format!(
+                "unknown report mode '{}' (expected overwrite or append)",
+                other
+            )),
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ReportFormat::Json),
+            "junit" => Ok(ReportFormat::Junit),
+            other => Err(format!(
+                "unknown report format '{}' (expected json or junit)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MutantInfo {
     pub id: usize,
     pub commit: String,
     pub diff: String,
+    /// SHA-256 of the mutant's full diff, as stored with it in SQLite; empty in
+    /// reports written before it was recorded.
+    #[serde(default)]
+    pub patch_hash: String,
     pub status: String,
+    /// Operator that produced the mutant; empty when it was not recorded.
+    #[serde(default)]
+    pub operator: String,
+    /// What the operator does (e.g. `logical AND→OR`); empty when it is unknown.
+    #[serde(default)]
+    pub description: String,
+    /// The changed source line before the mutation, without indentation.
+    #[serde(default)]
+    pub original_line: String,
+    /// The changed source line after the mutation, without indentation.
+    #[serde(default)]
+    pub mutated_line: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReportData {
     pub filename: String,
+    /// Commit the file was analyzed at; empty in reports written before it was
+    /// recorded.
+    #[serde(default)]
+    pub commit: String,
     pub mutation_score: f64,
+    /// Mutants counted in the score, and how many of them were killed. Zero in
+    /// reports written before they were recorded.
+    #[serde(default)]
+    pub total_mutants: u64,
+    #[serde(default)]
+    pub killed_mutants: u64,
     pub date: String,
+    /// Surviving mutants by the line they change.
     pub diffs: HashMap<String, Vec<MutantInfo>>,
 }
 
+impl ReportData {
+    /// The report of `filename` at `commit` dated now, without counts.
+    pub fn new(
+        filename: &str,
+        commit: &str,
+        score: f64,
+        diffs: HashMap<String, Vec<MutantInfo>>,
+    ) -> Self {
+        ReportData {
+            filename: filename.to_string(),
+            commit: commit.to_string(),
+            mutation_score: score,
+            total_mutants: 0,
+            killed_mutants: 0,
+            date: Local::now().format("%d/%m/%Y %H:%M:%S").to_string(),
+            diffs,
+        }
+    }
+}
+
+/// The JSON report (`diff_not_killed.json`): the overall score of the
+/// analyzed files, with each file's score and surviving mutants under it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    pub date: String,
+    /// Killed mutants over the mutants counted across all files, from the
+    /// latest report of each file.
+    pub mutation_score: f64,
+    pub total_mutants: u64,
+    pub killed_mutants: u64,
+    pub files: Vec<ReportData>,
+}
+
+impl RunReport {
+    /// The report of `files`, dated now, with the counts of the latest (last
+    /// listed) report of each file added up, so commits are not mixed.
+    pub fn new(files: Vec<ReportData>) -> Self {
+        let mut latest = HashMap::new();
+        for file in &files {
+            latest.insert(file.filename.as_str(), file);
+        }
+        let total_mutants = latest.values().map(|file| file.total_mutants).sum();
+        let killed_mutants = latest.values().map(|file| file.killed_mutants).sum();
+        RunReport {
+            date: Local::now().format("%d/%m/%Y %H:%M:%S").to_string(),
+            mutation_score: if total_mutants > 0 {
+                killed_mutants as f64 / total_mutants as f64
+            } else {
+                0.0
+            },
+            total_mutants,
+            killed_mutants,
+            files,
+        }
+    }
+
+    /// Read the file reports of an existing report. Reports written before
+    /// [`RunReport`] are an array of [`ReportData`], or a single one.
+    fn read_files(path: &Path) -> Result<Vec<ReportData>> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Existing {
+            Run(RunReport),
+            Files(Vec<ReportData>),
+            File(ReportData),
+        }
+
+        let content = fs::read_to_string(path)?;
+        let existing = serde_json::from_str(&content).map_err(|e| {
+            MutationError::InvalidInput(format!("Cannot read {}: {}", path.display(), e))
+        })?;
+        Ok(match existing {
+            Existing::Run(report) => report.files,
+            Existing::Files(files) => files,
+            Existing::File(file) => vec![file],
+        })
+    }
+}
+
+/// Print the surviving mutants of a folder and build their report entry.
+/// Returns `None` when every mutant was killed. The caller saves the collected
+/// reports with [`save_reports`].
 pub async fn generate_report(
     not_killed_mutants: &[String],
     folder: &str,
     original_file: &str,
     score: f64,
-) -> Result<()> {
+) -> Result<Option<ReportData>> {
     // Skip creating a report file if mutation score is 100%
     if not_killed_mutants.is_empty() {
-        return Ok(());
+        return Ok(None);
     }
 
-    let now: DateTime<Local> = Local::now();
     let mut original_file_path = original_file.to_string();
 
     // Adjust path for test files
@@ -47,41 +227,662 @@ pub async fn generate_report(
     // Restore original file
     restore_original_file(&original_file_path).await?;
 
-    println!("Surviving mutants:");
+    info!("Surviving mutants:");
 
     let mut diffs = Vec::new();
 
     // Collect diffs for all surviving mutants
     for filename in not_killed_mutants {
         let modified_file = Path::new(folder).join(filename);
-        let diff_output =
-            get_git_diff(&original_file_path, modified_file.to_str().unwrap()).await?;
+        // Mutants written with `mutate --patch` already are diffs.
+        let diff_output = if modified_file.extension().is_some_and(|ext| ext == "patch") {
+            fs::read_to_string(&modified_file)?
+        } else {
+            generate_diff_from_file(&original_file_path, &modified_file).await?
+        };
 
-        println!("{}", diff_output);
-        println!("--------------");
+        info!("{}", diff_output);
+        info!("--------------");
 
         diffs.push(diff_output);
     }
 
     // Parse diffs and create report
-    let parsed_diffs = parse_diffs_to_json(&diffs).await?;
+    let commit = get_git_hash().await?;
+    let parsed_diffs = parse_diffs_to_json(&diffs, &commit)?;
+    Ok(Some(ReportData::new(
+        &original_file_path,
+        &commit,
+        score,
+        parsed_diffs,
+    )))
+}
 
-    let report_data = ReportData {
-        filename: original_file_path.clone(),
-        mutation_score: score,
-        date: now.format("%d/%m/%Y %H:%M:%S").to_string(),
-        diffs: parsed_diffs,
+/// Return `source` with a `SURVIVED` comment after every line that has a
+/// surviving mutant in `report`, sorted by line. Python sources get `#` comments.
+pub fn annotate_source(source: &str, report: &ReportData) -> String {
+    let comment = if report.filename.ends_with(".py") {
+        "#"
+    } else {
+        "//"
     };
 
-    // Save report
-    save_report(report_data, &original_file_path).await?;
+    let mut by_line: BTreeMap<usize, &Vec<MutantInfo>> = BTreeMap::new();
+    for (line, mutants) in &report.diffs {
+        if let Ok(line) = line.parse() {
+            by_line.insert(line, mutants);
+        }
+    }
+
+    let mut annotated = String::with_capacity(source.len());
+    for (i, line) in source.lines().enumerate() {
+        annotated.push_str(line);
+        annotated.push('\n');
+        let Some(mutants) = by_line.get(&(i + 1)) else {
+            continue;
+        };
+        let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+        for mutant in mutants.iter() {
+            let operator = if mutant.operator.is_empty() {
+                String::new()
+            } else {
+                format!("{} ", mutant.operator)
+            };
+            annotated.push_str(&format!(
+                "{}{} SURVIVED: {}-{} +{}\n",
+                indent, comment, operator, mutant.original_line, mutant.mutated_line
+            ));
+        }
+    }
+    annotated
+}
+
+/// Write a JUnit XML report with one `<testsuite>` per target file. Killed
+/// mutants pass; surviving mutants fail with their diff as the failure body.
+/// `destination` may be `-` to print the XML to stdout.
+pub fn generate_junit_report(results: &[FolderResult], destination: &Path) -> Result<()> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    for result in results {
+        let failures = result
+            .mutants
+            .iter()
+            .filter(|m| m.status == "survived")
+            .count();
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            xml_escape(&result.target_file),
+            result.mutants.len(),
+            failures
+        ));
+        for mutant in &result.mutants {
+            let name = xml_escape(&mutant.name);
+            let classname = xml_escape(&result.target_file);
+            if mutant.status == "survived" {
+                xml.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\">\n      <failure message=\"Mutant survived\">{}</failure>\n    </testcase>\n",
+                    name,
+                    classname,
+                    xml_escape(mutant.diff.as_deref().unwrap_or(""))
+                ));
+            } else if mutant.status == "build_failed" || mutant.status == "error" {
+                let message = if mutant.status == "error" {
+                    "Mutant did not apply to the target"
+                } else {
+                    "Mutant did not build"
+                };
+                xml.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\">\n      <skipped message=\"{}\"/>\n    </testcase>\n",
+                    name, classname, message
+                ));
+            } else {
+                xml.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\"/>\n",
+                    name, classname
+                ));
+            }
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+
+    if is_stdout(destination) {
+        print!("{}", xml);
+    } else {
+        fs::write(destination, xml)?;
+        info!("JUnit report saved to {}", destination.display());
+    }
+    Ok(())
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Render the report for the surviving mutants of a run stored in SQLite,
+/// without re-running any analysis.
+pub async fn generate_report_from_db(
+    db_path: &Path,
+    run_id: i64,
+    destination: &Path,
+    mode: ReportMode,
+) -> Result<()> {
+    let db = Database::open(db_path)?;
+    db.ensure_schema()?;
+
+    let commit = db.get_run_commit(run_id)?.ok_or_else(|| {
+        MutationError::InvalidInput(format!(
+            "Run id {} not found in {}",
+            run_id,
+            db_path.display()
+        ))
+    })?;
+
+    // Scores count statuses the way a default analysis does.
+    let options = AnalysisOptions::default();
+    // Every analyzed file is reported, along with any file that has survivors.
+    let mut diffs_by_file: BTreeMap<String, Vec<(String, String)>> = db
+        .get_file_kill_counts(run_id, |status| options.counts_as_killed(status))?
+        .into_iter()
+        .filter(|(_, _, analyzed)| *analyzed > 0)
+        .map(|(file, _, _)| (file, Vec::new()))
+        .collect();
+    for mutant in db.get_mutants_for_run(run_id, None, true)? {
+        diffs_by_file
+            .entry(mutant.file_path.unwrap_or_default())
+            .or_default()
+            .push((mutant.diff, mutant.operator.unwrap_or_default()));
+    }
+    if diffs_by_file.is_empty() {
+        info!("No analyzed mutants for run_id={}", run_id);
+        return Ok(());
+    }
+
+    let counts: HashMap<String, (u64, u64)> = db
+        .get_file_kill_counts(run_id, |status| options.counts_as_killed(status))?
+        .into_iter()
+        .map(|(file, killed, analyzed)| (file, (killed, analyzed)))
+        .collect();
+
+    let mut reports = Vec::new();
+    for (file_path, diffs) in diffs_by_file {
+        info!("{}: {} surviving mutants", file_path, diffs.len());
+
+        let (killed, analyzed) = counts.get(&file_path).copied().unwrap_or_default();
+        let score = if analyzed > 0 {
+            killed as f64 / analyzed as f64
+        } else {
+            0.0
+        };
+        reports.push(ReportData {
+            total_mutants: analyzed,
+            killed_mutants: killed,
+            ..ReportData::new(
+                &file_path,
+                &commit,
+                score,
+                parse_diffs_with_commit(&diffs, &commit)?,
+            )
+        });
+    }
+
+    save_reports(reports, destination, mode)
+}
+
+/// Open an existing database for the read-only commands.
+fn open_existing_db(db_path: &Path) -> Result<Database> {
+    if !db_path.exists() {
+        return Err(MutationError::InvalidInput(format!(
+            "Database {} does not exist",
+            db_path.display()
+        )));
+    }
+    let db = Database::open(db_path)?;
+    db.ensure_schema()?;
+    Ok(db)
+}
+
+/// Delete a run and its mutants from the database.
+pub fn delete_run(db_path: &Path, run_id: i64) -> Result<()> {
+    let db = open_existing_db(db_path)?;
+    let mutants = db.delete_run(run_id)?.ok_or_else(|| {
+        MutationError::InvalidInput(format!(
+            "Run id {} not found in {}",
+            run_id,
+            db_path.display()
+        ))
+    })?;
+    println!("Deleted run {} and its {} mutants", run_id, mutants);
+    Ok(())
+}
+
+/// Print every run stored in the database with its mutant counts and score,
+/// as a table or, with `json`, as a JSON array.
+pub fn print_runs(db_path: &Path, json: bool) -> Result<()> {
+    let db = open_existing_db(db_path)?;
+    let runs = db.list_runs()?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&runs)?);
+        return Ok(());
+    }
+
+    if runs.is_empty() {
+        println!("No runs found in {}", db_path.display());
+        return Ok(());
+    }
+
+    println!(
+        "{:>6}  {:<12}  {:>6}  {:<19}  {:>7}  {:>7}  {:>8}  {:>7}",
+        "RUN", "COMMIT", "PR", "DATE", "TOTAL", "KILLED", "SURVIVED", "SCORE"
+    );
+    for run in &runs {
+        let commit: String = run.commit_hash.chars().take(12).collect();
+        let pr = run.pr_number.map(|n| n.to_string()).unwrap_or_default();
+        let score = run
+            .score
+            .map(|s| format!("{:.1}%", s * 100.0))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:>6}  {:<12}  {:>6}  {:<19}  {:>7}  {:>7}  {:>8}  {:>7}",
+            run.id,
+            commit,
+            pr,
+            run.created_at.as_deref().unwrap_or(""),
+            run.total,
+            run.killed,
+            run.survived,
+            score
+        );
+    }
 
     Ok(())
 }
 
+/// Filters of the `mutants` command.
+#[derive(Debug, Default)]
+pub struct MutantQuery {
+    pub run_id: i64,
+    pub status: Option<String>,
+    pub file_path: Option<String>,
+    /// Print counts and kill rates per group instead of the mutants.
+    pub group_by: Option<GroupColumn>,
+}
+
+/// Print the mutants of a run matching `query` with their diffs or, with
+/// `group_by`, their counts per operator or file. `json` prints a JSON array.
+pub fn print_mutants(db_path: &Path, query: &MutantQuery, json: bool) -> Result<()> {
+    let db = open_existing_db(db_path)?;
+    let file_path = query.file_path.as_deref();
+
+    if let Some(column) = query.group_by {
+        let groups = db.count_mutants_by(query.run_id, column, file_path)?;
+        if json {
+            println!("{}", serde_json::to_string_pretty(&groups)?);
+            return Ok(());
+        }
+        let header = match column {
+            GroupColumn::Operator => "OPERATOR",
+            GroupColumn::File => "FILE",
+        };
+        println!(
+            "{:>7}  {:>7}  {:>8}  {:>9}  {}",
+            "TOTAL", "KILLED", "SURVIVED", "KILL RATE", header
+        );
+        for group in &groups {
+            let kill_rate = group
+                .kill_rate()
+                .map(|r| format!("{:.1}%", r * 100.0))
+                .unwrap_or_else(|| "-".to_string());
+            println!(
+                "{:>7}  {:>7}  {:>8}  {:>9}  {}",
+                group.total,
+                group.killed,
+                group.survived,
+                kill_rate,
+                group.key.as_deref().unwrap_or("-")
+            );
+        }
+        return Ok(());
+    }
+
+    let mutants = db.query_mutants(query.run_id, query.status.as_deref(), file_path)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&mutants)?);
+        return Ok(());
+    }
+    if mutants.is_empty() {
+        println!("No matching mutants in run {}", query.run_id);
+        return Ok(());
+    }
+    for mutant in &mutants {
+        println!(
+            "#{} {} [{}] {}",
+            mutant.id,
+            mutant.file_path.as_deref().unwrap_or("-"),
+            mutant.operator.as_deref().unwrap_or("-"),
+            mutant.status
+        );
+        if let Some(ref command) = mutant.command_to_test {
+            println!("$ {}", command);
+        }
+        println!("{}", mutant.diff.trim_end());
+        println!();
+    }
+    Ok(())
+}
+
+/// A run compared by the `compare` command: its id in SQLite, or a JSON report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunSource {
+    Run(i64),
+    Report(PathBuf),
+}
+
+impl FromStr for RunSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s.parse() {
+            Ok(run_id) => RunSource::Run(run_id),
+            Err(_) => RunSource::Report(PathBuf::from(s)),
+        })
+    }
+}
+
+impl fmt::Display for RunSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunSource::Run(run_id) => write!(f, "run {}", run_id),
+            RunSource::Report(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl RunSource {
+    /// The surviving mutants of the run: in insertion order for a run, by file
+    /// and line for a report.
+    fn survivors(&self, db_path: &Path) -> Result<Vec<Survivor>> {
+        match self {
+            RunSource::Run(run_id) => {
+                let db = open_existing_db(db_path)?;
+                if db.get_run_commit(*run_id)?.is_none() {
+                    return Err(MutationError::InvalidInput(format!(
+                        "Run id {} not found in {}",
+                        run_id,
+                        db_path.display()
+                    )));
+                }
+                Ok(db
+                    .get_mutants_for_run(*run_id, None, true)?
+                    .into_iter()
+                    .map(|mutant| {
+                        Survivor::new(
+                            mutant.patch_hash,
+                            mutant.file_path.unwrap_or_default(),
+                            mutant.operator.unwrap_or_default(),
+                            mutant.diff,
+                        )
+                    })
+                    .collect())
+            }
+            RunSource::Report(path) => {
+                let mut survivors = Vec::new();
+                for file in RunReport::read_files(path)? {
+                    let mut lines: Vec<_> = file.diffs.into_iter().collect();
+                    lines.sort_by_key(|(line, _)| line.parse::<usize>().unwrap_or(usize::MAX));
+                    for mutant in lines.into_iter().flat_map(|(_, mutants)| mutants) {
+                        // Older reports only have the hunk to hash.
+                        let patch_hash = if mutant.patch_hash.is_empty() {
+                            compute_patch_hash(&mutant.diff)
+                        } else {
+                            mutant.patch_hash
+                        };
+                        survivors.push(Survivor::new(
+                            patch_hash,
+                            file.filename.clone(),
+                            mutant.operator,
+                            mutant.diff,
+                        ));
+                    }
+                }
+                Ok(survivors)
+            }
+        }
+    }
+}
+
+/// A surviving mutant, as listed by the `compare` command.
+#[derive(Debug, Clone, Serialize)]
+pub struct Survivor {
+    pub patch_hash: String,
+    pub file_path: String,
+    pub operator: String,
+    pub diff: String,
+    /// What the survivor is matched on across runs, see [`survivor_identity`].
+    #[serde(skip)]
+    identity: String,
+}
+
+impl Survivor {
+    fn new(patch_hash: String, file_path: String, operator: String, diff: String) -> Self {
+        let identity = survivor_identity(&file_path, &operator, &diff);
+        Survivor {
+            patch_hash,
+            file_path,
+            operator,
+            diff,
+            identity,
+        }
+    }
+}
+
+/// Identify a surviving mutant by its file, operator and the lines it removes
+/// and adds. Unlike its patch hash, this ignores the diff header, the `index`
+/// line (blob hashes of the whole file) and the hunk positions and context,
+/// which all change when another part of the file does.
+fn survivor_identity(file_path: &str, operator: &str, diff: &str) -> String {
+    let changes: Vec<&str> = diff
+        .lines()
+        .skip_while(|line| !line.starts_with("@@"))
+        .filter(|line| line.starts_with(['-', '+']))
+        .collect();
+    compute_patch_hash(&format!(
+        "{}\n{}\n{}",
+        file_path,
+        operator,
+        changes.join("\n")
+    ))
+}
+
+/// The survivors of a run compared to those of a baseline, by file, operator
+/// and changed lines.
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    pub baseline: String,
+    pub current: String,
+    pub baseline_survivors: usize,
+    pub current_survivors: usize,
+    /// Baseline survivors that are killed or no longer generated in the current run.
+    pub resolved: usize,
+    /// Current survivors that did not survive in the baseline.
+    pub new_survivors: Vec<Survivor>,
+}
+
+/// Compare the survivors of `current` with those of `baseline`. Runs are read
+/// from the database at `db_path`.
+pub fn compare_runs(
+    baseline: &RunSource,
+    current: &RunSource,
+    db_path: &Path,
+) -> Result<Comparison> {
+    let baseline_survivors = baseline.survivors(db_path)?;
+    let current_survivors = current.survivors(db_path)?;
+
+    let baseline_hashes: HashSet<&str> = baseline_survivors
+        .iter()
+        .map(|survivor| survivor.identity.as_str())
+        .collect();
+    let current_hashes: HashSet<&str> = current_survivors
+        .iter()
+        .map(|survivor| survivor.identity.as_str())
+        .collect();
+    let new_survivors = current_survivors
+        .iter()
+        .filter(|survivor| !baseline_hashes.contains(survivor.identity.as_str()))
+        .cloned()
+        .collect();
+
+    Ok(Comparison {
+        baseline: baseline.to_string(),
+        current: current.to_string(),
+        baseline_survivors: baseline_hashes.len(),
+        current_survivors: current_hashes.len(),
+        resolved: baseline_hashes.difference(&current_hashes).count(),
+        new_survivors,
+    })
+}
+
+/// Print the survivors of `current` that did not survive in `baseline`, as
+/// text or, with `json`, as a JSON [`Comparison`]. Fails with
+/// [`MutationError::NewSurvivors`] if there are any.
+pub fn print_comparison(
+    baseline: &RunSource,
+    current: &RunSource,
+    db_path: &Path,
+    json: bool,
+) -> Result<()> {
+    let comparison = compare_runs(baseline, current, db_path)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+    } else {
+        println!(
+            "Baseline ({}): {} surviving mutants",
+            comparison.baseline, comparison.baseline_survivors
+        );
+        println!(
+            "Current ({}): {} surviving mutants",
+            comparison.current, comparison.current_survivors
+        );
+        println!(
+            "{} baseline survivors no longer survive",
+            comparison.resolved
+        );
+        if comparison.new_survivors.is_empty() {
+            println!("No new surviving mutants");
+        } else {
+            println!("{} new surviving mutants:", comparison.new_survivors.len());
+            for survivor in &comparison.new_survivors {
+                let operator = match survivor.operator.as_str() {
+                    "" => "-",
+                    operator => operator,
+                };
+                println!("{} [{}]", survivor.file_path, operator);
+                println!("{}", survivor.diff.trim_end());
+                println!();
+            }
+        }
+    }
+
+    match comparison.new_survivors.len() {
+        0 => Ok(()),
+        n => Err(MutationError::NewSurvivors(n)),
+    }
+}
+
+/// Run arid node detection on `file`, with `expert_rule` on top of the
+/// built-in rules, and print which lines would be mutated and why the others
+/// would not.
+pub fn print_ast(file: &Path, expert_rule: Option<&str>, json: bool) -> Result<()> {
+    let content = fs::read_to_string(file)?;
+    let mut detector = AridNodeDetector::new()?;
+    if let Some(rule) = expert_rule {
+        detector.add_expert_rule(rule, "Custom user rule")?;
+    }
+    let analysis = detector.analyze_file_detailed(&content);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&analysis)?);
+        return Ok(());
+    }
+    analysis.print_summary();
+    analysis.print_arid_lines();
+    Ok(())
+}
+
+/// Print the operators of `mode`, named as they are recorded with their mutants.
+pub fn print_operators(mode: OperatorMode, only: Option<&str>, json: bool) -> Result<()> {
+    let mut operators = mode.operators()?;
+    if let Some(name) = only {
+        check_operator_name(name).map_err(MutationError::InvalidInput)?;
+        operators.retain(|operator| operator.is_named(name));
+        if operators.is_empty() {
+            let modes: Vec<String> = OperatorMode::ALL
+                .into_iter()
+                .filter(|other| {
+                    other
+                        .operators()
+                        .is_ok_and(|operators| operators.iter().any(|op| op.is_named(name)))
+                })
+                .map(|other| format!("--mode {:?}", other).to_lowercase())
+                .collect();
+            return Err(MutationError::InvalidInput(format!(
+                "operator '{}' is not a {:?} operator; list it with {}",
+                name,
+                mode,
+                modes.join(" or ")
+            )));
+        }
+    }
+    if json {
+        let operators: Vec<_> = operators
+            .iter()
+            .map(|operator| {
+                serde_json::json!({
+                    "name": operator.name(),
+                    "description": operator.description,
+                    "pattern": operator.pattern.as_str(),
+                    "replacement": operator.replacement,
+                    "skip_if": operator.skip_if.as_ref().map(|guard| guard.as_str()),
+                    "skip_within": operator.skip_within.as_ref().map(|guard| guard.as_str()),
+                    "only_if": operator.only_if.map(|(name, _)| name),
+                    "priority": operator.priority,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&operators)?);
+        return Ok(());
+    }
+
+    println!("{} {:?} operators:", operators.len(), mode);
+    for (i, operator) in operators.iter().enumerate() {
+        let mut line = format!(
+            "{:>4}  {}: {}",
+            i + 1,
+            operator.description,
+            operator.name()
+        );
+        if let Some(ref guard) = operator.skip_if {
+            line.push_str(&format!("  (unless {})", guard));
+        }
+        if let Some(ref guard) = operator.skip_within {
+            line.push_str(&format!("  (except inside {})", guard));
+        }
+        if let Some((only_if, _)) = operator.only_if {
+            line.push_str(&format!("  (only on a {})", only_if));
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
 async fn restore_original_file(file_path: &str) -> Result<()> {
     let output = Command::new("git")
-        .args(&["checkout", "--", file_path])
+        .args(["checkout", "--", file_path])
         .output()
         .map_err(|e| MutationError::Git(format!("Failed to restore file: {}", e)))?;
 
@@ -96,28 +897,29 @@ async fn restore_original_file(file_path: &str) -> Result<()> {
     Ok(())
 }
 
-async fn get_git_diff(original_file: &str, modified_file: &str) -> Result<String> {
-    let output = Command::new("git")
-        .args(&["diff", "--no-index", original_file, modified_file])
-        .output()
-        .map_err(|e| MutationError::Git(format!("Failed to get git diff: {}", e)))?;
-
-    // git diff --no-index returns exit code 1 when files differ, which is expected
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    Ok(stdout.to_string())
+fn parse_diffs_to_json(
+    diffs_list: &[String],
+    commit: &str,
+) -> Result<HashMap<String, Vec<MutantInfo>>> {
+    // Folder mutants carry no generation metadata, so the operator is unknown.
+    let diffs: Vec<(String, String)> = diffs_list
+        .iter()
+        .map(|diff| (diff.clone(), String::new()))
+        .collect();
+    parse_diffs_with_commit(&diffs, commit)
 }
 
-async fn parse_diffs_to_json(diffs_list: &[String]) -> Result<HashMap<String, Vec<MutantInfo>>> {
+/// Group `(diff, operator)` pairs by the source line they change.
+fn parse_diffs_with_commit(
+    diffs_list: &[(String, String)],
+    commit: &str,
+) -> Result<HashMap<String, Vec<MutantInfo>>> {
     let mut result = HashMap::new();
-    let line_regex = Regex::new(r"@@ -(\d+),")?;
-    let commit = get_git_hash().await?;
+    let hunk_regex = Regex::new(r"^@@ -\d+(?:,\d+)? \+(\d+)(?:,\d+)? @@")?;
 
-    for diff in diffs_list {
-        if let Some(captures) = line_regex.captures(diff) {
-            let line_num = captures[1].parse::<usize>().map_err(|_| {
-                MutationError::InvalidInput("Invalid line number in diff".to_string())
-            })?;
-            let line_key = (line_num + 3).to_string();
+    for (diff, operator) in diffs_list {
+        if let Some(change) = first_change(diff, &hunk_regex)? {
+            let line_key = change.line.to_string();
 
             let entry = result.entry(line_key).or_insert_with(Vec::new);
 
@@ -130,9 +932,14 @@ async fn parse_diffs_to_json(diffs_list: &[String]) -> Result<HashMap<String, Ve
 
             entry.push(MutantInfo {
                 id: entry.len() + 1,
-                commit: commit.clone(),
+                commit: commit.to_string(),
                 diff: diff_content.to_string(),
+                patch_hash: compute_patch_hash(diff),
                 status: "alive".to_string(),
+                operator: operator.clone(),
+                description: describe_operator(operator).unwrap_or_default(),
+                original_line: change.original_line,
+                mutated_line: change.mutated_line,
             });
         }
     }
@@ -140,9 +947,75 @@ async fn parse_diffs_to_json(diffs_list: &[String]) -> Result<HashMap<String, Ve
     Ok(result)
 }
 
-async fn get_git_hash() -> Result<String> {
+/// The (1-indexed) line of the target changed first by `diff`, if any.
+pub(crate) fn changed_line(diff: &str) -> Option<usize> {
+    let hunk_regex = Regex::new(r"^@@ -\d+(?:,\d+)? \+(\d+)(?:,\d+)? @@").ok()?;
+    first_change(diff, &hunk_regex)
+        .ok()
+        .flatten()
+        .map(|change| change.line)
+}
+
+/// The first change in a diff: where it landed and the lines it swapped.
+struct DiffChange {
+    line: usize,
+    original_line: String,
+    mutated_line: String,
+}
+
+/// Find the first change in `diff`. The line number is the new-side line,
+/// found by walking the first hunk from its `+c` start past the leading context.
+fn first_change(diff: &str, hunk_regex: &Regex) -> Result<Option<DiffChange>> {
+    let mut new_line: Option<usize> = None;
+    let mut change: Option<DiffChange> = None;
+    for line in diff.lines() {
+        if let Some(captures) = hunk_regex.captures(line) {
+            if change.is_some() {
+                break;
+            }
+            let start = captures[1].parse::<usize>().map_err(|_| {
+                MutationError::InvalidInput("Invalid line number in diff".to_string())
+            })?;
+            new_line = Some(start);
+            continue;
+        }
+        let Some(current) = new_line else {
+            continue;
+        };
+        let removed = line.strip_prefix('-');
+        let added = line.strip_prefix('+');
+        if removed.is_none() && added.is_none() {
+            // Context after the first change ends it.
+            if change.is_some() {
+                break;
+            }
+            if line.starts_with(' ') {
+                new_line = Some(current + 1);
+            }
+            continue;
+        }
+        let change = change.get_or_insert_with(|| DiffChange {
+            line: current,
+            original_line: String::new(),
+            mutated_line: String::new(),
+        });
+        if let Some(text) = removed {
+            if change.original_line.is_empty() {
+                change.original_line = text.trim().to_string();
+            }
+        }
+        if let Some(text) = added {
+            if change.mutated_line.is_empty() {
+                change.mutated_line = text.trim().to_string();
+            }
+        }
+    }
+    Ok(change)
+}
+
+pub(crate) async fn get_git_hash() -> Result<String> {
     let output = Command::new("git")
-        .args(&["log", "--pretty=format:%h", "-n", "1"])
+        .args(["log", "--pretty=format:%h", "-n", "1"])
         .output()
         .map_err(|e| MutationError::Git(format!("Failed to get git hash: {}", e)))?;
 
@@ -154,33 +1027,38 @@ async fn get_git_hash() -> Result<String> {
     }
 }
 
-async fn save_report(report_data: ReportData, _original_file_path: &str) -> Result<()> {
-    let json_file = "diff_not_killed.json";
+/// Write `reports` as the [`RunReport`] at `destination`, or print them as one
+/// when `destination` is `-`. With [`ReportMode::Append`] they are added to the
+/// existing report, replacing earlier reports of the same file and commit, and
+/// the overall score is updated from the latest report of each file. Nothing is written if `reports` is empty.
+pub fn save_reports(reports: Vec<ReportData>, destination: &Path, mode: ReportMode) -> Result<()> {
+    if reports.is_empty() {
+        return Ok(());
+    }
 
-    let final_data = if Path::new(json_file).exists() {
-        // Load existing data and append
-        let existing_content = fs::read_to_string(json_file)?;
-        let mut existing_data: serde_json::Value = serde_json::from_str(&existing_content)?;
+    if is_stdout(destination) {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&RunReport::new(reports))?
+        );
+        return Ok(());
+    }
 
-        match existing_data {
-            serde_json::Value::Array(ref mut arr) => {
-                arr.push(serde_json::to_value(report_data)?);
-                existing_data
-            }
-            _ => {
-                // Convert single object to array and append
-                serde_json::json!([existing_data, report_data])
-            }
-        }
+    // A file analyzed again at the same commit replaces its earlier report.
+    let mut files = if mode == ReportMode::Append && destination.exists() {
+        RunReport::read_files(destination)?
     } else {
-        // Create new array with this report
-        serde_json::json!([report_data])
+        Vec::new()
     };
+    for report in reports {
+        files.retain(|file| (&file.filename, &file.commit) != (&report.filename, &report.commit));
+        files.push(report);
+    }
 
-    let json_content = serde_json::to_string_pretty(&final_data)?;
-    fs::write(json_file, json_content)?;
+    let json_content = serde_json::to_string_pretty(&RunReport::new(files))?;
+    fs::write(destination, json_content)?;
 
-    println!("Report saved to {}", json_file);
+    info!("Report saved to {}", destination.display());
     Ok(())
 }
 
@@ -188,25 +1066,459 @@ async fn save_report(report_data: ReportData, _original_file_path: &str) -> Resu
 mod tests {
     use super::*;
 
-    #[tokio::test]
-    async fn test_parse_diffs_to_json() {
+    #[test]
+    fn test_parse_diffs_to_json() {
         let diffs = vec![
             "@@ -10,3 +11,5 @@ some context\n-old line\n+new line".to_string(),
             "@@ -20,1 +21,1 @@ other context\n-another old\n+another new".to_string(),
         ];
 
-        let result = parse_diffs_to_json(&diffs).await.unwrap();
+        let result = parse_diffs_to_json(&diffs, "abc").unwrap();
 
         assert_eq!(result.len(), 2);
-        assert!(result.contains_key("13")); // 10 + 3
-        assert!(result.contains_key("23")); // 20 + 3
+        // No leading context: the change is on the first line of the new-side range.
+        assert!(result.contains_key("11"));
+        assert!(result.contains_key("21"));
 
-        let first_entry = &result["13"][0];
+        let first_entry = &result["11"][0];
         assert_eq!(first_entry.id, 1);
+        assert_eq!(first_entry.commit, "abc");
         assert_eq!(first_entry.status, "alive");
         assert!(first_entry.diff.contains("@@"));
     }
 
+    #[test]
+    fn test_parse_diffs_keys_by_changed_line() {
+        let multi_hunk = [
+            "diff --git a/src/x.cpp b/src/x.cpp",
+            "--- a/src/x.cpp",
+            "+++ b/src/x.cpp",
+            "@@ -5,7 +5,7 @@ void f()",
+            " ctx5",
+            " ctx6",
+            " ctx7",
+            "-    if (a < b) {",
+            "+    if (a <= b) {",
+            " ctx9",
+            " ctx10",
+            " ctx11",
+            "@@ -40,3 +40,3 @@",
+            " ctx40",
+            "-    return x;",
+            "+    return y;",
+            " ctx42",
+        ]
+        .join("\n");
+        // One line of context, as produced with a different -U setting.
+        let short_context = "@@ -29,3 +29,3 @@\n ctx\n-a + b\n+a - b\n ctx\n".to_string();
+
+        let result = parse_diffs_with_commit(
+            &[
+                (multi_hunk, "LessThan".to_string()),
+                (short_context, String::new()),
+            ],
+            "abc",
+        )
+        .unwrap();
+        assert_eq!(result.len(), 2);
+        let first = &result["8"][0];
+        assert_eq!(first.operator, "LessThan");
+        assert_eq!(first.original_line, "if (a < b) {");
+        assert_eq!(first.mutated_line, "if (a <= b) {");
+        assert_eq!(result["30"][0].mutated_line, "a - b");
+    }
+
+    #[tokio::test]
+    async fn test_generate_report_from_db() {
+        use crate::db::MutantData;
+        use crate::test_util::enter_dir;
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let _cwd = enter_dir(temp_dir.path());
+        let db_path = temp_dir.path().join("mutation.db");
+
+        let mut db = Database::open(&db_path).unwrap();
+        db.ensure_schema().unwrap();
+        db.seed_projects().unwrap();
+        let project_id = db.get_bitcoin_core_project_id().unwrap();
+        let run_id = db
+            .create_run(project_id, "abc123", "0.0.0", None, None, None)
+            .unwrap();
+        db.insert_mutant_batch(
+            run_id,
+            &[
+                MutantData {
+                    diff: "@@ -10,3 +10,3 @@\n-a > b\n+a >= b\n".to_string(),
+                    patch_hash: "h1".to_string(),
+                    file_path: "src/test.cpp".to_string(),
+                    operator: "op".to_string(),
+                },
+                MutantData {
+                    diff: "@@ -20,3 +20,3 @@\n-a == b\n+a != b\n".to_string(),
+                    patch_hash: "h2".to_string(),
+                    file_path: "src/test.cpp".to_string(),
+                    operator: "op".to_string(),
+                },
+            ],
+        )
+        .unwrap();
+        db.update_status_mutant(run_id, "h1", "survived", "make check")
+            .unwrap();
+        db.update_status_mutant(run_id, "h2", "killed", "make check")
+            .unwrap();
+
+        // Unknown runs are rejected.
+        let destination = ReportFormat::Json.default_path();
+        assert!(
+            generate_report_from_db(&db_path, run_id + 1, destination, ReportMode::Overwrite)
+                .await
+                .is_err()
+        );
+
+        generate_report_from_db(&db_path, run_id, destination, ReportMode::Overwrite)
+            .await
+            .unwrap();
+        let content = fs::read_to_string("diff_not_killed.json").unwrap();
+        let report: RunReport = serde_json::from_str(&content).unwrap();
+        assert_eq!(report.mutation_score, 0.5);
+        let reports = report.files;
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].filename, "src/test.cpp");
+        assert_eq!(reports[0].commit, "abc123");
+        assert_eq!(reports[0].mutation_score, 0.5);
+        assert_eq!(
+            (reports[0].killed_mutants, reports[0].total_mutants),
+            (1, 2)
+        );
+        let mutants: Vec<&MutantInfo> = reports[0].diffs.values().flatten().collect();
+        assert_eq!(mutants.len(), 1);
+        assert_eq!(mutants[0].commit, "abc123");
+        assert_eq!(mutants[0].operator, "op");
+        assert_eq!(mutants[0].mutated_line, "a >= b");
+    }
+
+    #[tokio::test]
+    async fn test_compare_runs() {
+        use crate::db::MutantData;
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("mutation.db");
+        let mut db = Database::open(&db_path).unwrap();
+        db.ensure_schema().unwrap();
+        db.seed_projects().unwrap();
+        let project_id = db.get_bitcoin_core_project_id().unwrap();
+
+        let diff = |line: usize| format!("@@ -{0},1 +{0},1 @@\n-a{0} > b\n+a{0} >= b\n", line);
+        // Mutants of lines 1 and 2 survive the baseline; 2 and 3 survive the current run.
+        let mut run = |commit: &str, survived: &[usize]| {
+            let run_id = db
+                .create_run(project_id, commit, "0.0.0", None, None, None)
+                .unwrap();
+            let mutants: Vec<MutantData> = (1..=3)
+                .map(|line| MutantData {
+                    diff: diff(line),
+                    patch_hash: compute_patch_hash(&diff(line)),
+                    file_path: "src/test.cpp".to_string(),
+                    operator: "op".to_string(),
+                })
+                .collect();
+            db.insert_mutant_batch(run_id, &mutants).unwrap();
+            for line in 1..=3 {
+                let status = if survived.contains(&line) {
+                    "survived"
+                } else {
+                    "killed"
+                };
+                db.update_status_mutant(run_id, &compute_patch_hash(&diff(line)), status, "make")
+                    .unwrap();
+            }
+            run_id
+        };
+        let baseline = RunSource::Run(run("abc", &[1, 2]));
+        let current = RunSource::Run(run("def", &[2, 3]));
+
+        let comparison = compare_runs(&baseline, &current, &db_path).unwrap();
+        assert_eq!(comparison.baseline, "run 1");
+        assert_eq!(
+            (comparison.baseline_survivors, comparison.current_survivors),
+            (2, 2)
+        );
+        assert_eq!(comparison.resolved, 1);
+        assert_eq!(comparison.new_survivors.len(), 1);
+        assert_eq!(comparison.new_survivors[0].diff, diff(3));
+        assert!(matches!(
+            print_comparison(&baseline, &current, &db_path, true),
+            Err(MutationError::NewSurvivors(1))
+        ));
+        assert!(print_comparison(&current, &current, &db_path, true).is_ok());
+
+        // A JSON report of a run compares like the run itself.
+        let report_path = temp_dir.path().join("current.json");
+        generate_report_from_db(&db_path, 2, &report_path, ReportMode::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(
+            "current.json".parse::<RunSource>().unwrap(),
+            RunSource::Report(PathBuf::from("current.json"))
+        );
+        assert_eq!("7".parse::<RunSource>().unwrap(), RunSource::Run(7));
+        let comparison = compare_runs(&current, &RunSource::Report(report_path), &db_path).unwrap();
+        assert_eq!(comparison.current_survivors, 2);
+        assert!(comparison.new_survivors.is_empty());
+
+        assert!(compare_runs(&RunSource::Run(9), &current, &db_path).is_err());
+
+        // An unrelated edit to the file changes the index line, hunk position
+        // and context of a real diff, but the survivor is still the same.
+        let target = temp_dir.path().join("wallet.cpp");
+        let mutated = temp_dir.path().join("wallet.mutant.cpp");
+        let mut real_diffs = Vec::new();
+        for source in [
+            "int x;\nif (a > b) f();\n",
+            "// header\nint x;\nint y;\nif (a > b) f();\n",
+        ] {
+            fs::write(&target, source).unwrap();
+            fs::write(&mutated, source.replace("a > b", "a >= b")).unwrap();
+            let diff = generate_diff_from_file(target.to_str().unwrap(), &mutated)
+                .await
+                .unwrap();
+            real_diffs.push(diff);
+        }
+        assert_ne!(
+            compute_patch_hash(&real_diffs[0]),
+            compute_patch_hash(&real_diffs[1])
+        );
+        let runs: Vec<RunSource> = real_diffs
+            .iter()
+            .map(|diff| {
+                let run_id = db
+                    .create_run(project_id, "abc", "0.0.0", None, None, None)
+                    .unwrap();
+                let mutant = MutantData {
+                    diff: diff.clone(),
+                    patch_hash: compute_patch_hash(diff),
+                    file_path: "src/wallet.cpp".to_string(),
+                    operator: "op".to_string(),
+                };
+                db.insert_mutant_batch(run_id, &[mutant]).unwrap();
+                db.update_status_mutant(run_id, &compute_patch_hash(diff), "survived", "make")
+                    .unwrap();
+                RunSource::Run(run_id)
+            })
+            .collect();
+        let comparison = compare_runs(&runs[0], &runs[1], &db_path).unwrap();
+        assert_eq!(comparison.current_survivors, 1);
+        assert!(comparison.new_survivors.is_empty());
+    }
+
+    #[test]
+    fn test_annotate_source() {
+        let mutant = |line: &str, original: &str, mutated: &str| {
+            (
+                line.to_string(),
+                vec![MutantInfo {
+                    id: 1,
+                    commit: "abc".to_string(),
+                    diff: String::new(),
+                    patch_hash: String::new(),
+                    status: "alive".to_string(),
+                    operator: String::new(),
+                    description: String::new(),
+                    original_line: original.to_string(),
+                    mutated_line: mutated.to_string(),
+                }],
+            )
+        };
+        let report = ReportData {
+            filename: "src/test.cpp".to_string(),
+            commit: "abc".to_string(),
+            mutation_score: 0.5,
+            total_mutants: 4,
+            killed_mutants: 2,
+            date: String::new(),
+            diffs: HashMap::from([
+                mutant("3", "return a;", "return b;"),
+                mutant("1", "int f() {", "int g() {"),
+            ]),
+        };
+        let source = "int f() {\n    int a = 1;\n    return a;\n}\n";
+
+        let expected = [
+            "int f() {",
+            "// SURVIVED: -int f() { +int g() {",
+            "    int a = 1;",
+            "    return a;",
+            "    // SURVIVED: -return a; +return b;",
+            "}",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(annotate_source(source, &report), expected);
+    }
+
+    #[test]
+    fn test_save_reports_updates_chosen_path() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("out.json");
+        let report = |filename: &str, killed: u64, total: u64| ReportData {
+            total_mutants: total,
+            killed_mutants: killed,
+            ..ReportData::new(
+                filename,
+                "abc",
+                killed as f64 / total as f64,
+                HashMap::new(),
+            )
+        };
+        let append = ReportMode::Append;
+        let read = |path: &Path| -> RunReport {
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+        };
+
+        save_reports(Vec::new(), &path, append).unwrap();
+        assert!(!path.exists());
+
+        save_reports(
+            vec![report("a.cpp", 1, 2), report("b.cpp", 3, 4)],
+            &path,
+            append,
+        )
+        .unwrap();
+        save_reports(vec![report("c.cpp", 4, 4)], &path, append).unwrap();
+        let saved = read(&path);
+        let names: Vec<&str> = saved.files.iter().map(|r| r.filename.as_str()).collect();
+        assert_eq!(names, ["a.cpp", "b.cpp", "c.cpp"]);
+        assert_eq!((saved.killed_mutants, saved.total_mutants), (8, 10));
+        assert_eq!(saved.mutation_score, 0.8);
+
+        // A file analyzed again at the same commit replaces its earlier report.
+        save_reports(vec![report("a.cpp", 2, 2)], &path, append).unwrap();
+        let saved = read(&path);
+        let names: Vec<&str> = saved.files.iter().map(|r| r.filename.as_str()).collect();
+        assert_eq!(names, ["b.cpp", "c.cpp", "a.cpp"]);
+        assert_eq!(saved.mutation_score, 0.9);
+
+        // At another commit, it is kept alongside the earlier one.
+        let other = ReportData {
+            commit: "def".to_string(),
+            ..report("a.cpp", 0, 2)
+        };
+        save_reports(vec![other], &path, append).unwrap();
+        let saved = read(&path);
+        assert_eq!(saved.files.len(), 4);
+        // Only the latest report of a file counts in the overall score.
+        assert_eq!((saved.killed_mutants, saved.total_mutants), (7, 10));
+
+        // Overwriting drops every earlier report.
+        save_reports(vec![report("d.cpp", 1, 4)], &path, ReportMode::Overwrite).unwrap();
+        let saved = read(&path);
+        assert_eq!(saved.files.len(), 1);
+        assert_eq!(saved.mutation_score, 0.25);
+
+        // Reports written as an array of files are converted.
+        let old = temp_dir.path().join("old.json");
+        fs::write(
+            &old,
+            r#"[{"filename":"a.cpp","mutation_score":0.5,"date":"","diffs":{}}]"#,
+        )
+        .unwrap();
+        save_reports(vec![report("b.cpp", 3, 4)], &old, append).unwrap();
+        let saved = read(&old);
+        assert_eq!(saved.files.len(), 2);
+        assert_eq!(saved.files[0].total_mutants, 0);
+        assert_eq!(saved.mutation_score, 0.75);
+
+        fs::write(&old, "not json").unwrap();
+        assert!(save_reports(vec![report("b.cpp", 3, 4)], &old, append).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_report_round_trip() {
+        let survivors = parse_diffs_with_commit(
+            &[(
+                "@@ -10,3 +10,3 @@\n-a > b\n+a >= b\n".to_string(),
+                "&& ==> ||".to_string(),
+            )],
+            "abc",
+        )
+        .unwrap();
+        let report = RunReport::new(vec![
+            ReportData {
+                total_mutants: 4,
+                killed_mutants: 3,
+                ..ReportData::new("src/wallet/wallet.cpp", "abc", 0.75, survivors)
+            },
+            ReportData {
+                total_mutants: 6,
+                killed_mutants: 6,
+                ..ReportData::new("src/validation.cpp", "abc", 1.0, HashMap::new())
+            },
+        ]);
+        assert_eq!((report.killed_mutants, report.total_mutants), (9, 10));
+        assert_eq!(report.mutation_score, 0.9);
+
+        let json = serde_json::to_string(&report).unwrap();
+        let read: RunReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.mutation_score, 0.9);
+        assert_eq!(read.date, report.date);
+        assert_eq!(read.files.len(), 2);
+        assert_eq!(read.files[0].killed_mutants, 3);
+        let survivor = &read.files[0].diffs["10"][0];
+        assert_eq!(survivor.description, "logical AND→OR");
+        assert!(read.files[1].diffs.is_empty());
+    }
+
+    #[test]
+    fn test_generate_junit_report() {
+        use crate::analyze::MutantResult;
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("mutation-junit.xml");
+        let results = vec![FolderResult {
+            target_file: "src/a&b.cpp".to_string(),
+            killed: 1,
+            total: 2,
+            mutants: vec![
+                MutantResult {
+                    name: "a.mutant.0.cpp".to_string(),
+                    status: "killed".to_string(),
+                    diff: None,
+                    operator: None,
+                },
+                MutantResult {
+                    name: "a.mutant.1.cpp".to_string(),
+                    status: "survived".to_string(),
+                    diff: Some("-if (a < b)\n+if (a <= b)".to_string()),
+                    operator: None,
+                },
+            ],
+            baseline_secs: None,
+            report: None,
+        }];
+
+        generate_junit_report(&results, &path).unwrap();
+        let xml = fs::read_to_string(&path).unwrap();
+        assert!(xml.contains(r#"<testsuite name="src/a&amp;b.cpp" tests="2" failures="1">"#));
+        assert!(xml.contains(r#"<testcase name="a.mutant.0.cpp" classname="src/a&amp;b.cpp"/>"#));
+        assert!(xml.contains("+if (a &lt;= b)</failure>"));
+    }
+
+    #[test]
+    fn test_print_operators_of_another_mode() {
+        let err = print_operators(OperatorMode::Regex, Some("comparison→assignment"), false)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("not a Regex operator; list it with --mode security"));
+        assert!(
+            print_operators(OperatorMode::Security, Some("comparison→assignment"), true).is_ok()
+        );
+    }
+
     #[test]
     fn test_report_data_serialization() {
         let mut diffs = HashMap::new();
@@ -216,13 +1528,21 @@ mod tests {
                 id: 1,
                 commit: "abc123".to_string(),
                 diff: "@@ test diff".to_string(),
+                patch_hash: String::new(),
                 status: "alive".to_string(),
+                operator: "op".to_string(),
+                description: String::new(),
+                original_line: "a < b".to_string(),
+                mutated_line: "a <= b".to_string(),
             }],
         );
 
         let report = ReportData {
             filename: "test.cpp".to_string(),
+            commit: "abc123".to_string(),
             mutation_score: 0.85,
+            total_mutants: 20,
+            killed_mutants: 17,
             date: "01/01/2024 12:00:00".to_string(),
             diffs,
         };
@@ -233,5 +1553,13 @@ mod tests {
         assert_eq!(deserialized.filename, "test.cpp");
         assert_eq!(deserialized.mutation_score, 0.85);
         assert_eq!(deserialized.diffs.len(), 1);
+        assert_eq!(deserialized.diffs["10"][0].mutated_line, "a <= b");
+
+        // Reports written before the operator/line fields existed still load.
+        let old: MutantInfo =
+            serde_json::from_str(r#"{"id":1,"commit":"abc","diff":"@@","status":"alive"}"#)
+                .unwrap();
+        assert!(old.operator.is_empty());
+        assert!(old.description.is_empty());
     }
 }
@@ -0,0 +1,55 @@
+//! Helpers shared by unit tests.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Mutex, MutexGuard};
+
+static CWD_LOCK: Mutex<()> = Mutex::new(());
+
+/// Keeps the process working directory pinned until dropped, then restores
+/// the crate root.
+pub struct CwdGuard {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Drop for CwdGuard {
+    fn drop(&mut self) {
+        let _ = std::env::set_current_dir(env!("CARGO_MANIFEST_DIR"));
+    }
+}
+
+/// Change into `dir` for the lifetime of the returned guard.
+///
+/// Tests share one working directory, so any test that changes it or spawns
+/// processes relying on it (e.g. `git`) must hold a guard; otherwise another
+/// test's temporary directory can be deleted out from under it.
+pub fn enter_dir(dir: &Path) -> CwdGuard {
+    let lock = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    std::env::set_current_dir(dir).unwrap();
+    CwdGuard { _lock: lock }
+}
+
+/// A `git` command with a test identity, so commits work without a global config.
+pub fn git_command() -> Command {
+    let mut command = Command::new("git");
+    command.args(["-c", "user.name=t", "-c", "user.email=t@t"]);
+    command
+}
+
+/// Run `git` with `args` in the working directory and assert it succeeds.
+pub fn git(args: &[&str]) {
+    let status = git_command().args(args).status().unwrap();
+    assert!(status.success(), "git {}", args.join(" "));
+}
+
+/// Create a git repository on `master` in the working directory, with `files`
+/// (path, content) written and committed.
+pub fn init_git_repo(files: &[(&str, &str)]) {
+    git(&["init", "-q", "-b", "master"]);
+    for (path, content) in files {
+        fs::write(path, content).unwrap();
+    }
+    git(&["add", "."]);
+    git(&["commit", "-q", "--allow-empty", "-m", "init"]);
+}